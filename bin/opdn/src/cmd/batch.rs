@@ -0,0 +1,68 @@
+//! Contains logic to generate the derivation fixtures listed in a manifest.
+
+use crate::cmd::{from_l1::FromL1, from_l2::FromL2};
+use clap::{ArgAction, Parser};
+use color_eyre::{eyre::ensure, Result};
+use fixture_manifest::{
+    Batch as ManifestBatch, BatchArgs, DerivationEntry, DerivationMode, Manifest,
+};
+use tracing::info;
+
+/// The logging target to use for [tracing].
+const TARGET: &str = "batch";
+
+/// CLI arguments for the `batch` subcommand of `opdn`.
+#[derive(Parser, Clone, Debug)]
+pub struct Batch {
+    /// Selects the manifest and whether up to date fixtures are regenerated.
+    #[command(flatten)]
+    pub batch: BatchArgs,
+    /// Verbosity level (0-4)
+    #[arg(long, short, help = "Verbosity level (0-4)", action = ArgAction::Count)]
+    pub v: u8,
+}
+
+impl Batch {
+    /// Runs the batch subcommand, which generates every derivation fixture of the
+    /// manifest that is not up to date, and prints a summary table.
+    pub async fn run(&self) -> Result<()> {
+        let manifest = Manifest::load(&self.batch.manifest)?;
+        info!(target: TARGET, "Generating {} derivation fixtures from {:?}", manifest.derivation.len(), self.batch.manifest);
+
+        let mut batch = ManifestBatch::new(&self.batch.manifest, self.batch.force)?;
+        for entry in &manifest.derivation {
+            let hash = match entry.content_hash(&manifest) {
+                Ok(hash) => hash,
+                Err(e) => {
+                    batch.fail("derivation", &entry.output, e);
+                    continue;
+                }
+            };
+            let output = manifest.resolve(&entry.output);
+            batch
+                .run("derivation", &entry.output, &output, hash, || {
+                    generate(&manifest, entry)
+                })
+                .await?;
+        }
+
+        let report = batch.finish();
+        println!("{report}");
+        ensure!(
+            report.failures() == 0,
+            "{} of {} derivation fixtures failed",
+            report.failures(),
+            report.rows.len()
+        );
+        Ok(())
+    }
+}
+
+/// Generates the fixture of a manifest entry with the `from-l1` or `from-l2` subcommand.
+async fn generate(manifest: &Manifest, entry: &DerivationEntry) -> Result<()> {
+    let args = entry.args(manifest)?;
+    match entry.mode {
+        DerivationMode::FromL1 => FromL1::try_parse_from(args)?.run().await,
+        DerivationMode::FromL2 => FromL2::try_parse_from(args)?.run().await,
+    }
+}
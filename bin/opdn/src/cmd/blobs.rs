@@ -1,103 +1,161 @@
 //! Blob Loading Module
 
-use alloy_consensus::{Transaction, TxEip4844Variant, TxEnvelope, TxType};
-use alloy_primitives::{Address, TxKind};
-use color_eyre::Result;
+use crate::codec::blob::{self, BlobKzg};
+use alloy_consensus::{Transaction, TxEip4844Variant, TxEnvelope};
+use alloy_primitives::{Address, Bytes, TxKind, B256};
+use async_trait::async_trait;
+use color_eyre::{
+    eyre::{ensure, eyre},
+    Result,
+};
+use derivation_driver::online::OnlineSidecarProvider;
 use tracing::warn;
 
-use kona_derive::online::{
-    OnlineBeaconClient, OnlineBlobProviderWithFallback, SimpleSlotDerivation,
-};
-use kona_derive::traits::BlobProvider;
-use kona_primitives::{Blob, BlockInfo, IndexedBlobHash};
+use kona_primitives::{Blob, BlobSidecar, BlockInfo, IndexedBlobHash};
+
+/// Serves the sidecars of the blobs in an L1 block, which carry the KZG commitment and
+/// proof of each blob.
+#[async_trait]
+pub trait SidecarProvider {
+    /// Returns the sidecars of the blobs with the given indexed hashes in the given L1
+    /// block, ordered by blob index.
+    async fn blob_sidecars(
+        &mut self,
+        block: &BlockInfo,
+        blob_hashes: &[IndexedBlobHash],
+    ) -> Result<Vec<BlobSidecar>>;
+}
+
+#[async_trait]
+impl SidecarProvider for OnlineSidecarProvider {
+    async fn blob_sidecars(
+        &mut self,
+        block: &BlockInfo,
+        blob_hashes: &[IndexedBlobHash],
+    ) -> Result<Vec<BlobSidecar>> {
+        self.sidecars(block, blob_hashes).await
+    }
+}
 
-/// Loads blobs for the given block number.
-pub async fn load(
+/// The fields of an L1 transaction that carry batch data.
+#[derive(Debug, Clone)]
+pub struct DaFields<'a> {
+    /// The recipient of the transaction.
+    pub to: TxKind,
+    /// The transaction calldata.
+    pub calldata: &'a Bytes,
+    /// The versioned hashes of the blobs referenced by the transaction.
+    /// Empty for transaction types that cannot carry blobs.
+    pub blob_hashes: &'a [B256],
+}
+
+/// Returns the data-availability fields of the transaction, or `None` if the
+/// transaction type is unknown.
+pub fn da_fields(tx: &TxEnvelope) -> Option<DaFields<'_>> {
+    let (to, calldata, blob_hashes): (_, _, &[B256]) = match tx {
+        TxEnvelope::Legacy(tx) => (tx.tx().to(), &tx.tx().input, &[]),
+        TxEnvelope::Eip2930(tx) => (tx.tx().to(), &tx.tx().input, &[]),
+        TxEnvelope::Eip1559(tx) => (tx.tx().to(), &tx.tx().input, &[]),
+        TxEnvelope::Eip4844(tx) => {
+            let tx = match tx.tx() {
+                TxEip4844Variant::TxEip4844(tx) => tx,
+                TxEip4844Variant::TxEip4844WithSidecar(tx) => tx.tx(),
+            };
+            (tx.to(), &tx.input, &tx.blob_versioned_hashes)
+        }
+        // This is necessary since `TxEnvelope` is marked as non-exhaustive.
+        _ => return None,
+    };
+    Some(DaFields {
+        to,
+        calldata,
+        blob_hashes,
+    })
+}
+
+/// Loads the blobs sent to the batch inbox by the batcher in the given block.
+///
+/// Every blob is verified, along with the KZG commitment and proof in its sidecar,
+/// against the versioned hash it is referenced by, so that a beacon endpoint returning
+/// wrong blobs is caught. Returns the blobs along with their KZG commitments and proofs.
+pub async fn load<S: SidecarProvider>(
     b: &BlockInfo,
     txs: &[TxEnvelope],
+    batch_inbox_address: Address,
     batcher_address: Address,
-    signer: Address,
-    provider: &mut OnlineBlobProviderWithFallback<
-        OnlineBeaconClient,
-        OnlineBeaconClient,
-        SimpleSlotDerivation,
-    >,
-) -> Result<Vec<Box<Blob>>> {
-    let blob_hashes = extract_blob_data(batcher_address, signer, txs);
+    provider: &mut S,
+) -> Result<Vec<(Box<Blob>, BlobKzg)>> {
+    let blob_hashes = extract_blob_data(batch_inbox_address, batcher_address, txs);
 
     // If there are no blob hashes, we can return empty.
     if blob_hashes.is_empty() {
         return Ok(vec![]);
     }
 
-    provider
-        .get_blobs(b, &blob_hashes)
-        .await
-        .map_err(|e| {
-            warn!(target: "blobs", "Failed to fetch blobs: {e}");
-            color_eyre::eyre::eyre!("Failed to fetch blobs: {e}")
-        })
-        .map(|blobs| {
-            blobs
-                .into_iter()
-                .map(|b| Box::new(b) as Box<Blob>)
-                .collect()
+    let sidecars = provider.blob_sidecars(b, &blob_hashes).await.map_err(|e| {
+        warn!(target: "blobs", "Failed to fetch blobs: {e}");
+        eyre!("Failed to fetch blobs: {e}")
+    })?;
+    ensure!(
+        sidecars.len() == blob_hashes.len(),
+        "Requested {} blobs in L1 block {}, received {}",
+        blob_hashes.len(),
+        b.number,
+        sidecars.len()
+    );
+    sidecars
+        .into_iter()
+        .zip(&blob_hashes)
+        .map(|(sidecar, hash)| {
+            ensure!(
+                sidecar.index as usize == hash.index,
+                "Received blob {} in L1 block {}, expected blob {}",
+                sidecar.index,
+                b.number,
+                hash.index
+            );
+            let kzg = BlobKzg {
+                commitment: sidecar.kzg_commitment,
+                proof: sidecar.kzg_proof,
+            };
+            blob::verify(&sidecar.blob, &kzg, hash.hash)
+                .map_err(|e| eyre!("Invalid blob {} in L1 block {}: {e}", hash.index, b.number))?;
+            Ok((Box::new(sidecar.blob), kzg))
         })
+        .collect()
 }
 
-fn extract_blob_data(
+/// Returns the indexed hashes of the blobs sent to the batch inbox by the batcher.
+///
+/// Blob indices count every blob in the block, so the blobs of transactions that are
+/// not batcher transactions still advance the index.
+pub(crate) fn extract_blob_data(
+    batch_inbox_address: Address,
     batcher_address: Address,
-    signer: Address,
     txs: &[TxEnvelope],
 ) -> Vec<IndexedBlobHash> {
     let mut index = 0;
     let mut hashes = Vec::new();
     for tx in txs {
-        let (tx_kind, calldata, blob_hashes) = match &tx {
-            TxEnvelope::Legacy(tx) => (tx.tx().to(), tx.tx().input.clone(), None),
-            TxEnvelope::Eip2930(tx) => (tx.tx().to(), tx.tx().input.clone(), None),
-            TxEnvelope::Eip1559(tx) => (tx.tx().to(), tx.tx().input.clone(), None),
-            TxEnvelope::Eip4844(blob_tx_wrapper) => match blob_tx_wrapper.tx() {
-                TxEip4844Variant::TxEip4844(tx) => (
-                    tx.to(),
-                    tx.input.clone(),
-                    Some(tx.blob_versioned_hashes.clone()),
-                ),
-                TxEip4844Variant::TxEip4844WithSidecar(tx) => {
-                    let tx = tx.tx();
-                    (
-                        tx.to(),
-                        tx.input.clone(),
-                        Some(tx.blob_versioned_hashes.clone()),
-                    )
-                }
-            },
-            // This is necessary since `TxEnvelope` is marked as non-exhaustive.
-            _ => continue,
-        };
-        let TxKind::Call(to) = tx_kind else { continue };
-
-        if to != batcher_address {
-            index += blob_hashes.map_or(0, |h| h.len());
+        let Some(fields) = da_fields(tx) else {
             continue;
-        }
-        if tx.recover_signer().unwrap_or_default() != signer {
-            index += blob_hashes.map_or(0, |h| h.len());
+        };
+        if fields.to != TxKind::Call(batch_inbox_address)
+            || tx.recover_signer().unwrap_or_default() != batcher_address
+        {
+            index += fields.blob_hashes.len();
             continue;
         }
-        if tx.tx_type() != TxType::Eip4844 {
+        // Calldata transactions are stored in the fixture as is, with no blobs to load.
+        if fields.blob_hashes.is_empty() {
             continue;
         }
-        if !calldata.is_empty() {
+        if !fields.calldata.is_empty() {
             let hash = tx.tx_hash();
             warn!(target: "blobs", "Blob tx has calldata, which will be ignored: {hash:?}");
         }
-        let Some(blob_hashes) = blob_hashes else {
-            continue;
-        };
-        for blob in blob_hashes {
-            let indexed = IndexedBlobHash { hash: blob, index };
-            hashes.push(indexed);
+        for hash in fields.blob_hashes {
+            hashes.push(IndexedBlobHash { hash: *hash, index });
             index += 1;
         }
     }
@@ -0,0 +1,101 @@
+//! Checkpoints of the progress of a fixture generation run.
+
+use crate::cmd::fixtures::add_alt_da_inputs;
+use crate::cmd::util::to_payload_attributes;
+use crate::offline::KonaDerivationFixture;
+use alloy_primitives::Bytes;
+use color_eyre::{eyre::ensure, Result};
+use derivation_driver::L2Reads;
+use kona_primitives::SystemConfig;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::Path;
+use tracing::debug;
+
+/// The logging target to use for [tracing].
+const TARGET: &str = "checkpoint";
+
+/// The progress of a fixture generation run, written periodically so that the run can
+/// be resumed after a failure.
+///
+/// The fixture is filled in as the run progresses. Once derivation completes, the L1
+/// blocks are appended to it in order, starting at `first_l1_block`.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Checkpoint {
+    /// The first L2 block of the run.
+    pub start_block: u64,
+    /// The last L2 block of the run.
+    pub end_block: u64,
+    /// The partially built fixture.
+    pub fixture: KonaDerivationFixture,
+    /// The number of the last derived L2 block, which derivation resumes on top of.
+    pub cursor: u64,
+    /// Whether every L2 block in the range has been derived.
+    pub derivation_complete: bool,
+    /// The first L1 block needed to derive the range.
+    pub first_l1_block: u64,
+    /// The last L1 block needed to derive the range so far.
+    pub last_l1_block: u64,
+    /// The system config the batcher address is tracked with, as of the last L1 block
+    /// in the fixture.
+    pub batcher_system_config: SystemConfig,
+}
+
+impl Checkpoint {
+    /// Loads the checkpoint at the given path, and checks that it belongs to a run over
+    /// the same L2 block range.
+    pub fn load(path: &Path, start_block: u64, end_block: u64) -> Result<Self> {
+        let file = std::fs::File::open(path)?;
+        let checkpoint: Self = serde_json::from_reader(std::io::BufReader::new(file))?;
+        ensure!(
+            checkpoint.start_block == start_block && checkpoint.end_block == end_block,
+            "Checkpoint {:?} is for L2 block range [{}, {}], not [{}, {}]",
+            path,
+            checkpoint.start_block,
+            checkpoint.end_block,
+            start_block,
+            end_block
+        );
+        Ok(checkpoint)
+    }
+
+    /// Writes the checkpoint to the given path. The checkpoint is written to a temporary
+    /// file first, so that a failure part way through never corrupts the last checkpoint.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let tmp = path.with_extension("tmp");
+        let file = std::fs::File::create(&tmp)?;
+        let mut writer = std::io::BufWriter::new(file);
+        serde_json::to_writer(&mut writer, self)?;
+        writer.flush()?;
+        std::fs::rename(&tmp, path)?;
+        debug!(target: TARGET, "Wrote checkpoint at L2 block {} with {} L1 blocks to {:?}", self.cursor, self.fixture.l1_blocks.len(), path);
+        Ok(())
+    }
+
+    /// Adds the L2 chain data read by the pipeline to the fixture. Payloads read by the
+    /// pipeline become reference payloads.
+    pub fn add_reads(&mut self, reads: L2Reads) {
+        let fixture = &mut self.fixture;
+        fixture.l2_block_infos.extend(reads.block_infos);
+        fixture.l2_system_configs.extend(reads.system_configs);
+        fixture.ref_payloads.extend(
+            reads
+                .payloads
+                .into_iter()
+                .map(|(number, payload)| (number, to_payload_attributes(payload))),
+        );
+    }
+
+    /// Adds the alt-DA inputs resolved by the pipeline, keyed by encoded commitment, to
+    /// the fixture.
+    pub fn add_alt_da_inputs(&mut self, inputs: HashMap<Bytes, Bytes>) {
+        add_alt_da_inputs(&mut self.fixture.alt_da_inputs, inputs);
+    }
+
+    /// Returns the L1 block numbers that still need to be fetched.
+    pub fn remaining_l1_blocks(&self) -> std::ops::RangeInclusive<u64> {
+        self.first_l1_block + self.fixture.l1_blocks.len() as u64..=self.last_l1_block
+    }
+}
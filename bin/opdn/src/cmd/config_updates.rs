@@ -0,0 +1,411 @@
+//! Contains logic to harvest system config update test vectors from L1 receipts.
+
+use alloy_consensus::{Eip658Value, Receipt};
+use alloy_primitives::{b256, Log, LogData, B256, U256};
+use clap::{ArgAction, Parser};
+use color_eyre::{
+    eyre::{ensure, eyre},
+    Result,
+};
+use derivation_driver::RollupConfigArgs;
+use kona_derive::online::*;
+use kona_derive::traits::ChainProvider;
+use kona_primitives::SystemConfig;
+use op_test_vectors::system_config::{SystemConfigUpdate, SystemConfigUpdateFixture};
+use reqwest::Url;
+use std::path::PathBuf;
+use tracing::{debug, info, trace};
+
+/// The logging target to use for [tracing].
+const TARGET: &str = "config-updates";
+
+/// The `ConfigUpdate(uint256,uint8,bytes)` event topic emitted by the L1 `SystemConfig` contract.
+pub const CONFIG_UPDATE_TOPIC: B256 =
+    b256!("1d2b0bda21d56b8bd12d4f94ebacffdfb35f5e226f84b461103bb8beab6353be");
+
+/// CLI arguments for the `config-updates` subcommand of `opdn`.
+#[derive(Parser, Clone, Debug)]
+pub struct ConfigUpdates {
+    /// The L1 block number to start from
+    #[clap(short, long, help = "Starting L1 block number")]
+    pub start_block: u64,
+    /// The L1 block number to end at
+    #[clap(short, long, help = "Ending L1 block number")]
+    pub end_block: u64,
+    /// An L1 RPC URL to fetch L1 receipts from.
+    #[clap(long, help = "RPC url to fetch L1 receipts from")]
+    pub l1_rpc_url: String,
+    /// An L2 RPC URL used to look up the rollup config.
+    #[clap(long, help = "L2 RPC URL used to look up the rollup config")]
+    pub l2_rpc_url: String,
+    /// Also emit malformed variants of every harvested log.
+    #[clap(long, help = "Emit malformed variants of every harvested log")]
+    pub malformed: bool,
+    /// Selects where the rollup config is loaded from.
+    #[command(flatten)]
+    pub rollup: RollupConfigArgs,
+    /// The output file for the test fixture.
+    #[clap(long, help = "Output file for the test fixture")]
+    pub output: PathBuf,
+    /// Verbosity level (0-4)
+    #[arg(long, short, help = "Verbosity level (0-4)", action = ArgAction::Count)]
+    pub v: u8,
+}
+
+impl ConfigUpdates {
+    /// Runs the config-updates subcommand.
+    ///
+    /// Walks the L1 receipts in `[start_block, end_block]`, and records every `ConfigUpdate`
+    /// log emitted by the rollup's `SystemConfig` contract along with the transition it causes.
+    /// The genesis system config is used as the initial pre-state.
+    pub async fn run(&self) -> Result<()> {
+        ensure!(
+            self.end_block >= self.start_block,
+            "End block must not come before the start block"
+        );
+        trace!(target: TARGET, "Harvesting system config updates for L1 block range [{}, {}]", self.start_block, self.end_block);
+
+        let cfg = self.rollup_config().await?;
+        let mut l1_provider = AlloyChainProvider::new_http(self.l1_rpc_url()?);
+        let mut system_config = cfg
+            .genesis
+            .system_config
+            .clone()
+            .ok_or_else(|| eyre!("Rollup config is missing the genesis system config"))?;
+
+        let mut updates = Vec::new();
+        for number in self.start_block..=self.end_block {
+            let block_info = l1_provider
+                .block_info_by_number(number)
+                .await
+                .map_err(|e| eyre!(e))?;
+            let receipts = l1_provider
+                .receipts_by_hash(block_info.hash)
+                .await
+                .map_err(|e| eyre!(e))?;
+            let logs = config_update_logs(&cfg, &receipts);
+            debug!(target: TARGET, "Found {} config update logs in L1 block {}", logs.len(), number);
+
+            for log in logs {
+                let post = apply_log(&cfg, &system_config, &log, block_info.timestamp);
+                let name = update_name(&log);
+                if self.malformed {
+                    for (suffix, malformed) in malformed_variants(&log) {
+                        updates.push(SystemConfigUpdate {
+                            name: format!("{name}: {suffix}"),
+                            l1_block_number: number,
+                            l1_timestamp: block_info.timestamp,
+                            post: apply_log(&cfg, &system_config, &malformed, block_info.timestamp),
+                            log: malformed,
+                            pre: system_config.clone(),
+                        });
+                    }
+                }
+                updates.push(SystemConfigUpdate {
+                    name,
+                    l1_block_number: number,
+                    l1_timestamp: block_info.timestamp,
+                    log,
+                    pre: system_config.clone(),
+                    post: post.clone(),
+                });
+                if let Some(post) = post {
+                    system_config = post;
+                }
+            }
+        }
+        info!(target: TARGET, "Collected {} system config updates", updates.len());
+
+        let fixture = SystemConfigUpdateFixture {
+            rollup_config: cfg,
+            updates,
+        };
+        let file = std::fs::File::create(&self.output)?;
+        serde_json::to_writer_pretty(file, &fixture)?;
+        info!(target: TARGET, "Wrote system config update fixture to: {:?}", self.output);
+
+        Ok(())
+    }
+
+    /// Gets the rollup config from the rollup config file, the superchain registry, or the
+    /// rollup node.
+    pub async fn rollup_config(&self) -> Result<RollupConfig> {
+        self.rollup.resolve(self.l2_rpc_url()?, None).await
+    }
+
+    /// Returns the l1 rpc url from CLI or environment variable.
+    pub fn l1_rpc_url(&self) -> Result<Url> {
+        Url::parse(&self.l1_rpc_url).map_err(|e| eyre!(e))
+    }
+
+    /// Returns the l2 rpc url from CLI or environment variable.
+    pub fn l2_rpc_url(&self) -> Result<Url> {
+        Url::parse(&self.l2_rpc_url).map_err(|e| eyre!(e))
+    }
+}
+
+/// Returns the `ConfigUpdate` logs emitted by the rollup's `SystemConfig` contract
+/// in successful receipts.
+pub fn config_update_logs(cfg: &RollupConfig, receipts: &[Receipt]) -> Vec<Log> {
+    receipts
+        .iter()
+        .filter(|r| r.status != Eip658Value::Eip658(false))
+        .flat_map(|r| r.logs.iter())
+        .filter(|log| {
+            log.address == cfg.l1_system_config_address
+                && log.topics().first() == Some(&CONFIG_UPDATE_TOPIC)
+        })
+        .cloned()
+        .collect()
+}
+
+/// Applies a single `ConfigUpdate` log to the given system config.
+/// Returns `None` if the log is rejected.
+pub fn apply_log(
+    cfg: &RollupConfig,
+    system_config: &SystemConfig,
+    log: &Log,
+    l1_timestamp: u64,
+) -> Option<SystemConfig> {
+    let receipt = Receipt {
+        status: Eip658Value::Eip658(true),
+        cumulative_gas_used: 0,
+        logs: vec![log.clone()],
+    };
+    let mut post = system_config.clone();
+    match post.update_with_receipts(&[receipt], cfg, l1_timestamp) {
+        Ok(_) => Some(post),
+        Err(e) => {
+            debug!(target: TARGET, "Rejected config update log: {e}");
+            None
+        }
+    }
+}
+
+/// Returns a short name for the update type encoded in the log topics.
+fn update_name(log: &Log) -> String {
+    let Some(update_type) = log.topics().get(2) else {
+        return "missing update type".to_string();
+    };
+    match u8::try_from(U256::from_be_bytes(update_type.0)) {
+        Ok(0) => "batcher".to_string(),
+        Ok(1) => "gas config".to_string(),
+        Ok(2) => "gas limit".to_string(),
+        Ok(3) => "unsafe block signer".to_string(),
+        _ => format!("unknown update type {update_type}"),
+    }
+}
+
+/// Derives malformed variants of a well-formed `ConfigUpdate` log.
+pub fn malformed_variants(log: &Log) -> Vec<(&'static str, Log)> {
+    let topics = log.topics();
+    let data = &log.data.data;
+    let with = |topics: Vec<B256>, data: Vec<u8>| Log {
+        address: log.address,
+        data: LogData::new_unchecked(topics, data.into()),
+    };
+
+    let mut variants = Vec::new();
+    if topics.len() == 3 {
+        variants.push((
+            "unsupported version",
+            with(
+                vec![topics[0], B256::with_last_byte(1), topics[2]],
+                data.to_vec(),
+            ),
+        ));
+        variants.push((
+            "unknown update type",
+            with(
+                vec![topics[0], topics[1], B256::with_last_byte(0xff)],
+                data.to_vec(),
+            ),
+        ));
+        variants.push(("missing topics", with(vec![topics[0]], data.to_vec())));
+    }
+    if data.len() >= 64 {
+        let mut bad_pointer = data.to_vec();
+        bad_pointer[31] = 0x40;
+        variants.push(("invalid data pointer", with(topics.to_vec(), bad_pointer)));
+        let mut bad_length = data.to_vec();
+        bad_length[63] = bad_length[63].wrapping_add(1);
+        variants.push(("invalid data length", with(topics.to_vec(), bad_length)));
+    }
+    if !data.is_empty() {
+        variants.push((
+            "truncated data",
+            with(topics.to_vec(), data[..data.len() - 1].to_vec()),
+        ));
+    }
+    variants
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use alloy_primitives::{address, Address};
+
+    pub(crate) const SYSTEM_CONFIG: Address = address!("229047fed2591dbec1ef1118d64f7af3db9eb290");
+
+    pub(crate) fn rollup_config() -> RollupConfig {
+        RollupConfig {
+            l1_system_config_address: SYSTEM_CONFIG,
+            ..Default::default()
+        }
+    }
+
+    fn system_config() -> SystemConfig {
+        SystemConfig {
+            batcher_address: address!("3333333333333333333333333333333333333333"),
+            overhead: U256::from(8),
+            scalar: U256::from(7),
+            gas_limit: 30_000_000,
+            base_fee_scalar: None,
+            blob_base_fee_scalar: None,
+        }
+    }
+
+    /// Returns a version 0 `ConfigUpdate` log of the update type, with the ABI-encoded
+    /// payload as its `bytes` data.
+    pub(crate) fn config_update(address: Address, update_type: u8, payload: &[B256]) -> Log {
+        let mut data = Vec::new();
+        data.extend(U256::from(32).to_be_bytes::<32>());
+        data.extend(U256::from(payload.len() * 32).to_be_bytes::<32>());
+        for word in payload {
+            data.extend(word.as_slice());
+        }
+        Log {
+            address,
+            data: LogData::new_unchecked(
+                vec![
+                    CONFIG_UPDATE_TOPIC,
+                    B256::ZERO,
+                    B256::with_last_byte(update_type),
+                ],
+                data.into(),
+            ),
+        }
+    }
+
+    pub(crate) fn receipt(success: bool, logs: Vec<Log>) -> Receipt {
+        Receipt {
+            status: Eip658Value::Eip658(success),
+            cumulative_gas_used: 0,
+            logs,
+        }
+    }
+
+    fn batcher_update() -> Log {
+        let batcher = address!("4444444444444444444444444444444444444444");
+        config_update(SYSTEM_CONFIG, 0, &[batcher.into_word()])
+    }
+
+    fn gas_config_update() -> Log {
+        config_update(
+            SYSTEM_CONFIG,
+            1,
+            &[U256::from(188).into(), U256::from(684_000).into()],
+        )
+    }
+
+    fn gas_limit_update() -> Log {
+        config_update(SYSTEM_CONFIG, 2, &[U256::from(60_000_000).into()])
+    }
+
+    #[test]
+    fn test_config_update_logs_roundtrip() {
+        let cfg = rollup_config();
+        let signer = address!("5555555555555555555555555555555555555555");
+        let receipts = vec![
+            receipt(
+                true,
+                vec![
+                    batcher_update(),
+                    config_update(Address::ZERO, 2, &[U256::from(1).into()]),
+                    gas_config_update(),
+                ],
+            ),
+            receipt(false, vec![gas_limit_update()]),
+            receipt(
+                true,
+                vec![
+                    gas_limit_update(),
+                    config_update(SYSTEM_CONFIG, 3, &[signer.into_word()]),
+                ],
+            ),
+        ];
+
+        let logs = config_update_logs(&cfg, &receipts);
+        let names = logs.iter().map(update_name).collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            ["batcher", "gas config", "gas limit", "unsafe block signer"]
+        );
+
+        let mut config = system_config();
+        let mut posts = Vec::new();
+        for log in &logs {
+            config = apply_log(&cfg, &config, log, 0).unwrap();
+            posts.push(config.clone());
+        }
+        let batcher = SystemConfig {
+            batcher_address: address!("4444444444444444444444444444444444444444"),
+            ..system_config()
+        };
+        let gas_config = SystemConfig {
+            overhead: U256::from(188),
+            scalar: U256::from(684_000),
+            ..batcher.clone()
+        };
+        let gas_limit = SystemConfig {
+            gas_limit: 60_000_000,
+            ..gas_config.clone()
+        };
+        assert_eq!(posts, [batcher, gas_config, gas_limit.clone(), gas_limit]);
+    }
+
+    #[test]
+    fn test_malformed_variants_are_rejected() {
+        let cfg = rollup_config();
+        for log in [batcher_update(), gas_config_update(), gas_limit_update()] {
+            assert!(apply_log(&cfg, &system_config(), &log, 0).is_some());
+
+            let variants = malformed_variants(&log);
+            let names = variants.iter().map(|(name, _)| *name).collect::<Vec<_>>();
+            assert_eq!(
+                names,
+                [
+                    "unsupported version",
+                    "unknown update type",
+                    "missing topics",
+                    "invalid data pointer",
+                    "invalid data length",
+                    "truncated data",
+                ]
+            );
+            for (name, malformed) in &variants {
+                assert_eq!(
+                    apply_log(&cfg, &system_config(), malformed, 0),
+                    None,
+                    "{} variant of {} was accepted",
+                    name,
+                    update_name(&log)
+                );
+            }
+            assert_eq!(
+                update_name(&variants[1].1),
+                format!("unknown update type {}", B256::with_last_byte(0xff))
+            );
+            assert_eq!(update_name(&variants[2].1), "missing update type");
+        }
+    }
+
+    #[test]
+    fn test_malformed_variants_without_data() {
+        let log = Log {
+            address: SYSTEM_CONFIG,
+            data: LogData::new_unchecked(vec![CONFIG_UPDATE_TOPIC], Default::default()),
+        };
+        assert!(malformed_variants(&log).is_empty());
+    }
+}
@@ -1,69 +1,345 @@
 //! Logic for building the derivation fixture blocks.
 
-use crate::cmd::blobs;
+use crate::cmd::blobs::{self, SidecarProvider};
+use crate::cmd::config_updates::{apply_log, config_update_logs};
+use alloy_consensus::{Header, Receipt, TxEnvelope};
 use alloy_eips::eip2718::Encodable2718;
-use alloy_primitives::Address;
+use alloy_primitives::{Address, Bytes};
 use color_eyre::eyre::{eyre, Result};
-use kona_derive::online::{
-    AlloyChainProvider, OnlineBeaconClient, OnlineBlobProviderWithFallback, SimpleSlotDerivation,
-};
+use derivation_driver::Fetcher;
 use kona_derive::traits::ChainProvider;
-use kona_primitives::Blob;
-use op_test_vectors::derivation::FixtureBlock;
+use kona_primitives::{Blob, BlockInfo, RollupConfig, SystemConfig};
+use op_test_vectors::derivation::{AltDaInput, FixtureBlock};
+use std::collections::HashMap;
+use tracing::{debug, info};
+
+/// The logging target to use for [tracing].
+const TARGET: &str = "fixtures";
+
+/// The contents of an L1 block, before its blobs are loaded.
+#[derive(Debug, Clone)]
+struct L1BlockData {
+    /// The block info.
+    block_info: BlockInfo,
+    /// The block header.
+    header: Header,
+    /// The block transactions.
+    txs: Vec<TxEnvelope>,
+    /// The block receipts.
+    receipts: Vec<Receipt>,
+}
 
 /// Constructs [FixtureBlock]s for the given L1 blocks.
-pub async fn build_fixture_blocks(
-    batcher_address: Address,
-    signer: Address,
+///
+/// The blocks are fetched concurrently by the [Fetcher], and returned in order.
+///
+/// The batcher address is tracked across the blocks by applying the `ConfigUpdate`
+/// logs in each block's receipts to the given system config, which is left as of the
+/// last block. As in the L1 traversal stage, the updates in a block apply to the
+/// batcher transactions of that same block.
+///
+/// Blobs are verified, with the KZG commitments and proofs of their sidecars, against
+/// their versioned hashes. The commitments and proofs are stored in the fixture blocks
+/// if `store_kzg` is set.
+pub async fn build_fixture_blocks<C, S>(
+    cfg: &RollupConfig,
+    system_config: &mut SystemConfig,
     blocks: &[u64],
-    l1_provider: &mut AlloyChainProvider,
-    blob_provider: &mut OnlineBlobProviderWithFallback<
-        OnlineBeaconClient,
-        OnlineBeaconClient,
-        SimpleSlotDerivation,
-    >,
-) -> Result<Vec<FixtureBlock<Blob>>> {
-    let mut fixtures = Vec::with_capacity(blocks.len());
-    for b in blocks {
-        let block_info = l1_provider
-            .block_info_by_number(*b)
-            .await
-            .map_err(|e| eyre!(e))?;
-        let block_header = l1_provider
-            .header_by_hash(block_info.hash)
-            .await
-            .map_err(|e| eyre!(e))?;
-        let (_, txs) = l1_provider
-            .block_info_and_transactions_by_hash(block_info.hash)
-            .await
-            .map_err(|e| eyre!(e))?;
-        let mut transactions = Vec::with_capacity(txs.len());
-        for tx in txs.as_slice() {
-            let mut out = Vec::new();
-            tx.encode_2718(&mut out);
-            transactions.push(out.into());
+    fetcher: &Fetcher,
+    l1_provider: &C,
+    sidecar_provider: &S,
+    store_kzg: bool,
+) -> Result<Vec<FixtureBlock<Blob>>>
+where
+    C: ChainProvider + Clone,
+    S: SidecarProvider + Clone,
+{
+    let data = fetcher
+        .map_ordered(blocks.iter().copied(), |number| {
+            fetch_l1_block(fetcher, l1_provider, number)
+        })
+        .await?;
+    debug!(target: TARGET, "Fetched {} L1 blocks", data.len());
+
+    let batchers = batcher_addresses(cfg, system_config, &data);
+    fetcher
+        .map_ordered(data.into_iter().zip(batchers), |(block, batcher)| {
+            fixture_block(
+                fetcher,
+                sidecar_provider,
+                cfg.batch_inbox_address,
+                batcher,
+                block,
+                store_kzg,
+            )
+        })
+        .await
+}
+
+/// Follows the batcher address through the system config updates of every block, and
+/// returns the batcher of each block. The system config is left as of the last block.
+fn batcher_addresses(
+    cfg: &RollupConfig,
+    system_config: &mut SystemConfig,
+    data: &[L1BlockData],
+) -> Vec<Address> {
+    let mut batchers = Vec::with_capacity(data.len());
+    for block in data {
+        for log in config_update_logs(cfg, &block.receipts) {
+            let Some(post) = apply_log(cfg, system_config, &log, block.block_info.timestamp) else {
+                continue;
+            };
+            if post.batcher_address != system_config.batcher_address {
+                info!(target: TARGET,
+                    "Batcher changed from {} to {} in L1 block {}",
+                    system_config.batcher_address, post.batcher_address, block.block_info.number
+                );
+            }
+            *system_config = post;
         }
-        let receipts = l1_provider
-            .receipts_by_hash(block_info.hash)
-            .await
-            .map_err(|e| eyre!(e))?;
-
-        let blobs = blobs::load(
-            &block_info,
-            txs.as_slice(),
-            batcher_address,
-            signer,
-            blob_provider,
-        )
+        batchers.push(system_config.batcher_address);
+    }
+    batchers
+}
+
+/// Fetches the header, transactions and receipts of the given L1 block.
+async fn fetch_l1_block<C: ChainProvider + Clone>(
+    fetcher: &Fetcher,
+    l1_provider: &C,
+    number: u64,
+) -> Result<L1BlockData> {
+    let block_info = fetcher
+        .call("L1 block info", || {
+            let mut l1_provider = l1_provider.clone();
+            async move {
+                l1_provider
+                    .block_info_by_number(number)
+                    .await
+                    .map_err(|e| eyre!(e))
+            }
+        })
         .await?;
+    let header = fetcher
+        .call("L1 header", || {
+            let mut l1_provider = l1_provider.clone();
+            async move {
+                l1_provider
+                    .header_by_hash(block_info.hash)
+                    .await
+                    .map_err(|e| eyre!(e))
+            }
+        })
+        .await?;
+    let (_, txs) = fetcher
+        .call("L1 transactions", || {
+            let mut l1_provider = l1_provider.clone();
+            async move {
+                l1_provider
+                    .block_info_and_transactions_by_hash(block_info.hash)
+                    .await
+                    .map_err(|e| eyre!(e))
+            }
+        })
+        .await?;
+    let receipts = fetcher
+        .call("L1 receipts", || {
+            let mut l1_provider = l1_provider.clone();
+            async move {
+                l1_provider
+                    .receipts_by_hash(block_info.hash)
+                    .await
+                    .map_err(|e| eyre!(e))
+            }
+        })
+        .await?;
+    Ok(L1BlockData {
+        block_info,
+        header,
+        txs,
+        receipts,
+    })
+}
+
+/// Loads the batcher blobs of the L1 block and assembles its [FixtureBlock].
+async fn fixture_block<S: SidecarProvider + Clone>(
+    fetcher: &Fetcher,
+    sidecar_provider: &S,
+    batch_inbox_address: Address,
+    batcher_address: Address,
+    block: L1BlockData,
+    store_kzg: bool,
+) -> Result<FixtureBlock<Blob>> {
+    let blobs = fetcher
+        .call("blobs", || {
+            let mut sidecar_provider = sidecar_provider.clone();
+            let block = &block;
+            async move {
+                blobs::load(
+                    &block.block_info,
+                    &block.txs,
+                    batch_inbox_address,
+                    batcher_address,
+                    &mut sidecar_provider,
+                )
+                .await
+            }
+        })
+        .await?;
+
+    let (blobs, kzg): (Vec<_>, Vec<_>) = blobs.into_iter().unzip();
+    let (kzg_commitments, kzg_proofs) = if store_kzg {
+        kzg.iter().map(|k| (k.commitment, k.proof)).unzip()
+    } else {
+        (vec![], vec![])
+    };
+
+    let mut transactions = Vec::with_capacity(block.txs.len());
+    for tx in &block.txs {
+        let mut out = Vec::new();
+        tx.encode_2718(&mut out);
+        transactions.push(out.into());
+    }
+    Ok(FixtureBlock {
+        header: block.header,
+        transactions,
+        blobs,
+        kzg_commitments,
+        kzg_proofs,
+        receipts: block.receipts,
+        pruned: None,
+    })
+}
 
-        let fixture = FixtureBlock {
-            header: block_header,
-            transactions,
-            blobs,
+/// Adds the alt-DA inputs recorded during derivation, keyed by encoded commitment, to the
+/// inputs of a fixture. The inputs are kept sorted by commitment, without duplicates.
+pub fn add_alt_da_inputs(inputs: &mut Vec<AltDaInput>, recorded: HashMap<Bytes, Bytes>) {
+    inputs.extend(
+        recorded
+            .into_iter()
+            .map(|(commitment, data)| AltDaInput { commitment, data }),
+    );
+    inputs.sort_by(|a, b| a.commitment.cmp(&b.commitment));
+    inputs.dedup_by(|a, b| a.commitment == b.commitment);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmd::blobs::extract_blob_data;
+    use crate::cmd::config_updates::tests::{config_update, receipt, rollup_config, SYSTEM_CONFIG};
+    use alloy_consensus::{SignableTransaction, TxEip4844};
+    use alloy_primitives::{address, b256, keccak256, B256};
+    use alloy_signer::SignerSync;
+    use alloy_signer_local::PrivateKeySigner;
+
+    const INBOX: Address = address!("ff00000000000000000000000000000000000901");
+
+    fn signer(key: B256) -> PrivateKeySigner {
+        PrivateKeySigner::from_bytes(&key).unwrap()
+    }
+
+    /// Returns a blob transaction to the batch inbox, referencing `count` blobs tagged
+    /// with the given byte.
+    fn blob_tx(signer: &PrivateKeySigner, tag: u8, count: u8) -> TxEnvelope {
+        let tx = TxEip4844 {
+            chain_id: 900,
+            to: INBOX,
+            blob_versioned_hashes: (0..count).map(|i| keccak256([tag, i])).collect(),
+            ..Default::default()
+        };
+        let signature = signer.sign_hash_sync(&tx.signature_hash()).unwrap();
+        tx.into_signed(signature).into()
+    }
+
+    fn block(number: u64, receipts: Vec<Receipt>, txs: Vec<TxEnvelope>) -> L1BlockData {
+        L1BlockData {
+            block_info: BlockInfo {
+                number,
+                timestamp: number * 12,
+                ..Default::default()
+            },
+            header: Header {
+                number,
+                ..Default::default()
+            },
+            txs,
             receipts,
+        }
+    }
+
+    /// Returns the indexed blob hashes tagged with the given byte, at the given indices.
+    fn hashes(tag: u8, indices: &[usize]) -> Vec<(B256, usize)> {
+        indices
+            .iter()
+            .enumerate()
+            .map(|(i, index)| (keccak256([tag, i as u8]), *index))
+            .collect()
+    }
+
+    fn extract(batcher: Address, txs: &[TxEnvelope]) -> Vec<(B256, usize)> {
+        extract_blob_data(INBOX, batcher, txs)
+            .into_iter()
+            .map(|h| (h.hash, h.index))
+            .collect()
+    }
+
+    #[test]
+    fn test_batcher_switch() {
+        let cfg = RollupConfig {
+            batch_inbox_address: INBOX,
+            ..rollup_config()
+        };
+        let old = signer(b256!(
+            "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80"
+        ));
+        let new = signer(b256!(
+            "59c6995e998f97a5a0044966f0945389dc9e86dae88c7a8412f4603b6b78690d"
+        ));
+        let mut system_config = SystemConfig {
+            batcher_address: old.address(),
+            ..Default::default()
+        };
+        let update = config_update(SYSTEM_CONFIG, 0, &[new.address().into_word()]);
+        let blocks = [
+            block(1, vec![], vec![blob_tx(&old, 1, 2)]),
+            // The update applies to the batcher transactions of its own block.
+            block(
+                2,
+                vec![receipt(true, vec![update])],
+                vec![blob_tx(&old, 2, 1), blob_tx(&new, 3, 2)],
+            ),
+            block(3, vec![], vec![blob_tx(&new, 4, 1), blob_tx(&old, 5, 1)]),
+        ];
+
+        let batchers = batcher_addresses(&cfg, &mut system_config, &blocks);
+        assert_eq!(batchers, [old.address(), new.address(), new.address()]);
+        assert_eq!(system_config.batcher_address, new.address());
+
+        let extracted = blocks
+            .iter()
+            .zip(&batchers)
+            .map(|(block, batcher)| extract(*batcher, &block.txs))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            extracted,
+            // Blobs of the old batcher still advance the blob index.
+            [hashes(1, &[0, 1]), hashes(3, &[1, 2]), hashes(4, &[0])]
+        );
+    }
+
+    #[test]
+    fn test_extract_blob_data_skips_other_txs() {
+        let batcher = signer(b256!(
+            "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80"
+        ));
+        let other = TxEip4844 {
+            chain_id: 900,
+            to: SYSTEM_CONFIG,
+            blob_versioned_hashes: vec![keccak256([9])],
+            ..Default::default()
         };
-        fixtures.push(fixture);
+        let signature = batcher.sign_hash_sync(&other.signature_hash()).unwrap();
+        let txs = [other.into_signed(signature).into(), blob_tx(&batcher, 1, 1)];
+        assert_eq!(extract(batcher.address(), &txs), hashes(1, &[1]));
     }
-    Ok(fixtures)
 }
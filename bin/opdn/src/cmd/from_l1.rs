@@ -5,15 +5,20 @@ use color_eyre::{
     eyre::{ensure, eyre},
     Result,
 };
+use derivation_driver::{
+    online::{start_cursor, OnlineProviders},
+    DaServerClient, DerivationSession, FetchArgs, RecordingInputFetcher, RollupConfigArgs,
+    StallArgs,
+};
 use hashbrown::HashMap;
-use kona_derive::{errors::StageError, online::*};
-use kona_primitives::L2BlockInfo;
+use kona_derive::traits::L2ChainProvider;
+use kona_primitives::RollupConfig;
 use op_test_vectors::derivation::DerivationFixture;
 use reqwest::Url;
+use rpc_cache::RpcCacheArgs;
 use std::path::PathBuf;
 use std::sync::Arc;
-use superchain_registry::ROLLUP_CONFIGS;
-use tracing::{debug, error, info, trace, warn};
+use tracing::{info, trace};
 
 /// The logging target to use for [tracing].
 const TARGET: &str = "from-l1";
@@ -33,48 +38,122 @@ pub struct FromL1 {
     /// An L2 RPC URL to validate span batches.
     #[clap(long, help = "L2 RPC URL to validate span batches")]
     pub l2_rpc_url: String,
-    /// A beacon client to fetch blob data from.
-    #[clap(long, help = "Beacon client url to fetch blob data from")]
-    pub beacon_url: String,
+    /// Beacon clients to fetch blob data from. The healthiest one is used first.
+    #[clap(
+        long,
+        required = true,
+        value_delimiter = ',',
+        help = "Beacon client urls to fetch blob data from, repeated or comma separated"
+    )]
+    pub beacon_url: Vec<String>,
+    /// A blob archiver to fetch blobs past the beacon retention window from.
+    #[clap(
+        long,
+        help = "Blob archiver url to fetch blobs past the beacon retention window from"
+    )]
+    pub blob_archiver_url: Option<String>,
     /// The output file for the test fixture.
     #[clap(long, help = "Output file for the test fixture")]
     pub output: PathBuf,
+    /// A DA server to resolve alt-DA commitments with, for chains using alternative
+    /// data availability.
+    #[clap(long, help = "DA server url to resolve alt-DA commitments with")]
+    pub da_server_url: Option<String>,
+    /// Selects where the rollup config is loaded from.
+    #[command(flatten)]
+    pub rollup: RollupConfigArgs,
+    /// Store the KZG commitments and proofs of the blobs in the fixture.
+    #[clap(
+        long,
+        help = "Store the KZG commitments and proofs of the blobs in the fixture"
+    )]
+    pub kzg_proofs: bool,
+    /// Controls how L1 and L2 blocks are fetched.
+    #[command(flatten)]
+    pub fetch: FetchArgs,
+    /// Bounds how long derivation waits for progress before aborting.
+    #[command(flatten)]
+    pub stall: StallArgs,
+    /// Records or replays the RPC and beacon responses.
+    #[command(flatten)]
+    pub rpc_cache: RpcCacheArgs,
     /// Verbosity level (0-4)
     #[arg(long, short, help = "Verbosity level (0-4)", action = ArgAction::Count)]
     pub v: u8,
 }
 
 impl FromL1 {
+    /// Runs the from-l1 subcommand.
+    /// Endpoints are routed through the RPC cache if one is configured.
+    pub async fn run(&self) -> Result<()> {
+        self.with_rpc_cache().await?.generate().await
+    }
+
+    /// Returns a copy of the command with its endpoints routed through the RPC cache.
+    pub async fn with_rpc_cache(&self) -> Result<Self> {
+        let mut cmd = self.clone();
+        cmd.l1_rpc_url = self.rpc_cache.route("l1", &self.l1_rpc_url).await?;
+        cmd.l2_rpc_url = self.rpc_cache.route("l2", &self.l2_rpc_url).await?;
+        cmd.beacon_url = self.rpc_cache.route_all("beacon", &self.beacon_url).await?;
+        if let Some(url) = &self.blob_archiver_url {
+            cmd.blob_archiver_url = Some(self.rpc_cache.route("blob-archiver", url).await?);
+        }
+        if let Some(url) = &self.da_server_url {
+            cmd.da_server_url = Some(self.rpc_cache.route("da-server", url).await?);
+        }
+        if let Some(url) = &self.rollup.rollup_rpc_url {
+            cmd.rollup.rollup_rpc_url = Some(self.rpc_cache.route("rollup", url).await?);
+        }
+        cmd.rpc_cache = RpcCacheArgs::default();
+        Ok(cmd)
+    }
+
     /// Runs the derivation test fixture generation using the L1 source block information.
     /// This function effectively takes the L1 block info and fetches any calldata or blob
     /// data associated with this block.
-    pub async fn run(&self) -> Result<()> {
+    pub async fn generate(&self) -> Result<()> {
         ensure!(
             self.end_block > self.start_block,
             "End block must come after the start block"
         );
-        trace!(target: "from-l1", "Producing derivation fixture for L1 block range [{}, {}]", self.start_block, self.end_block);
+        trace!(target: TARGET, "Producing derivation fixture for L1 block range [{}, {}]", self.start_block, self.end_block);
 
-        // Build the pipeline
+        // Build the derivation session
         let cfg = Arc::new(self.rollup_config().await?);
-        let mut l1_provider = self.l1_provider()?;
-        let mut l2_provider = self.l2_provider(cfg.clone())?;
-        let attributes = self.attributes(cfg.clone(), &l2_provider, &l1_provider);
-        let mut blob_provider = self.blob_provider();
-        let dap = self.dap(l1_provider.clone(), blob_provider.clone(), &cfg);
-        let mut l2_cursor = self.cursor().await?;
-        let l1_tip = l1_provider
-            .block_info_by_number(l2_cursor.l1_origin.number)
-            .await
-            .expect("Failed to fetch genesis L1 block info for pipeline tip");
-        let mut pipeline = new_online_pipeline(
-            cfg.clone(),
-            l1_provider.clone(),
-            dap,
-            l2_provider.clone(),
-            attributes,
-            l1_tip,
-        );
+        let mut providers = self.providers(cfg.clone())?;
+        let fetcher = self.fetch.fetcher();
+        let l2_cursor = start_cursor(&mut providers.l2, &cfg, self.start_block).await?;
+        // Record the alt-DA inputs the pipeline resolves, if the chain uses alt-DA.
+        let da_inputs = self
+            .da_server_url
+            .as_ref()
+            .map(|url| RecordingInputFetcher::new(DaServerClient::new(url)));
+        let session = match &da_inputs {
+            Some(inputs) => {
+                DerivationSession::new_alt_da(
+                    cfg.clone(),
+                    providers.l1.clone(),
+                    providers.l2.clone(),
+                    providers.blobs.clone(),
+                    inputs.clone(),
+                    l2_cursor,
+                )
+                .await?
+            }
+            None => {
+                DerivationSession::new(
+                    cfg.clone(),
+                    providers.l1.clone(),
+                    providers.l2.clone(),
+                    providers.blobs.clone(),
+                    l2_cursor,
+                )
+                .await?
+            }
+        };
+        let mut session = session
+            .with_end_block(self.end_block)
+            .with_stall_args(&self.stall);
 
         // Collect reference payloads for span batch validation.
         let mut ref_payloads = HashMap::new();
@@ -82,94 +161,43 @@ impl FromL1 {
         let mut payloads = HashMap::new();
         let mut l2_block_infos = HashMap::new();
         let mut configs = HashMap::new();
-        let first_system_config = l2_provider
+        let first_system_config = providers
+            .l2
             .system_config_by_number(l2_cursor.block_info.number, Arc::clone(&cfg))
             .await
             .map_err(|e| eyre!(e))?;
+        // The batcher address is tracked from the system config of the starting L2 block.
+        let mut start_system_config = first_system_config.clone();
         configs.insert(l2_cursor.block_info.number, first_system_config);
         l2_block_infos.insert(l2_cursor.block_info.number, l2_cursor);
         let start_l2_cursor = l2_cursor.block_info.number;
 
         // Run the pipeline
-        loop {
-            // If the cursor is beyond the end block, break the loop.
-            if l2_cursor.block_info.number >= self.end_block {
-                trace!(target: TARGET, "Cursor is beyond the end block, breaking loop");
-                break;
-            }
-
-            // Step on the pipeline.
-            match pipeline.step(l2_cursor).await {
-                StepResult::PreparedAttributes => trace!(target: "loop", "Prepared attributes"),
-                StepResult::AdvancedOrigin => trace!(target: "loop", "Advanced origin"),
-                StepResult::OriginAdvanceErr(e) => {
-                    warn!(target: TARGET, "Could not advance origin: {:?}", e)
-                }
-                StepResult::StepFailed(e) => match e {
-                    StageError::NotEnoughData => {
-                        debug!(target: TARGET, "Not enough data to step derivation pipeline");
-                    }
-                    _ => {
-                        error!(target: TARGET, "Error stepping derivation pipeline: {:?}", e);
-                    }
-                },
-            }
-
-            // Get the attributes if there are some available.
-            let Some(attributes) = pipeline.next() else {
-                continue;
-            };
-
+        while let Some(block) = session.next_block().await? {
             // Print the L1 range for this L2 Block.
-            let derived = attributes.parent.block_info.number as i64 + 1;
-            let l2_block_info = l2_provider
-                .l2_block_info_by_number(derived as u64)
-                .await
-                .map_err(|e| eyre!(e))?;
-            let origin = pipeline
-                .origin()
-                .ok_or(eyre!("Failed to get pipeline l1 origin"))?;
+            let number = block.number();
             info!(target: TARGET,
                 "L2 Block [{}] L1 Range: [{}, {}]",
-                derived, l2_block_info.l1_origin.number, origin.number
+                number, block.l2_block_info.l1_origin.number, block.l1_origin.number
             );
-            payloads.insert(derived as u64, attributes.attributes);
-
-            // Keep trying to advance the cursor in case the fetch fails.
-            loop {
-                match l2_provider
-                    .l2_block_info_by_number(l2_cursor.block_info.number + 1)
-                    .await
-                {
-                    Ok(bi) => {
-                        l2_cursor = bi;
-                        break;
-                    }
-                    Err(e) => {
-                        error!(target: TARGET, "Failed to fetch next pending l2 safe head: {}, err: {:?}", l2_cursor.block_info.number + 1, e);
-                        // Don't step on the pipeline if we failed to fetch the next l2 safe head.
-                        continue;
-                    }
-                }
-            }
+            payloads.insert(number, block.attributes.attributes);
 
             // Add the system config
-            let system_config = l2_provider
-                .system_config_by_number(l2_cursor.block_info.number, Arc::clone(&cfg))
+            let system_config = providers
+                .l2
+                .system_config_by_number(number, Arc::clone(&cfg))
                 .await
                 .map_err(|e| eyre!(e))?;
-            configs.insert(l2_cursor.block_info.number, system_config);
-            l2_block_infos.insert(l2_cursor.block_info.number, l2_cursor);
+            configs.insert(number, system_config);
+            l2_block_infos.insert(number, block.l2_block_info);
 
             // Get reference payloads by l2 block number for span batch validation
-            let l2_payload = l2_provider
-                .payload_by_number(l2_cursor.block_info.number)
+            let l2_payload = providers
+                .l2
+                .payload_by_number(number)
                 .await
                 .map_err(|e| eyre!(e))?;
-            ref_payloads.insert(
-                l2_cursor.block_info.number,
-                crate::cmd::util::to_payload_attributes(l2_payload),
-            );
+            ref_payloads.insert(number, crate::cmd::util::to_payload_attributes(l2_payload));
         }
 
         // Construct a sequential list of block numbers from [start_block, end_block].
@@ -177,19 +205,17 @@ impl FromL1 {
 
         // Construct the derivation fixture.
         let fixture_blocks = crate::cmd::build_fixture_blocks(
-            cfg.batch_inbox_address,
-            cfg.genesis
-                .system_config
-                .as_ref()
-                .map(|sc| sc.batcher_address)
-                .unwrap_or_default(),
+            &cfg,
+            &mut start_system_config,
             &blocks,
-            &mut l1_provider,
-            &mut blob_provider,
+            &fetcher,
+            &providers.l1,
+            &providers.sidecars,
+            self.kzg_proofs,
         )
         .await?;
 
-        let fixture = DerivationFixture {
+        let mut fixture = DerivationFixture {
             rollup_config: Arc::unwrap_or_clone(cfg),
             l1_blocks: fixture_blocks,
             l2_payloads: payloads,
@@ -198,102 +224,36 @@ impl FromL1 {
             l2_block_infos,
             l2_cursor_start: start_l2_cursor,
             l2_cursor_end: self.end_block,
+            alt_da_inputs: vec![],
         };
-        info!(target: "from-l1", "Successfully built derivation test fixture");
+        if let Some(inputs) = &da_inputs {
+            crate::cmd::fixtures::add_alt_da_inputs(&mut fixture.alt_da_inputs, inputs.take());
+        }
+        info!(target: TARGET, "Successfully built derivation test fixture");
 
         // Write the derivation fixture to the specified output location.
         let file = std::fs::File::create(&self.output)?;
         serde_json::to_writer_pretty(file, &fixture)?;
-        info!(target: "from-l1", "Wrote derivation fixture to: {:?}", self.output);
+        info!(target: TARGET, "Wrote derivation fixture to: {:?}", self.output);
 
         Ok(())
     }
 
-    /// Gets the L2 starting block number.
-    /// Returns the genesis L2 block number if the start block is less than the genesis block number.
-    pub fn start_block(&self, cfg: &RollupConfig) -> u64 {
-        if self.start_block < cfg.genesis.l2.number {
-            cfg.genesis.l2.number
-        } else if self.start_block != 0 {
-            self.start_block - 1
-        } else {
-            self.start_block
-        }
-    }
-
-    /// Returns an [L2BlockInfo] cursor for the pipeline.
-    pub async fn cursor(&self) -> Result<L2BlockInfo> {
-        let cfg = self.rollup_config().await?;
-        let start_block = self.start_block(&cfg);
-        let mut l2_provider = self.l2_provider(Arc::new(cfg))?;
-        let cursor = l2_provider
-            .l2_block_info_by_number(start_block)
-            .await
-            .map_err(|_| eyre!("Failed to fetch genesis L2 block info for pipeline cursor"))?;
-        Ok(cursor)
-    }
-
-    /// Returns a new [AlloyChainProvider] using the l1 rpc url.
-    pub fn l1_provider(&self) -> Result<AlloyChainProvider> {
-        Ok(AlloyChainProvider::new_http(self.l1_rpc_url()?))
+    /// Returns the [OnlineProviders] for the configured endpoints.
+    pub fn providers(&self, cfg: Arc<RollupConfig>) -> Result<OnlineProviders> {
+        Ok(OnlineProviders::new(
+            cfg,
+            self.l1_rpc_url()?,
+            self.l2_rpc_url()?,
+            self.beacon_url.clone(),
+            self.blob_archiver_url.clone(),
+        ))
     }
 
-    /// Returns a new [AlloyL2ChainProvider] using the l2 rpc url.
-    pub fn l2_provider(&self, cfg: Arc<RollupConfig>) -> Result<AlloyL2ChainProvider> {
-        Ok(AlloyL2ChainProvider::new_http(self.l2_rpc_url()?, cfg))
-    }
-
-    /// Returns a new [StatefulAttributesBuilder] using the l1 and l2 providers.
-    pub fn attributes(
-        &self,
-        cfg: Arc<RollupConfig>,
-        l2_provider: &AlloyL2ChainProvider,
-        l1_provider: &AlloyChainProvider,
-    ) -> StatefulAttributesBuilder<AlloyChainProvider, AlloyL2ChainProvider> {
-        StatefulAttributesBuilder::new(cfg, l2_provider.clone(), l1_provider.clone())
-    }
-
-    /// Returns a new [OnlineBlobProviderWithFallback] using the beacon url.
-    pub fn blob_provider(
-        &self,
-    ) -> OnlineBlobProviderWithFallback<OnlineBeaconClient, OnlineBeaconClient, SimpleSlotDerivation>
-    {
-        OnlineBlobProviderBuilder::new()
-            .with_beacon_client(OnlineBeaconClient::new_http(self.beacon_url.clone()))
-            .build()
-    }
-
-    /// Returns a new [EthereumDataSource] using the l1 provider and blob provider.
-    pub fn dap(
-        &self,
-        l1_provider: AlloyChainProvider,
-        blob_provider: OnlineBlobProviderWithFallback<
-            OnlineBeaconClient,
-            OnlineBeaconClient,
-            SimpleSlotDerivation,
-        >,
-        cfg: &RollupConfig,
-    ) -> EthereumDataSource<
-        AlloyChainProvider,
-        OnlineBlobProviderWithFallback<
-            OnlineBeaconClient,
-            OnlineBeaconClient,
-            SimpleSlotDerivation,
-        >,
-    > {
-        EthereumDataSource::new(l1_provider, blob_provider, cfg)
-    }
-
-    /// Gets the rollup config from the l2 rpc url.
+    /// Gets the rollup config from the rollup config file, the superchain registry, or the
+    /// rollup node.
     pub async fn rollup_config(&self) -> Result<RollupConfig> {
-        let mut l2_provider =
-            AlloyL2ChainProvider::new_http(self.l2_rpc_url()?, Arc::new(Default::default()));
-        let l2_chain_id = l2_provider.chain_id().await.map_err(|e| eyre!(e))?;
-        let cfg = ROLLUP_CONFIGS
-            .get(&l2_chain_id)
-            .cloned()
-            .ok_or_else(|| eyre!("No rollup config found for L2 chain ID: {}", l2_chain_id))?;
-        Ok(cfg)
+        self.rollup.resolve(self.l2_rpc_url()?, None).await
     }
 
     /// Returns the l1 rpc url from CLI or environment variable.
@@ -305,9 +265,4 @@ impl FromL1 {
     pub fn l2_rpc_url(&self) -> Result<Url> {
         Url::parse(&self.l2_rpc_url).map_err(|e| eyre!(e))
     }
-
-    /// Returns the beacon url from CLI or environment variable.
-    pub fn beacon_url(&self) -> String {
-        self.beacon_url.clone()
-    }
 }
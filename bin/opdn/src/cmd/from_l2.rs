@@ -1,19 +1,28 @@
 //! From L2 Subcommand
 
+use crate::cmd::blobs::SidecarProvider;
+use crate::cmd::checkpoint::Checkpoint;
+use crate::offline::KonaDerivationFixture;
 use clap::{ArgAction, Parser};
 use color_eyre::{
     eyre::{ensure, eyre},
     Result,
 };
+use derivation_driver::{
+    online::{start_cursor, OnlineProviders},
+    DaServerClient, DerivationSession, FetchArgs, RecordingInputFetcher, RecordingL2Provider,
+    RollupConfigArgs, StallArgs,
+};
 use hashbrown::HashMap;
-use kona_derive::{errors::StageError, online::*};
-use kona_primitives::L2BlockInfo;
+use kona_derive::traits::{BlobProvider, ChainProvider, L2ChainProvider};
+use kona_primitives::RollupConfig;
 use op_test_vectors::derivation::DerivationFixture;
 use reqwest::Url;
+use rpc_cache::RpcCacheArgs;
+use std::fmt::Debug;
 use std::path::PathBuf;
 use std::sync::Arc;
-use superchain_registry::ROLLUP_CONFIGS;
-use tracing::{debug, error, info, trace, warn};
+use tracing::{info, trace};
 
 /// The logging target to use for [tracing].
 const TARGET: &str = "from-l2";
@@ -33,12 +42,58 @@ pub struct FromL2 {
     /// An L2 RPC URL to validate span batches.
     #[clap(long, help = "L2 RPC URL to validate span batches")]
     pub l2_rpc_url: String,
-    /// A beacon client to fetch blob data from.
-    #[clap(long, help = "Beacon client url to fetch blob data from")]
-    pub beacon_url: String,
+    /// Beacon clients to fetch blob data from. The healthiest one is used first.
+    #[clap(
+        long,
+        required = true,
+        value_delimiter = ',',
+        help = "Beacon client urls to fetch blob data from, repeated or comma separated"
+    )]
+    pub beacon_url: Vec<String>,
+    /// A blob archiver to fetch blobs past the beacon retention window from.
+    #[clap(
+        long,
+        help = "Blob archiver url to fetch blobs past the beacon retention window from"
+    )]
+    pub blob_archiver_url: Option<String>,
     /// The output file for the test fixture.
     #[clap(long, help = "Output file for the test fixture")]
     pub output: PathBuf,
+    /// A DA server to resolve alt-DA commitments with, for chains using alternative
+    /// data availability.
+    #[clap(long, help = "DA server url to resolve alt-DA commitments with")]
+    pub da_server_url: Option<String>,
+    /// Selects where the rollup config is loaded from.
+    #[command(flatten)]
+    pub rollup: RollupConfigArgs,
+    /// Store the KZG commitments and proofs of the blobs in the fixture.
+    #[clap(
+        long,
+        help = "Store the KZG commitments and proofs of the blobs in the fixture"
+    )]
+    pub kzg_proofs: bool,
+    /// Controls how L1 and L2 blocks are fetched.
+    #[command(flatten)]
+    pub fetch: FetchArgs,
+    /// Bounds how long derivation waits for progress before aborting.
+    #[command(flatten)]
+    pub stall: StallArgs,
+    /// A file to periodically write the progress of the run to.
+    #[clap(long, help = "File to periodically write the progress of the run to")]
+    pub checkpoint: Option<PathBuf>,
+    /// The number of L2 blocks derived, or L1 blocks fetched, between two checkpoints.
+    #[clap(long, default_value_t = 100, value_parser = clap::value_parser!(u64).range(1..), help = "Blocks between two checkpoints")]
+    pub checkpoint_interval: u64,
+    /// Resumes the run from the checkpoint file.
+    #[clap(
+        long,
+        requires = "checkpoint",
+        help = "Resume the run from the checkpoint file"
+    )]
+    pub resume: bool,
+    /// Records or replays the RPC and beacon responses.
+    #[command(flatten)]
+    pub rpc_cache: RpcCacheArgs,
     /// Verbosity level (0-4)
     #[arg(long, short, help = "Verbosity level (0-4)", action = ArgAction::Count)]
     pub v: u8,
@@ -46,268 +101,259 @@ pub struct FromL2 {
 
 impl FromL2 {
     /// Runs the from-l2 subcommand.
+    /// Endpoints are routed through the RPC cache if one is configured.
     pub async fn run(&self) -> Result<()> {
+        self.with_rpc_cache().await?.generate().await
+    }
+
+    /// Returns a copy of the command with its endpoints routed through the RPC cache.
+    pub async fn with_rpc_cache(&self) -> Result<Self> {
+        let mut cmd = self.clone();
+        cmd.l1_rpc_url = self.rpc_cache.route("l1", &self.l1_rpc_url).await?;
+        cmd.l2_rpc_url = self.rpc_cache.route("l2", &self.l2_rpc_url).await?;
+        cmd.beacon_url = self.rpc_cache.route_all("beacon", &self.beacon_url).await?;
+        if let Some(url) = &self.blob_archiver_url {
+            cmd.blob_archiver_url = Some(self.rpc_cache.route("blob-archiver", url).await?);
+        }
+        if let Some(url) = &self.da_server_url {
+            cmd.da_server_url = Some(self.rpc_cache.route("da-server", url).await?);
+        }
+        if let Some(url) = &self.rollup.rollup_rpc_url {
+            cmd.rollup.rollup_rpc_url = Some(self.rpc_cache.route("rollup", url).await?);
+        }
+        cmd.rpc_cache = RpcCacheArgs::default();
+        Ok(cmd)
+    }
+
+    /// Generates the derivation fixture for the L2 block range.
+    pub async fn generate(&self) -> Result<()> {
         ensure!(
             self.end_block > self.start_block,
             "End block before start block"
         );
         trace!(target: TARGET, "Producing derivation fixture for L2 block range [{}, {}]", self.start_block, self.end_block);
 
-        // Build the pipeline
         let cfg = Arc::new(self.rollup_config().await?);
-        let mut l1_provider = self.l1_provider()?;
-        let mut l2_provider = self.l2_provider(cfg.clone())?;
-        let attributes = self.attributes(cfg.clone(), &l2_provider, &l1_provider);
-        let mut blob_provider = self.blob_provider();
-        let dap = self.dap(l1_provider.clone(), blob_provider.clone(), &cfg);
-        let mut l2_cursor = self.cursor().await?;
-        let l1_tip = l1_provider
-            .block_info_by_number(l2_cursor.l1_origin.number)
-            .await
-            .expect("Failed to fetch genesis L1 block info for pipeline tip");
-        let mut pipeline = new_online_pipeline(
-            cfg.clone(),
-            l1_provider.clone(),
-            dap,
-            l2_provider.clone(),
-            attributes,
-            l1_tip,
-        );
-
-        // Collect the output payload attributes.
-        let mut payloads = HashMap::new();
+        let providers = self.providers(cfg.clone())?;
+        let fixture = self
+            .build(
+                cfg,
+                providers.l1,
+                providers.l2,
+                providers.blobs,
+                providers.sidecars,
+            )
+            .await?;
+        info!(target: TARGET, "Successfully built derivation test fixture");
 
-        // Collect reference payloads for span batch validation.
-        let mut ref_payloads = HashMap::new();
+        // Write the derivation fixture to the specified output location.
+        let file = std::fs::File::create(&self.output)?;
+        serde_json::to_writer_pretty(file, &fixture)?;
+        info!(target: TARGET, "Wrote derivation fixture to: {:?}", self.output);
 
-        // Track the earliest l1 block and last l1 block.
-        let first_l1_block = l2_cursor.l1_origin.number;
-        let mut last_l1_block = l2_cursor.block_info.number;
-        let mut l2_block_infos = HashMap::new();
-        let mut configs = HashMap::new();
-        let first_system_config = l2_provider
-            .system_config_by_number(l2_cursor.block_info.number, Arc::clone(&cfg))
-            .await
-            .map_err(|e| eyre!(e))?;
-        configs.insert(l2_cursor.block_info.number, first_system_config);
-        l2_block_infos.insert(l2_cursor.block_info.number, l2_cursor);
-        let start_l2_cursor = l2_cursor.block_info.number;
-
-        // TODO: Temporary patch to provide all span batch data to check.
-        // 100 blocks before the start block.
-        for i in (self.start_block.saturating_sub(500)..self.start_block).rev() {
-            let l2_block_info = l2_provider
-                .l2_block_info_by_number(i)
-                .await
-                .map_err(|e| eyre!(e))?;
-            let system_config = l2_provider
-                .system_config_by_number(i, Arc::clone(&cfg))
-                .await
-                .map_err(|e| eyre!(e))?;
-            configs.insert(i, system_config);
-            l2_block_infos.insert(i, l2_block_info);
-            // Get reference payloads by l2 block number for span batch validation
-            let l2_payload = l2_provider
-                .payload_by_number(i)
-                .await
-                .map_err(|e| eyre!(e))?;
-            ref_payloads.insert(i, crate::cmd::util::to_payload_attributes(l2_payload));
+        // The run is complete, so its checkpoint is no longer needed.
+        if let Some(path) = &self.checkpoint {
+            std::fs::remove_file(path)?;
         }
 
-        // Run the pipeline
-        loop {
-            // If the cursor is beyond the end block, break the loop.
-            if l2_cursor.block_info.number >= self.end_block {
-                trace!(target: TARGET, "Cursor is beyond the end block, breaking loop");
-                break;
-            }
+        Ok(())
+    }
 
-            // Step on the pipeline.
-            match pipeline.step(l2_cursor).await {
-                StepResult::PreparedAttributes => trace!(target: "loop", "Prepared attributes"),
-                StepResult::AdvancedOrigin => trace!(target: "loop", "Advanced origin"),
-                StepResult::OriginAdvanceErr(e) => {
-                    warn!(target: TARGET, "Could not advance origin: {:?}", e)
-                }
-                StepResult::StepFailed(e) => match e {
-                    StageError::NotEnoughData => {
-                        debug!(target: TARGET, "Not enough data to step derivation pipeline");
-                    }
-                    _ => {
-                        error!(target: TARGET, "Error stepping derivation pipeline: {:?}", e);
-                    }
-                },
+    /// Builds the derivation fixture for the L2 block range from the given providers,
+    /// resuming from the checkpoint file if requested, and checkpointing as it goes.
+    pub async fn build<C, L2, B, S>(
+        &self,
+        cfg: Arc<RollupConfig>,
+        l1_provider: C,
+        mut l2_provider: L2,
+        blob_provider: B,
+        sidecar_provider: S,
+    ) -> Result<KonaDerivationFixture>
+    where
+        C: ChainProvider + Clone + Send + Sync + Debug + 'static,
+        L2: L2ChainProvider + Clone + Send + Sync + Debug + 'static,
+        B: BlobProvider + Clone + Send + Sync + Debug + 'static,
+        S: SidecarProvider + Clone,
+    {
+        let fetcher = self.fetch.fetcher();
+        let mut checkpoint = match (&self.checkpoint, self.resume) {
+            (Some(path), true) => {
+                let checkpoint = Checkpoint::load(path, self.start_block, self.end_block)?;
+                info!(target: TARGET, "Resuming from checkpoint at L2 block {}", checkpoint.cursor);
+                checkpoint
             }
+            _ => self.start(&cfg, &mut l2_provider).await?,
+        };
 
-            // Get the attributes if there are some available.
-            let Some(attributes) = pipeline.next() else {
-                continue;
+        // Run the pipeline
+        if !checkpoint.derivation_complete {
+            let l2_cursor = *checkpoint
+                .fixture
+                .l2_block_infos
+                .get(&checkpoint.cursor)
+                .ok_or_else(|| eyre!("Checkpoint is missing the L2 block info of its cursor"))?;
+            // Record the L2 chain data the pipeline reads, such as the blocks that span
+            // batches are checked against, so that exactly those end up in the fixture.
+            let l2_recorder = RecordingL2Provider::new(l2_provider.clone());
+            // Likewise, record the alt-DA inputs the pipeline resolves.
+            let da_inputs = self
+                .da_server_url
+                .as_ref()
+                .map(|url| RecordingInputFetcher::new(DaServerClient::new(url)));
+            let session = match &da_inputs {
+                Some(inputs) => {
+                    DerivationSession::new_alt_da(
+                        cfg.clone(),
+                        l1_provider.clone(),
+                        l2_recorder.clone(),
+                        blob_provider.clone(),
+                        inputs.clone(),
+                        l2_cursor,
+                    )
+                    .await?
+                }
+                None => {
+                    DerivationSession::new(
+                        cfg.clone(),
+                        l1_provider.clone(),
+                        l2_recorder.clone(),
+                        blob_provider.clone(),
+                        l2_cursor,
+                    )
+                    .await?
+                }
             };
+            let mut session = session
+                .with_end_block(self.end_block)
+                .with_stall_args(&self.stall);
+
+            while let Some(block) = session.next_block().await? {
+                // Print the L1 range for this L2 Block.
+                let number = block.number();
+                info!(target: TARGET,
+                    "L2 Block [{}] L1 Range: [{}, {}]",
+                    number, block.l2_block_info.l1_origin.number, block.l1_origin.number
+                );
 
-            // Print the L1 range for this L2 Block.
-            let derived = attributes.parent.block_info.number as i64 + 1;
-            let l2_block_info = l2_provider
-                .l2_block_info_by_number(derived as u64)
-                .await
-                .map_err(|e| eyre!(e))?;
-            let origin = pipeline
-                .origin()
-                .ok_or(eyre!("Failed to get pipeline l1 origin"))?;
-            info!(target: TARGET,
-                "L2 Block [{}] L1 Range: [{}, {}]",
-                derived, l2_block_info.l1_origin.number, origin.number
-            );
-            payloads.insert(derived as u64, attributes.attributes);
-            last_l1_block = origin.number;
-
-            // Keep trying to advance the cursor in case the fetch fails.
-            loop {
-                match l2_provider
-                    .l2_block_info_by_number(l2_cursor.block_info.number + 1)
+                // Add the system config
+                let system_config = l2_provider
+                    .system_config_by_number(number, Arc::clone(&cfg))
                     .await
-                {
-                    Ok(bi) => {
-                        l2_cursor = bi;
-                        break;
-                    }
-                    Err(e) => {
-                        error!(target: TARGET, "Failed to fetch next pending l2 safe head: {}, err: {:?}", l2_cursor.block_info.number + 1, e);
-                        // Don't step on the pipeline if we failed to fetch the next l2 safe head.
-                        continue;
+                    .map_err(|e| eyre!(e))?;
+
+                let fixture = &mut checkpoint.fixture;
+                fixture
+                    .l2_payloads
+                    .insert(number, block.attributes.attributes);
+                fixture.l2_system_configs.insert(number, system_config);
+                fixture.l2_block_infos.insert(number, block.l2_block_info);
+                checkpoint.last_l1_block = block.l1_origin.number;
+                checkpoint.cursor = number;
+                if (number - self.start_block + 1) % self.checkpoint_interval == 0 {
+                    checkpoint.add_reads(l2_recorder.take());
+                    if let Some(inputs) = &da_inputs {
+                        checkpoint.add_alt_da_inputs(inputs.take());
                     }
+                    self.save_checkpoint(&checkpoint)?;
                 }
             }
-
-            // Add the system config
-            let system_config = l2_provider
-                .system_config_by_number(l2_cursor.block_info.number, Arc::clone(&cfg))
-                .await
-                .map_err(|e| eyre!(e))?;
-            configs.insert(l2_cursor.block_info.number, system_config);
-            l2_block_infos.insert(l2_cursor.block_info.number, l2_cursor);
+            checkpoint.add_reads(l2_recorder.take());
+            if let Some(inputs) = &da_inputs {
+                checkpoint.add_alt_da_inputs(inputs.take());
+            }
+            checkpoint.derivation_complete = true;
+            self.save_checkpoint(&checkpoint)?;
         }
 
-        // Take the full L1 range of blocks and get all needed data.
-        let l1_blocks = (first_l1_block..=last_l1_block).collect::<Vec<u64>>();
-
-        // Construct the fixture blocks.
-        let blocks = crate::cmd::build_fixture_blocks(
-            cfg.batch_inbox_address,
-            cfg.genesis
-                .system_config
-                .as_ref()
-                .map(|sc| sc.batcher_address)
-                .unwrap_or_default(),
-            &l1_blocks,
-            &mut l1_provider,
-            &mut blob_provider,
-        )
-        .await?;
-        let fixture = DerivationFixture {
-            rollup_config: Arc::unwrap_or_clone(cfg),
-            l1_blocks: blocks,
-            l2_payloads: payloads,
-            ref_payloads,
-            l2_system_configs: configs,
-            l2_block_infos,
-            l2_cursor_start: start_l2_cursor,
-            l2_cursor_end: self.end_block,
-        };
-        info!(target: TARGET, "Successfully built derivation test fixture");
-
-        // Write the derivation fixture to the specified output location.
-        let file = std::fs::File::create(&self.output)?;
-        serde_json::to_writer_pretty(file, &fixture)?;
-        info!(target: "from-l1", "Wrote derivation fixture to: {:?}", self.output);
-
-        Ok(())
-    }
-
-    /// Gets the L2 starting block number.
-    /// Returns the genesis L2 block number if the start block is less than the genesis block number.
-    pub fn start_block(&self, cfg: &RollupConfig) -> u64 {
-        if self.start_block < cfg.genesis.l2.number {
-            cfg.genesis.l2.number
-        } else if self.start_block != 0 {
-            self.start_block - 1
-        } else {
-            self.start_block
+        // Take the full L1 range of blocks and get all needed data, checkpointing
+        // after every chunk of blocks.
+        let remaining = checkpoint.remaining_l1_blocks().collect::<Vec<u64>>();
+        for chunk in remaining.chunks(self.checkpoint_interval as usize) {
+            let blocks = crate::cmd::build_fixture_blocks(
+                &cfg,
+                &mut checkpoint.batcher_system_config,
+                chunk,
+                &fetcher,
+                &l1_provider,
+                &sidecar_provider,
+                self.kzg_proofs,
+            )
+            .await?;
+            checkpoint.fixture.l1_blocks.extend(blocks);
+            self.save_checkpoint(&checkpoint)?;
         }
+        Ok(checkpoint.fixture)
     }
 
-    /// Returns an [L2BlockInfo] cursor for the pipeline.
-    pub async fn cursor(&self) -> Result<L2BlockInfo> {
-        let cfg = self.rollup_config().await?;
-        let start_block = self.start_block(&cfg);
-        let mut l2_provider = self.l2_provider(Arc::new(cfg))?;
-        let cursor = l2_provider
-            .l2_block_info_by_number(start_block)
+    /// Starts a new run at the starting L2 cursor.
+    pub async fn start<L2: L2ChainProvider>(
+        &self,
+        cfg: &Arc<RollupConfig>,
+        l2_provider: &mut L2,
+    ) -> Result<Checkpoint> {
+        let l2_cursor = start_cursor(l2_provider, cfg, self.start_block).await?;
+        let first_system_config = l2_provider
+            .system_config_by_number(l2_cursor.block_info.number, Arc::clone(cfg))
             .await
-            .map_err(|_| eyre!("Failed to fetch genesis L2 block info for pipeline cursor"))?;
-        Ok(cursor)
-    }
-
-    /// Returns a new [AlloyChainProvider] using the l1 rpc url.
-    pub fn l1_provider(&self) -> Result<AlloyChainProvider> {
-        Ok(AlloyChainProvider::new_http(self.l1_rpc_url()?))
-    }
+            .map_err(|e| eyre!(e))?;
 
-    /// Returns a new [AlloyL2ChainProvider] using the l2 rpc url.
-    pub fn l2_provider(&self, cfg: Arc<RollupConfig>) -> Result<AlloyL2ChainProvider> {
-        Ok(AlloyL2ChainProvider::new_http(self.l2_rpc_url()?, cfg))
-    }
+        let mut fixture = DerivationFixture {
+            rollup_config: cfg.as_ref().clone(),
+            l1_blocks: Vec::new(),
+            l2_payloads: HashMap::new(),
+            ref_payloads: HashMap::new(),
+            l2_system_configs: HashMap::new(),
+            l2_block_infos: HashMap::new(),
+            l2_cursor_start: l2_cursor.block_info.number,
+            l2_cursor_end: self.end_block,
+            alt_da_inputs: vec![],
+        };
+        fixture
+            .l2_system_configs
+            .insert(l2_cursor.block_info.number, first_system_config.clone());
+        fixture
+            .l2_block_infos
+            .insert(l2_cursor.block_info.number, l2_cursor);
 
-    /// Returns a new [StatefulAttributesBuilder] using the l1 and l2 providers.
-    pub fn attributes(
-        &self,
-        cfg: Arc<RollupConfig>,
-        l2_provider: &AlloyL2ChainProvider,
-        l1_provider: &AlloyChainProvider,
-    ) -> StatefulAttributesBuilder<AlloyChainProvider, AlloyL2ChainProvider> {
-        StatefulAttributesBuilder::new(cfg, l2_provider.clone(), l1_provider.clone())
+        // The L1 range starts at the L1 origin of the cursor, and the batcher address is
+        // tracked from the system config of the cursor.
+        let checkpoint = Checkpoint {
+            start_block: self.start_block,
+            end_block: self.end_block,
+            fixture,
+            cursor: l2_cursor.block_info.number,
+            derivation_complete: false,
+            first_l1_block: l2_cursor.l1_origin.number,
+            last_l1_block: l2_cursor.l1_origin.number,
+            batcher_system_config: first_system_config,
+        };
+        self.save_checkpoint(&checkpoint)?;
+        Ok(checkpoint)
     }
 
-    /// Returns a new [OnlineBlobProviderWithFallback] using the beacon url.
-    pub fn blob_provider(
-        &self,
-    ) -> OnlineBlobProviderWithFallback<OnlineBeaconClient, OnlineBeaconClient, SimpleSlotDerivation>
-    {
-        OnlineBlobProviderBuilder::new()
-            .with_beacon_client(OnlineBeaconClient::new_http(self.beacon_url.clone()))
-            .build()
+    /// Writes the checkpoint, if checkpoints are enabled.
+    pub fn save_checkpoint(&self, checkpoint: &Checkpoint) -> Result<()> {
+        match &self.checkpoint {
+            Some(path) => checkpoint.save(path),
+            None => Ok(()),
+        }
     }
 
-    /// Returns a new [EthereumDataSource] using the l1 provider and blob provider.
-    pub fn dap(
-        &self,
-        l1_provider: AlloyChainProvider,
-        blob_provider: OnlineBlobProviderWithFallback<
-            OnlineBeaconClient,
-            OnlineBeaconClient,
-            SimpleSlotDerivation,
-        >,
-        cfg: &RollupConfig,
-    ) -> EthereumDataSource<
-        AlloyChainProvider,
-        OnlineBlobProviderWithFallback<
-            OnlineBeaconClient,
-            OnlineBeaconClient,
-            SimpleSlotDerivation,
-        >,
-    > {
-        EthereumDataSource::new(l1_provider, blob_provider, cfg)
+    /// Returns the [OnlineProviders] for the configured endpoints.
+    pub fn providers(&self, cfg: Arc<RollupConfig>) -> Result<OnlineProviders> {
+        Ok(OnlineProviders::new(
+            cfg,
+            self.l1_rpc_url()?,
+            self.l2_rpc_url()?,
+            self.beacon_url.clone(),
+            self.blob_archiver_url.clone(),
+        ))
     }
 
-    /// Gets the rollup config from the l2 rpc url.
+    /// Gets the rollup config from the rollup config file, the superchain registry, or the
+    /// rollup node.
     pub async fn rollup_config(&self) -> Result<RollupConfig> {
-        let mut l2_provider =
-            AlloyL2ChainProvider::new_http(self.l2_rpc_url()?, Arc::new(Default::default()));
-        let l2_chain_id = l2_provider.chain_id().await.map_err(|e| eyre!(e))?;
-        let cfg = ROLLUP_CONFIGS
-            .get(&l2_chain_id)
-            .cloned()
-            .ok_or_else(|| eyre!("No rollup config found for L2 chain ID: {}", l2_chain_id))?;
-        Ok(cfg)
+        self.rollup.resolve(self.l2_rpc_url()?, None).await
     }
 
     /// Returns the l1 rpc url from CLI or environment variable.
@@ -319,9 +365,97 @@ impl FromL2 {
     pub fn l2_rpc_url(&self) -> Result<Url> {
         Url::parse(&self.l2_rpc_url).map_err(|e| eyre!(e))
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::offline::FixtureProvider;
+    use crate::synth::{tests::fixture, BatcherSettings};
+    use derivation_driver::StalledError;
+    use std::path::Path;
+
+    /// Returns a command over L2 blocks 1 to 6, checkpointing to the given file every
+    /// two blocks.
+    fn command(checkpoint: Option<&Path>, resume: bool) -> FromL2 {
+        let mut args = [
+            "from-l2",
+            "--start-block",
+            "1",
+            "--end-block",
+            "6",
+            "--l1-rpc-url",
+            "http://localhost:8545",
+            "--l2-rpc-url",
+            "http://localhost:9545",
+            "--beacon-url",
+            "http://localhost:5052",
+            "--output",
+            "fixture.json",
+            "--checkpoint-interval",
+            "2",
+            "--provider-attempts",
+            "1",
+        ]
+        .map(String::from)
+        .to_vec();
+        if let Some(path) = checkpoint {
+            args.extend(["--checkpoint".to_string(), path.display().to_string()]);
+        }
+        if resume {
+            args.push("--resume".to_string());
+        }
+        FromL2::try_parse_from(args).unwrap()
+    }
+
+    /// Builds the fixture of the command from the data of the given fixture.
+    async fn build(cmd: &FromL2, fixture: KonaDerivationFixture) -> Result<KonaDerivationFixture> {
+        let cfg = Arc::new(fixture.rollup_config.clone());
+        let provider = FixtureProvider::new(Arc::new(fixture));
+        cmd.build(
+            cfg,
+            provider.clone(),
+            provider.clone(),
+            provider.clone(),
+            provider,
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn test_resume_from_checkpoint() {
+        let batcher = BatcherSettings {
+            blocks_per_channel: 2,
+            ..Default::default()
+        };
+        let fixture = fixture(batcher, 6).await;
+
+        let uninterrupted = build(&command(None, false), fixture.clone()).await.unwrap();
+        assert_eq!(uninterrupted.l2_payloads, fixture.l2_payloads);
+        assert_eq!(
+            uninterrupted.l1_blocks[..],
+            fixture.l1_blocks[..uninterrupted.l1_blocks.len()]
+        );
+
+        let dir = std::env::temp_dir().join(format!("opdn-from-l2-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("checkpoint.json");
+
+        // Interrupt the run at L2 block 4, whose block info cannot be fetched. The last
+        // checkpoint is at block 2, so block 3 is derived again on resume.
+        let mut interrupted = fixture.clone();
+        interrupted.l2_block_infos.retain(|number, _| *number < 4);
+        let err = build(&command(Some(&path), false), interrupted)
+            .await
+            .unwrap_err();
+        assert!(err.downcast_ref::<StalledError>().is_some());
+        let checkpoint = Checkpoint::load(&path, 1, 6).unwrap();
+        assert_eq!(checkpoint.cursor, 2);
+        assert!(!checkpoint.derivation_complete);
+        assert_eq!(checkpoint.first_l1_block, 0);
 
-    /// Returns the beacon url from CLI or environment variable.
-    pub fn beacon_url(&self) -> String {
-        self.beacon_url.clone()
+        let resumed = build(&command(Some(&path), true), fixture).await.unwrap();
+        assert_eq!(resumed, uninterrupted);
+        std::fs::remove_dir_all(&dir).unwrap();
     }
 }
@@ -1,44 +1,583 @@
 //! Info Module
 
-use clap::{ArgAction, Parser};
-use color_eyre::eyre::{eyre, Result};
-use kona_derive::online::AlloyL2ChainProvider;
-use kona_derive::traits::L2ChainProvider;
+use alloy_primitives::{address, keccak256, Address, B256};
+use clap::{ArgAction, Parser, ValueEnum};
+use color_eyre::eyre::{bail, ensure, eyre, Result};
+use derivation_driver::{
+    assemble_channels,
+    online::{start_cursor, OnlineProviders},
+    posted_frames, Channel, DerivationSession, RecordingDataSource, RollupConfigArgs, StallArgs,
+};
+use kona_derive::online::{AlloyChainProvider, AlloyL2ChainProvider};
+use kona_derive::sources::EthereumDataSource;
+use kona_derive::traits::{ChainProvider, L2ChainProvider};
+use kona_primitives::{L2BlockInfo, RollupConfig, SystemConfig};
 use reqwest::Url;
+use rpc_cache::RpcCacheArgs;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
 use std::sync::Arc;
-use superchain_registry::ROLLUP_CONFIGS;
+use tracing::{debug, info, warn};
+
+/// The logging target to use for [tracing].
+const TARGET: &str = "info";
+
+/// The address of the `L2ToL1MessagePasser` predeploy, whose storage root is part of
+/// the output root.
+const L2_TO_L1_MESSAGE_PASSER: Address = address!("4200000000000000000000000000000000000016");
+
+/// The version of the output root computed by [output_root].
+const OUTPUT_VERSION_V0: B256 = B256::ZERO;
+
+/// The output format of the `info` subcommand.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum InfoFormat {
+    /// One row per L2 block.
+    #[default]
+    Table,
+    /// A JSON array with one object per L2 block.
+    Json,
+}
 
 /// CLI arguments for the `info` subcommand of `opdn`.
 #[derive(Parser, Clone, Debug)]
 pub struct Info {
-    /// The L2 Chain ID
-    #[clap(long, help = "L2 chain ID")]
-    l2_chain_id: u64,
-    /// The L2 block number to get info for
-    #[clap(long, help = "L2 block number")]
+    /// The L2 Chain ID. Defaults to the chain id of the RPC.
+    #[clap(long, help = "L2 chain ID, defaults to the chain ID of the RPC")]
+    l2_chain_id: Option<u64>,
+    /// The L2 block number to get info for, or the first block of the range.
+    #[clap(long, help = "L2 block number, or the first block of the range")]
     l2_block: u64,
+    /// The last L2 block of the range to get info for.
+    #[clap(
+        long,
+        help = "Last L2 block number of the range, defaults to the L2 block"
+    )]
+    end_block: Option<u64>,
     /// The rpc url to fetch L2 block info from.
     #[clap(long, help = "RPC url to fetch L2 block info from")]
     rpc_url: String,
+    /// An L1 RPC URL to fetch the details of the L1 origins from.
+    #[clap(long, help = "L1 RPC url to fetch the details of the L1 origins from")]
+    l1_rpc_url: Option<String>,
+    /// Beacon clients to fetch blob data from, to find the L1 blocks that carried the
+    /// batches. Requires the L1 RPC URL.
+    #[clap(
+        long,
+        value_delimiter = ',',
+        requires = "l1_rpc_url",
+        help = "Beacon client urls to find the L1 blocks that carried the batches with"
+    )]
+    beacon_url: Vec<String>,
+    /// A blob archiver to fetch blobs past the beacon retention window from.
+    #[clap(
+        long,
+        help = "Blob archiver url to fetch blobs past the beacon retention window from"
+    )]
+    blob_archiver_url: Option<String>,
+    /// The output format.
+    #[clap(long, value_enum, default_value_t, help = "Output format")]
+    pub format: InfoFormat,
+    /// Selects where the rollup config is loaded from.
+    #[command(flatten)]
+    pub rollup: RollupConfigArgs,
+    /// Bounds how long derivation waits for progress before aborting.
+    #[command(flatten)]
+    pub stall: StallArgs,
+    /// Records or replays the RPC responses.
+    #[command(flatten)]
+    pub rpc_cache: RpcCacheArgs,
     /// Verbosity level (0-4)
     #[arg(long, short, help = "Verbosity level (0-4)", action = ArgAction::Count)]
     pub v: u8,
 }
 
+/// The info of an L2 block.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockInfoReport {
+    /// The L2 block number.
+    pub number: u64,
+    /// The L2 block hash.
+    pub hash: B256,
+    /// The L2 parent block hash.
+    pub parent_hash: B256,
+    /// The L2 block timestamp.
+    pub timestamp: u64,
+    /// The sequence number of the block within its epoch.
+    pub seq_num: u64,
+    /// The L1 origin of the block.
+    pub l1_origin: L1Origin,
+    /// The system config at the block.
+    pub system_config: SystemConfig,
+    /// The L1 blocks the channel carrying the batch of the block was opened and closed
+    /// in. Only known if the L1 RPC and beacon URLs are given.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub batch_l1_range: Option<[u64; 2]>,
+    /// The output root of the block.
+    pub output_root: B256,
+}
+
+/// The L1 origin of an L2 block. The header details are only known if the L1 RPC URL
+/// is given.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct L1Origin {
+    /// The L1 block number.
+    pub number: u64,
+    /// The L1 block hash.
+    pub hash: B256,
+    /// The L1 parent block hash.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parent_hash: Option<B256>,
+    /// The L1 block timestamp.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<u64>,
+    /// The L1 block base fee.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub base_fee_per_gas: Option<u128>,
+}
+
 impl Info {
     /// Runs the info subcommand.
+    /// Endpoints are routed through the RPC cache if one is configured.
     pub async fn run(&self) -> Result<()> {
+        self.with_rpc_cache().await?.fetch().await
+    }
+
+    /// Returns a copy of the command with its endpoints routed through the RPC cache.
+    pub async fn with_rpc_cache(&self) -> Result<Self> {
+        let mut cmd = self.clone();
+        cmd.rpc_url = self.rpc_cache.route("l2", &self.rpc_url).await?;
+        if let Some(url) = &self.l1_rpc_url {
+            cmd.l1_rpc_url = Some(self.rpc_cache.route("l1", url).await?);
+        }
+        cmd.beacon_url = self.rpc_cache.route_all("beacon", &self.beacon_url).await?;
+        if let Some(url) = &self.blob_archiver_url {
+            cmd.blob_archiver_url = Some(self.rpc_cache.route("blob-archiver", url).await?);
+        }
+        if let Some(url) = &self.rollup.rollup_rpc_url {
+            cmd.rollup.rollup_rpc_url = Some(self.rpc_cache.route("rollup", url).await?);
+        }
+        cmd.rpc_cache = RpcCacheArgs::default();
+        Ok(cmd)
+    }
+
+    /// Fetches and prints the info of the L2 blocks in the range.
+    pub async fn fetch(&self) -> Result<()> {
+        let end_block = self.end_block.unwrap_or(self.l2_block);
+        ensure!(
+            end_block >= self.l2_block,
+            "End block must not come before the L2 block"
+        );
         let url = Url::parse(&self.rpc_url).map_err(|e| eyre!("Invalid RPC URL: {}", e))?;
-        let rollup_config = ROLLUP_CONFIGS
-            .get(&self.l2_chain_id)
-            .ok_or_else(|| eyre!("No rollup config found for chain id: {}", self.l2_chain_id))?;
-        let rollup_config = Arc::new(rollup_config.clone());
-        let mut provider = AlloyL2ChainProvider::new_http(url, rollup_config);
-        let info = provider
-            .l2_block_info_by_number(self.l2_block)
-            .await
-            .map_err(|e| eyre!("Failed to fetch block info: {}", e))?;
-        println!("{:#?}", info);
+        let rollup_config = Arc::new(self.rollup.resolve(url.clone(), self.l2_chain_id).await?);
+        let mut provider = AlloyL2ChainProvider::new_http(url.clone(), rollup_config.clone());
+        let mut l1_provider = self
+            .l1_rpc_url
+            .as_deref()
+            .map(|l1| Url::parse(l1).map_err(|e| eyre!("Invalid L1 RPC URL: {}", e)))
+            .transpose()?
+            .map(AlloyChainProvider::new_http);
+        let ranges = self
+            .batch_l1_ranges(rollup_config.clone(), end_block)
+            .await?;
+
+        let client = reqwest::Client::new();
+        let mut reports = Vec::new();
+        for number in self.l2_block..=end_block {
+            let info = provider
+                .l2_block_info_by_number(number)
+                .await
+                .map_err(|e| eyre!("Failed to fetch block info: {}", e))?;
+            let system_config = provider
+                .system_config_by_number(number, rollup_config.clone())
+                .await
+                .map_err(|e| eyre!("Failed to fetch system config: {}", e))?;
+            let l1_origin = match &mut l1_provider {
+                Some(l1_provider) => l1_origin(l1_provider, &info).await?,
+                None => L1Origin {
+                    number: info.l1_origin.number,
+                    hash: info.l1_origin.hash,
+                    parent_hash: None,
+                    timestamp: None,
+                    base_fee_per_gas: None,
+                },
+            };
+            reports.push(BlockInfoReport {
+                number,
+                hash: info.block_info.hash,
+                parent_hash: info.block_info.parent_hash,
+                timestamp: info.block_info.timestamp,
+                seq_num: info.seq_num,
+                l1_origin,
+                system_config,
+                batch_l1_range: ranges.get(&number).copied(),
+                output_root: output_root(&client, &url, number).await?,
+            });
+        }
+
+        match self.format {
+            InfoFormat::Table => print!("{}", table(&reports)),
+            InfoFormat::Json => println!("{}", serde_json::to_string_pretty(&reports)?),
+        }
         Ok(())
     }
+
+    /// Derives the L2 blocks up to the end block, and returns the L1 range of the
+    /// channel each was batched in, as range-finder does. Returns no ranges unless the
+    /// L1 RPC and beacon URLs are given.
+    async fn batch_l1_ranges(
+        &self,
+        cfg: Arc<RollupConfig>,
+        end_block: u64,
+    ) -> Result<HashMap<u64, [u64; 2]>> {
+        let Some(l1_rpc_url) = self.l1_rpc_url.as_deref() else {
+            return Ok(HashMap::new());
+        };
+        if self.beacon_url.is_empty() {
+            return Ok(HashMap::new());
+        }
+        let mut providers = OnlineProviders::new(
+            cfg.clone(),
+            Url::parse(l1_rpc_url).map_err(|e| eyre!("Invalid L1 RPC URL: {}", e))?,
+            Url::parse(&self.rpc_url).map_err(|e| eyre!("Invalid RPC URL: {}", e))?,
+            self.beacon_url.clone(),
+            self.blob_archiver_url.clone(),
+        );
+        let l2_cursor = start_cursor(&mut providers.l2, &cfg, self.l2_block).await?;
+        let dap = RecordingDataSource::new(EthereumDataSource::new(
+            providers.l1.clone(),
+            providers.blobs,
+            &cfg,
+        ));
+        let mut session = DerivationSession::with_data_source(
+            cfg.clone(),
+            providers.l1.clone(),
+            providers.l2,
+            dap.clone(),
+            l2_cursor,
+        )
+        .await?
+        .with_end_block(end_block)
+        .with_stall_args(&self.stall);
+        let mut timestamps = HashMap::new();
+        while let Some(block) = session.next_block().await? {
+            debug!(target: TARGET, "Derived L2 block {}", block.number());
+            timestamps.insert(block.attributes.attributes.timestamp, block.number());
+        }
+
+        // Find the channels the derived blocks were batched in.
+        let mut frames = Vec::new();
+        for data in dap.take().into_iter().filter(|d| !d.items.is_empty()) {
+            let (_, txs) = providers
+                .l1
+                .block_info_and_transactions_by_hash(data.block.hash)
+                .await
+                .map_err(|e| eyre!(e))?;
+            match posted_frames(&cfg, data.batcher, data.block.number, &txs, &data.items) {
+                Ok(posted) => frames.extend(posted),
+                Err(e) => warn!(target: TARGET, "Skipping batcher data: {e}"),
+            }
+        }
+        let ranges = channel_l1_ranges(&assemble_channels(&cfg, frames), &timestamps);
+        info!(target: TARGET, "Found the L1 ranges of {} L2 blocks", ranges.len());
+        Ok(ranges)
+    }
+}
+
+/// Returns the L1 blocks the channel of each L2 block was opened and closed in, keyed by
+/// L2 block number. `timestamps` maps the timestamps of the derived L2 blocks to their
+/// numbers. The first complete channel batching a block wins.
+fn channel_l1_ranges(
+    channels: &[Channel],
+    timestamps: &HashMap<u64, u64>,
+) -> HashMap<u64, [u64; 2]> {
+    let mut ranges = HashMap::new();
+    for channel in channels {
+        let Some(closed_in) = channel.closed_in else {
+            continue;
+        };
+        for batch in &channel.batches {
+            for block in batch.blocks() {
+                if let Some(&number) = timestamps.get(&block.timestamp) {
+                    ranges
+                        .entry(number)
+                        .or_insert([channel.opened_in, closed_in]);
+                }
+            }
+        }
+    }
+    ranges
+}
+
+/// Fetches the header details of the L1 origin of the block.
+async fn l1_origin(provider: &mut AlloyChainProvider, info: &L2BlockInfo) -> Result<L1Origin> {
+    let header = provider
+        .header_by_hash(info.l1_origin.hash)
+        .await
+        .map_err(|e| eyre!("Failed to fetch L1 origin header: {}", e))?;
+    Ok(L1Origin {
+        number: info.l1_origin.number,
+        hash: info.l1_origin.hash,
+        parent_hash: Some(header.parent_hash),
+        timestamp: Some(header.timestamp),
+        base_fee_per_gas: header.base_fee_per_gas,
+    })
+}
+
+/// Computes the version 0 output root of the L2 block, which commits to its state root,
+/// the storage root of the `L2ToL1MessagePasser` and its block hash.
+pub async fn output_root(client: &reqwest::Client, url: &Url, number: u64) -> Result<B256> {
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Block {
+        hash: B256,
+        state_root: B256,
+    }
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Proof {
+        storage_hash: B256,
+    }
+
+    let tag = format!("{number:#x}");
+    let block: Block = rpc(client, url, "eth_getBlockByNumber", json!([tag, false])).await?;
+    let proof: Proof = rpc(
+        client,
+        url,
+        "eth_getProof",
+        json!([L2_TO_L1_MESSAGE_PASSER, [], tag]),
+    )
+    .await?;
+    Ok(output_root_v0(
+        block.state_root,
+        proof.storage_hash,
+        block.hash,
+    ))
+}
+
+/// Hashes the version 0 output root from the state root, the storage root of the
+/// `L2ToL1MessagePasser` and the block hash.
+fn output_root_v0(state_root: B256, message_passer_storage_root: B256, block_hash: B256) -> B256 {
+    keccak256(
+        [
+            OUTPUT_VERSION_V0.as_slice(),
+            state_root.as_slice(),
+            message_passer_storage_root.as_slice(),
+            block_hash.as_slice(),
+        ]
+        .concat(),
+    )
+}
+
+/// Sends a JSON-RPC request, and returns its result.
+async fn rpc<T: DeserializeOwned>(
+    client: &reqwest::Client,
+    url: &Url,
+    method: &str,
+    params: Value,
+) -> Result<T> {
+    let request = json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": method,
+        "params": params,
+    });
+    let mut response: Value = client
+        .post(url.clone())
+        .json(&request)
+        .send()
+        .await
+        .map_err(|e| eyre!(e))?
+        .json()
+        .await
+        .map_err(|e| eyre!(e))?;
+    if let Some(error) = response.get("error") {
+        bail!("{method} returned an error: {error}");
+    }
+    serde_json::from_value(response["result"].take())
+        .map_err(|e| eyre!("Invalid {method} response: {e}"))
+}
+
+/// Formats the reports as a table with one row per L2 block.
+fn table(reports: &[BlockInfoReport]) -> String {
+    let header = [
+        "L2 BLOCK",
+        "HASH",
+        "TIMESTAMP",
+        "L1 ORIGIN",
+        "SEQ",
+        "BATCHER",
+        "BATCH L1 RANGE",
+        "OUTPUT ROOT",
+    ]
+    .map(String::from);
+    let rows = reports.iter().map(|r| {
+        [
+            r.number.to_string(),
+            r.hash.to_string(),
+            r.timestamp.to_string(),
+            r.l1_origin.number.to_string(),
+            r.seq_num.to_string(),
+            r.system_config.batcher_address.to_string(),
+            r.batch_l1_range
+                .map(|[start, end]| format!("[{start}, {end}]"))
+                .unwrap_or_else(|| "-".to_string()),
+            r.output_root.to_string(),
+        ]
+    });
+    let rows = std::iter::once(header).chain(rows).collect::<Vec<_>>();
+    let mut widths = [0; 8];
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.len());
+        }
+    }
+    let mut out = String::new();
+    for row in rows {
+        let line = row
+            .iter()
+            .zip(widths)
+            .map(|(cell, width)| format!("{cell:<width$}"))
+            .collect::<Vec<_>>()
+            .join("  ");
+        out.push_str(line.trim_end());
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::{b256, FixedBytes};
+    use derivation_driver::{BatchInfo, L2BlockRef};
+
+    fn block_ref(timestamp: u64) -> L2BlockRef {
+        L2BlockRef {
+            number: None,
+            timestamp,
+            epoch_num: 0,
+            tx_count: 0,
+        }
+    }
+
+    fn channel(id: u8, opened_in: u64, closed_in: Option<u64>, batches: Vec<BatchInfo>) -> Channel {
+        Channel {
+            id: FixedBytes::repeat_byte(id),
+            opened_in,
+            closed_in,
+            frames: vec![],
+            txs: vec![],
+            l1_blocks: vec![],
+            compressed_size: 0,
+            decompressed_size: None,
+            batches,
+            error: None,
+        }
+    }
+
+    fn single(timestamp: u64) -> BatchInfo {
+        BatchInfo::Single {
+            parent_hash: B256::ZERO,
+            block: block_ref(timestamp),
+        }
+    }
+
+    #[test]
+    fn test_channel_l1_ranges() {
+        let timestamps = HashMap::from([(2, 1), (4, 2), (6, 3), (8, 4)]);
+        let channels = [
+            channel(1, 10, Some(12), vec![single(2), single(4)]),
+            // Incomplete channels are skipped.
+            channel(2, 11, None, vec![single(6)]),
+            channel(
+                3,
+                13,
+                Some(13),
+                vec![BatchInfo::Span {
+                    parent_check: FixedBytes::ZERO,
+                    l1_origin_check: FixedBytes::ZERO,
+                    blocks: vec![block_ref(4), block_ref(6), block_ref(10)],
+                }],
+            ),
+        ];
+
+        let ranges = channel_l1_ranges(&channels, &timestamps);
+        assert_eq!(
+            ranges,
+            HashMap::from([(1, [10, 12]), (2, [10, 12]), (3, [13, 13])])
+        );
+    }
+
+    #[test]
+    fn test_output_root_v0() {
+        let state_root = b256!("1111111111111111111111111111111111111111111111111111111111111111");
+        let storage_root =
+            b256!("2222222222222222222222222222222222222222222222222222222222222222");
+        let block_hash = b256!("3333333333333333333333333333333333333333333333333333333333333333");
+        assert_eq!(
+            output_root_v0(state_root, storage_root, block_hash),
+            b256!("d50bf2ff34ced71be0d2f0be7c2433c6b39d9c3b16c95daf1ed6f24b7578a3b2")
+        );
+        assert_ne!(
+            output_root_v0(storage_root, state_root, block_hash),
+            output_root_v0(state_root, storage_root, block_hash)
+        );
+    }
+
+    fn report(number: u64, batch_l1_range: Option<[u64; 2]>) -> BlockInfoReport {
+        BlockInfoReport {
+            number,
+            hash: B256::repeat_byte(0xaa),
+            parent_hash: B256::ZERO,
+            timestamp: 1_700_000_000 + number * 2,
+            seq_num: number,
+            l1_origin: L1Origin {
+                number: 100,
+                hash: B256::ZERO,
+                parent_hash: None,
+                timestamp: None,
+                base_fee_per_gas: None,
+            },
+            system_config: SystemConfig {
+                batcher_address: address!("3333333333333333333333333333333333333333"),
+                ..Default::default()
+            },
+            batch_l1_range,
+            output_root: B256::repeat_byte(0xbb),
+        }
+    }
+
+    #[test]
+    fn test_table() {
+        let reports = [report(9, Some([100, 102])), report(10, None)];
+        let hash = B256::repeat_byte(0xaa).to_string();
+        let output_root = B256::repeat_byte(0xbb).to_string();
+        let batcher = address!("3333333333333333333333333333333333333333").to_string();
+        let hash_header = format!("{:<66}", "HASH");
+        let batcher_header = format!("{:<42}", "BATCHER");
+        let output = table(&reports);
+        let lines = output.lines().collect::<Vec<_>>();
+        assert_eq!(
+            lines,
+            [
+                format!(
+                    "L2 BLOCK  {hash_header}  TIMESTAMP   L1 ORIGIN  SEQ  {batcher_header}  \
+                     BATCH L1 RANGE  OUTPUT ROOT"
+                ),
+                format!(
+                    "9         {hash}  1700000018  100        9    {batcher}  [100, 102]      \
+                     {output_root}"
+                ),
+                format!(
+                    "10        {hash}  1700000020  100        10   {batcher}  -               \
+                     {output_root}"
+                ),
+            ]
+        );
+        assert!(output.ends_with('\n'));
+    }
 }
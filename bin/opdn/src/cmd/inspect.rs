@@ -0,0 +1,233 @@
+//! Contains logic to inspect the batcher data in a derivation fixture or an L1 block range.
+
+use crate::inspect::inspect;
+use crate::mutate::l1::{batcher_txs, decode_tx};
+use crate::offline::KonaDerivationFixture;
+use alloy_consensus::Transaction;
+use alloy_primitives::Bytes;
+use clap::{ArgAction, Parser, ValueEnum};
+use color_eyre::{
+    eyre::{ensure, eyre},
+    Result,
+};
+use derivation_driver::{
+    altda::TX_DATA_VERSION_1,
+    online::{start_cursor, OnlineProviders},
+    AltDaCommitment, DaServerClient, FetchArgs, InputFetcher, RollupConfigArgs,
+};
+use kona_derive::traits::L2ChainProvider;
+use op_test_vectors::derivation::DerivationFixture;
+use reqwest::Url;
+use rpc_cache::RpcCacheArgs;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tracing::{info, trace, warn};
+
+/// The logging target to use for [tracing].
+const TARGET: &str = "inspect";
+
+/// The output format of the `inspect` subcommand.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum InspectFormat {
+    /// A tree of L1 transactions, frames, channels and batches.
+    #[default]
+    Tree,
+    /// The decoded data as JSON.
+    Json,
+}
+
+/// CLI arguments for the `inspect` subcommand of `opdn`.
+#[derive(Parser, Clone, Debug)]
+pub struct Inspect {
+    /// The derivation fixture to inspect.
+    #[clap(
+        long,
+        conflicts_with = "start_block",
+        required_unless_present = "start_block",
+        help = "Derivation fixture to inspect"
+    )]
+    pub fixture: Option<PathBuf>,
+    /// The first L1 block to inspect, instead of a fixture.
+    #[clap(
+        short,
+        long,
+        requires_all = ["end_block", "l1_rpc_url", "l2_rpc_url", "beacon_url"],
+        help = "Starting L1 block number, to inspect an L1 block range instead of a fixture"
+    )]
+    pub start_block: Option<u64>,
+    /// The last L1 block to inspect.
+    #[clap(short, long, requires = "start_block", help = "Ending L1 block number")]
+    pub end_block: Option<u64>,
+    /// An L1 RPC URL to fetch L1 block data from.
+    #[clap(long, help = "RPC url to fetch L1 block data from")]
+    pub l1_rpc_url: Option<String>,
+    /// An L2 RPC URL to look up the batcher address at the start of the range.
+    #[clap(long, help = "L2 RPC URL to look up the batcher address from")]
+    pub l2_rpc_url: Option<String>,
+    /// Beacon clients to fetch blob data from. The healthiest one is used first.
+    #[clap(
+        long,
+        value_delimiter = ',',
+        help = "Beacon client urls to fetch blob data from, repeated or comma separated"
+    )]
+    pub beacon_url: Vec<String>,
+    /// A blob archiver to fetch blobs past the beacon retention window from.
+    #[clap(
+        long,
+        help = "Blob archiver url to fetch blobs past the beacon retention window from"
+    )]
+    pub blob_archiver_url: Option<String>,
+    /// A DA server to resolve alt-DA commitments with, for chains using alternative
+    /// data availability.
+    #[clap(long, help = "DA server url to resolve alt-DA commitments with")]
+    pub da_server_url: Option<String>,
+    /// Selects where the rollup config is loaded from.
+    #[command(flatten)]
+    pub rollup: RollupConfigArgs,
+    /// Controls how L1 blocks are fetched.
+    #[command(flatten)]
+    pub fetch: FetchArgs,
+    /// Records or replays the RPC and beacon responses.
+    #[command(flatten)]
+    pub rpc_cache: RpcCacheArgs,
+    /// The output format.
+    #[clap(long, value_enum, default_value_t, help = "Output format")]
+    pub format: InspectFormat,
+    /// Verbosity level (0-4)
+    #[arg(long, short, help = "Verbosity level (0-4)", action = ArgAction::Count)]
+    pub v: u8,
+}
+
+impl Inspect {
+    /// Runs the inspect subcommand.
+    /// Endpoints are routed through the RPC cache if one is configured.
+    pub async fn run(&self) -> Result<()> {
+        let fixture = match &self.fixture {
+            Some(path) => {
+                trace!(target: TARGET, "Reading derivation fixture from: {:?}", path);
+                serde_json::from_reader(std::fs::File::open(path)?)?
+            }
+            None => self.with_rpc_cache().await?.fetch_l1_range().await?,
+        };
+
+        let inspection = inspect(&fixture)?;
+        match self.format {
+            InspectFormat::Tree => print!("{inspection}"),
+            InspectFormat::Json => println!("{}", serde_json::to_string_pretty(&inspection)?),
+        }
+        Ok(())
+    }
+
+    /// Returns a copy of the command with its endpoints routed through the RPC cache.
+    pub async fn with_rpc_cache(&self) -> Result<Self> {
+        let mut cmd = self.clone();
+        if let Some(url) = &self.l1_rpc_url {
+            cmd.l1_rpc_url = Some(self.rpc_cache.route("l1", url).await?);
+        }
+        if let Some(url) = &self.l2_rpc_url {
+            cmd.l2_rpc_url = Some(self.rpc_cache.route("l2", url).await?);
+        }
+        cmd.beacon_url = self.rpc_cache.route_all("beacon", &self.beacon_url).await?;
+        if let Some(url) = &self.blob_archiver_url {
+            cmd.blob_archiver_url = Some(self.rpc_cache.route("blob-archiver", url).await?);
+        }
+        if let Some(url) = &self.da_server_url {
+            cmd.da_server_url = Some(self.rpc_cache.route("da-server", url).await?);
+        }
+        if let Some(url) = &self.rollup.rollup_rpc_url {
+            cmd.rollup.rollup_rpc_url = Some(self.rpc_cache.route("rollup", url).await?);
+        }
+        cmd.rpc_cache = RpcCacheArgs::default();
+        Ok(cmd)
+    }
+
+    /// Fetches the L1 blocks of the range into a fixture without any L2 data, resolving
+    /// the alt-DA commitments posted in the range if a DA server is configured.
+    async fn fetch_l1_range(&self) -> Result<KonaDerivationFixture> {
+        let (start_block, end_block) = self
+            .start_block
+            .zip(self.end_block)
+            .ok_or_else(|| eyre!("Either a fixture or an L1 block range is required"))?;
+        ensure!(
+            end_block >= start_block,
+            "End block must not come before the start block"
+        );
+        let url = |url: &Option<String>| {
+            Url::parse(url.as_deref().unwrap_or_default()).map_err(|e| eyre!(e))
+        };
+        let (l1_rpc_url, l2_rpc_url) = (url(&self.l1_rpc_url)?, url(&self.l2_rpc_url)?);
+        info!(target: TARGET, "Fetching L1 block range [{}, {}]", start_block, end_block);
+
+        let cfg = Arc::new(self.rollup.resolve(l2_rpc_url.clone(), None).await?);
+        let mut providers = OnlineProviders::new(
+            cfg.clone(),
+            l1_rpc_url,
+            l2_rpc_url,
+            self.beacon_url.clone(),
+            self.blob_archiver_url.clone(),
+        );
+        // The batcher address is tracked from the system config of the L2 block the
+        // range starts deriving from.
+        let l2_cursor = start_cursor(&mut providers.l2, &cfg, start_block).await?;
+        let mut system_config = providers
+            .l2
+            .system_config_by_number(l2_cursor.block_info.number, Arc::clone(&cfg))
+            .await
+            .map_err(|e| eyre!(e))?;
+        let blocks = (start_block..=end_block).collect::<Vec<_>>();
+        let l1_blocks = crate::cmd::build_fixture_blocks(
+            &cfg,
+            &mut system_config,
+            &blocks,
+            &self.fetch.fetcher(),
+            &providers.l1,
+            &providers.sidecars,
+            false,
+        )
+        .await?;
+
+        let mut fixture = DerivationFixture {
+            rollup_config: Arc::unwrap_or_clone(cfg),
+            l1_blocks,
+            l2_payloads: Default::default(),
+            ref_payloads: Default::default(),
+            l2_system_configs: Default::default(),
+            l2_block_infos: Default::default(),
+            l2_cursor_start: l2_cursor.block_info.number,
+            l2_cursor_end: l2_cursor.block_info.number,
+            alt_da_inputs: vec![],
+        };
+        if let Some(url) = &self.da_server_url {
+            let inputs = fetch_alt_da_inputs(&fixture, DaServerClient::new(url)).await?;
+            crate::cmd::fixtures::add_alt_da_inputs(&mut fixture.alt_da_inputs, inputs);
+        }
+        Ok(fixture)
+    }
+}
+
+/// Fetches the inputs of the alt-DA commitments posted in the fixture. Commitments that
+/// cannot be decoded or resolved are left out, and reported by the inspection.
+async fn fetch_alt_da_inputs(
+    fixture: &KonaDerivationFixture,
+    mut client: DaServerClient,
+) -> Result<HashMap<Bytes, Bytes>> {
+    let mut inputs = HashMap::new();
+    for tx_ref in batcher_txs(fixture)? {
+        let tx = decode_tx(&fixture.l1_blocks[tx_ref.block].transactions[tx_ref.tx])?;
+        let data = tx.input();
+        if data.first() != Some(&TX_DATA_VERSION_1) {
+            continue;
+        }
+        let Ok(commitment) = AltDaCommitment::decode(&data[1..]) else {
+            continue;
+        };
+        match client.get_input(&commitment).await {
+            Ok(input) => {
+                inputs.insert(commitment.encode(), input);
+            }
+            Err(e) => warn!(target: TARGET, "Failed to fetch alt-DA input: {e}"),
+        }
+    }
+    Ok(inputs)
+}
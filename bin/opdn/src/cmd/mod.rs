@@ -4,11 +4,18 @@ use clap::Parser;
 use color_eyre::eyre::{eyre, Result};
 use tracing::Level;
 
+pub mod batch;
 pub mod blobs;
+pub mod checkpoint;
+pub mod config_updates;
 pub mod fixtures;
 pub mod from_l1;
 pub mod from_l2;
 pub mod info;
+pub mod inspect;
+pub mod mutate;
+pub mod prune;
+pub mod synth;
 pub mod util;
 pub use fixtures::build_fixture_blocks;
 
@@ -30,6 +37,18 @@ pub enum Commands {
     FromL1(from_l1::FromL1),
     /// Gets the L2 block info including the l1 origin for the l2 block number.
     Info(info::Info),
+    /// Creates system config update test vectors from the L1 receipts of a block range.
+    ConfigUpdates(config_updates::ConfigUpdates),
+    /// Creates a derivation fixture offline from a list of L2 payloads and batcher settings.
+    Synth(synth::Synth),
+    /// Creates adversarial derivation fixtures by mutating an existing fixture.
+    Mutate(mutate::Mutate),
+    /// Prunes a derivation fixture down to the data derivation reads.
+    Prune(prune::Prune),
+    /// Decodes the batcher data of a derivation fixture or an L1 block range.
+    Inspect(inspect::Inspect),
+    /// Generates the derivation fixtures listed in a manifest that are not up to date.
+    Batch(batch::Batch),
 }
 
 impl Cli {
@@ -39,6 +58,12 @@ impl Cli {
             Commands::FromL2(cmd) => cmd.v,
             Commands::FromL1(cmd) => cmd.v,
             Commands::Info(cmd) => cmd.v,
+            Commands::ConfigUpdates(cmd) => cmd.v,
+            Commands::Synth(cmd) => cmd.v,
+            Commands::Mutate(cmd) => cmd.v,
+            Commands::Prune(cmd) => cmd.v,
+            Commands::Inspect(cmd) => cmd.v,
+            Commands::Batch(cmd) => cmd.v,
         }
     }
 
@@ -64,6 +89,12 @@ impl Cli {
             Commands::FromL2(cmd) => cmd.run().await,
             Commands::FromL1(cmd) => cmd.run().await,
             Commands::Info(cmd) => cmd.run().await,
+            Commands::ConfigUpdates(cmd) => cmd.run().await,
+            Commands::Synth(cmd) => cmd.run().await,
+            Commands::Mutate(cmd) => cmd.run().await,
+            Commands::Prune(cmd) => cmd.run().await,
+            Commands::Inspect(cmd) => cmd.run().await,
+            Commands::Batch(cmd) => cmd.run().await,
         }
     }
 }
@@ -0,0 +1,70 @@
+//! Contains logic to derive adversarial fixtures from an existing derivation fixture.
+
+use crate::mutate::{mutate, Mutation};
+use crate::offline::KonaDerivationFixture;
+use clap::{ArgAction, Parser, ValueEnum};
+use color_eyre::{eyre::WrapErr, Result};
+use std::path::PathBuf;
+use tracing::{info, trace};
+
+/// The logging target to use for [tracing].
+const TARGET: &str = "mutate";
+
+/// CLI arguments for the `mutate` subcommand of `opdn`.
+#[derive(Parser, Clone, Debug)]
+pub struct Mutate {
+    /// The derivation fixture to mutate.
+    #[clap(long, help = "Derivation fixture to mutate")]
+    pub fixture: PathBuf,
+    /// The mutations to apply. Each mutation produces a separate fixture.
+    #[clap(
+        long,
+        value_enum,
+        value_delimiter = ',',
+        help = "Mutations to apply, defaults to all"
+    )]
+    pub mutation: Vec<Mutation>,
+    /// The directory to write the mutated fixtures to.
+    #[clap(long, help = "Output directory for the mutated fixtures")]
+    pub output_dir: PathBuf,
+    /// Verbosity level (0-4)
+    #[arg(long, short, help = "Verbosity level (0-4)", action = ArgAction::Count)]
+    pub v: u8,
+}
+
+impl Mutate {
+    /// Runs the mutate subcommand.
+    ///
+    /// Writes one fixture per mutation to `<output-dir>/<fixture>-<mutation>.json`.
+    /// Fails if any of the mutations does not apply to the fixture.
+    pub async fn run(&self) -> Result<()> {
+        trace!(target: TARGET, "Reading derivation fixture from: {:?}", self.fixture);
+        let fixture: KonaDerivationFixture =
+            serde_json::from_reader(std::fs::File::open(&self.fixture)?)?;
+        let stem = self
+            .fixture
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_else(|| "fixture".to_string());
+        std::fs::create_dir_all(&self.output_dir)?;
+
+        let mutations = if self.mutation.is_empty() {
+            Mutation::value_variants().to_vec()
+        } else {
+            self.mutation.clone()
+        };
+        for mutation in mutations {
+            let mutated = mutate(&fixture, mutation)
+                .await
+                .wrap_err_with(|| format!("Failed to apply mutation {}", mutation.name()))?;
+            let output = self
+                .output_dir
+                .join(format!("{stem}-{}.json", mutation.name()));
+            let file = std::fs::File::create(&output)?;
+            serde_json::to_writer_pretty(file, &mutated)?;
+            info!(target: TARGET, "Wrote mutated fixture to: {:?}", output);
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,53 @@
+//! Contains logic to prune a derivation fixture down to the data derivation reads.
+
+use crate::offline::KonaDerivationFixture;
+use crate::prune::prune;
+use clap::{ArgAction, Parser};
+use color_eyre::Result;
+use std::path::PathBuf;
+use tracing::{info, trace};
+
+/// The logging target to use for [tracing].
+const TARGET: &str = "prune";
+
+/// CLI arguments for the `prune` subcommand of `opdn`.
+#[derive(Parser, Clone, Debug)]
+pub struct Prune {
+    /// The derivation fixture to prune.
+    #[clap(long, help = "Derivation fixture to prune")]
+    pub fixture: PathBuf,
+    /// The output file for the pruned fixture.
+    #[clap(long, help = "Output file for the pruned fixture")]
+    pub output: PathBuf,
+    /// Verbosity level (0-4)
+    #[arg(long, short, help = "Verbosity level (0-4)", action = ArgAction::Count)]
+    pub v: u8,
+}
+
+impl Prune {
+    /// Runs the prune subcommand.
+    pub async fn run(&self) -> Result<()> {
+        trace!(target: TARGET, "Reading derivation fixture from: {:?}", self.fixture);
+        let fixture: KonaDerivationFixture =
+            serde_json::from_reader(std::fs::File::open(&self.fixture)?)?;
+
+        let pruned = prune(&fixture).await?;
+        let transactions = |f: &KonaDerivationFixture| {
+            f.l1_blocks
+                .iter()
+                .map(|b| b.transactions.len())
+                .sum::<usize>()
+        };
+        info!(target: TARGET,
+            "Pruned {} of {} L1 transactions",
+            transactions(&fixture) - transactions(&pruned),
+            transactions(&fixture)
+        );
+
+        let file = std::fs::File::create(&self.output)?;
+        serde_json::to_writer_pretty(file, &pruned)?;
+        info!(target: TARGET, "Wrote pruned fixture to: {:?}", self.output);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,42 @@
+//! Contains logic to build a synthetic derivation fixture from L2 payloads.
+
+use crate::synth::{SynthFixtureBuilder, SynthSpec};
+use clap::{ArgAction, Parser};
+use color_eyre::Result;
+use std::path::PathBuf;
+use tracing::{info, trace};
+
+/// The logging target to use for [tracing].
+const TARGET: &str = "synth";
+
+/// CLI arguments for the `synth` subcommand of `opdn`.
+#[derive(Parser, Clone, Debug)]
+pub struct Synth {
+    /// The spec file containing the rollup config, batcher settings and L2 payloads.
+    #[clap(
+        long,
+        help = "JSON spec with the rollup config, batcher settings and L2 payloads"
+    )]
+    pub spec: PathBuf,
+    /// The output file for the test fixture.
+    #[clap(long, help = "Output file for the test fixture")]
+    pub output: PathBuf,
+    /// Verbosity level (0-4)
+    #[arg(long, short, help = "Verbosity level (0-4)", action = ArgAction::Count)]
+    pub v: u8,
+}
+
+impl Synth {
+    /// Runs the synth subcommand.
+    pub async fn run(&self) -> Result<()> {
+        trace!(target: TARGET, "Reading synth spec from: {:?}", self.spec);
+        let spec: SynthSpec = serde_json::from_reader(std::fs::File::open(&self.spec)?)?;
+        let fixture = SynthFixtureBuilder::from(spec).build().await?;
+
+        let file = std::fs::File::create(&self.output)?;
+        serde_json::to_writer_pretty(file, &fixture)?;
+        info!(target: TARGET, "Wrote derivation fixture to: {:?}", self.output);
+
+        Ok(())
+    }
+}
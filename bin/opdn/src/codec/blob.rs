@@ -0,0 +1,359 @@
+//! Encoding of batcher data into EIP-4844 blobs.
+
+use alloy_eips::eip4844::{env_settings::EnvKzgSettings, kzg_to_versioned_hash};
+use alloy_primitives::{FixedBytes, B256};
+use color_eyre::{
+    eyre::{bail, ensure, eyre},
+    Result,
+};
+use kona_primitives::Blob;
+
+/// The size of a blob in bytes.
+pub const BLOB_SIZE: usize = 131072;
+
+/// The blob encoding version.
+pub const BLOB_ENCODING_VERSION: u8 = 0;
+
+/// The number of encoding rounds in a blob. Each round encodes 127 bytes of data
+/// into 4 field elements.
+pub const BLOB_ENCODING_ROUNDS: usize = 1024;
+
+/// The maximum number of data bytes that fit into a single blob.
+pub const MAX_BLOB_DATA_SIZE: usize = (4 * 31 + 3) * BLOB_ENCODING_ROUNDS - 4;
+
+/// Encodes the given data into a blob using the OP Stack blob encoding.
+///
+/// Each field element carries 31 full bytes of data, plus 6 bits packed into its
+/// first byte so that it stays below the BLS modulus. The first field element
+/// starts with the encoding version and the 3 byte big-endian data length.
+pub fn encode(data: &[u8]) -> Result<Box<Blob>> {
+    ensure!(
+        data.len() <= MAX_BLOB_DATA_SIZE,
+        "Data too large for a blob: {} > {}",
+        data.len(),
+        MAX_BLOB_DATA_SIZE
+    );
+
+    let mut blob = Box::new(Blob::ZERO);
+    let mut writer = BlobWriter {
+        blob: &mut blob,
+        data,
+        read_offset: 0,
+        write_offset: 0,
+        buf: [0u8; 31],
+    };
+
+    let mut round = 0;
+    while round < BLOB_ENCODING_ROUNDS && writer.read_offset < data.len() {
+        if round == 0 {
+            writer.buf[0] = BLOB_ENCODING_VERSION;
+            let len = data.len() as u32;
+            writer.buf[1..4].copy_from_slice(&len.to_be_bytes()[1..]);
+            let n = data.len().min(27);
+            writer.buf[4..4 + n].copy_from_slice(&data[..n]);
+            writer.read_offset += n;
+        } else {
+            writer.read31();
+        }
+
+        let x = writer.read1();
+        writer.write1(x & 0b0011_1111);
+        writer.write31();
+
+        writer.read31();
+        let y = writer.read1();
+        writer.write1((y & 0b0000_1111) | ((x & 0b1100_0000) >> 2));
+        writer.write31();
+
+        writer.read31();
+        let z = writer.read1();
+        writer.write1(z & 0b0011_1111);
+        writer.write31();
+
+        writer.read31();
+        writer.write1(((z & 0b1100_0000) >> 2) | ((y & 0b1111_0000) >> 4));
+        writer.write31();
+
+        round += 1;
+    }
+
+    Ok(blob)
+}
+
+/// Decodes the data encoded into a blob using the OP Stack blob encoding.
+pub fn decode(blob: &Blob) -> Result<Vec<u8>> {
+    ensure!(
+        blob[1] == BLOB_ENCODING_VERSION,
+        "Unsupported blob encoding version: {}",
+        blob[1]
+    );
+    let len = u32::from_be_bytes([0, blob[2], blob[3], blob[4]]) as usize;
+    ensure!(
+        len <= MAX_BLOB_DATA_SIZE,
+        "Blob data length {len} exceeds the maximum of {MAX_BLOB_DATA_SIZE}"
+    );
+
+    // The first field element carries the version and length in place of data.
+    let mut output = vec![0u8; MAX_BLOB_DATA_SIZE];
+    output[..27].copy_from_slice(&blob[5..32]);
+    let mut reader = BlobReader {
+        blob,
+        output: &mut output,
+        read_offset: 32,
+        write_offset: 28,
+    };
+    let mut encoded = [blob[0], 0, 0, 0];
+    for byte in encoded.iter_mut().skip(1) {
+        *byte = reader.read_field_element()?;
+    }
+    reader.reassemble(&encoded);
+
+    let mut round = 1;
+    while round < BLOB_ENCODING_ROUNDS && reader.write_offset < len {
+        for byte in encoded.iter_mut() {
+            *byte = reader.read_field_element()?;
+        }
+        reader.reassemble(&encoded);
+        round += 1;
+    }
+
+    let read_offset = reader.read_offset;
+    if output[len..].iter().any(|b| *b != 0) {
+        bail!("Blob contains data beyond its declared length of {len}");
+    }
+    if blob[read_offset..].iter().any(|b| *b != 0) {
+        bail!("Blob contains trailing data after offset {read_offset}");
+    }
+    output.truncate(len);
+    Ok(output)
+}
+
+/// Computes the EIP-4844 versioned hash of the given blob.
+pub fn versioned_hash(blob: &Blob) -> Result<B256> {
+    let kzg_blob = c_kzg::Blob::from_bytes(blob.as_slice()).map_err(|e| eyre!("{e:?}"))?;
+    let commitment =
+        c_kzg::KzgCommitment::blob_to_kzg_commitment(&kzg_blob, EnvKzgSettings::Default.get())
+            .map_err(|e| eyre!("Failed to compute blob commitment: {e:?}"))?;
+    Ok(kzg_to_versioned_hash(commitment.to_bytes().as_slice()))
+}
+
+/// The KZG commitment and proof of a blob.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlobKzg {
+    /// The KZG commitment to the blob.
+    pub commitment: FixedBytes<48>,
+    /// The KZG proof of the blob against the commitment.
+    pub proof: FixedBytes<48>,
+}
+
+/// Computes the KZG commitment and proof of the given blob.
+pub fn commit(blob: &Blob) -> Result<BlobKzg> {
+    let settings = EnvKzgSettings::Default.get();
+    let kzg_blob = c_kzg::Blob::from_bytes(blob.as_slice()).map_err(|e| eyre!("{e:?}"))?;
+    let commitment = c_kzg::KzgCommitment::blob_to_kzg_commitment(&kzg_blob, settings)
+        .map_err(|e| eyre!("Failed to compute blob commitment: {e:?}"))?
+        .to_bytes();
+    let proof = c_kzg::KzgProof::compute_blob_kzg_proof(&kzg_blob, &commitment, settings)
+        .map_err(|e| eyre!("Failed to compute blob proof: {e:?}"))?
+        .to_bytes();
+    Ok(BlobKzg {
+        commitment: FixedBytes::from_slice(commitment.as_slice()),
+        proof: FixedBytes::from_slice(proof.as_slice()),
+    })
+}
+
+/// Verifies a blob, along with the KZG commitment and proof served with it, against the
+/// versioned hash the blob is referenced by.
+///
+/// The commitment must hash to the versioned hash, and the proof must verify the blob
+/// against the commitment. A blob that does not match its versioned hash is rejected.
+pub fn verify(blob: &Blob, kzg: &BlobKzg, versioned_hash: B256) -> Result<()> {
+    let computed = kzg_to_versioned_hash(kzg.commitment.as_slice());
+    ensure!(
+        computed == versioned_hash,
+        "Blob commitment hashes to {computed}, expected versioned hash {versioned_hash}"
+    );
+    let kzg_blob = c_kzg::Blob::from_bytes(blob.as_slice()).map_err(|e| eyre!("{e:?}"))?;
+    let commitment = c_kzg::Bytes48::from_bytes(kzg.commitment.as_slice())
+        .map_err(|e| eyre!("Invalid blob commitment: {e:?}"))?;
+    let proof = c_kzg::Bytes48::from_bytes(kzg.proof.as_slice())
+        .map_err(|e| eyre!("Invalid blob proof: {e:?}"))?;
+    let valid = c_kzg::KzgProof::verify_blob_kzg_proof(
+        &kzg_blob,
+        &commitment,
+        &proof,
+        EnvKzgSettings::Default.get(),
+    )
+    .map_err(|e| eyre!("Failed to verify blob proof: {e:?}"))?;
+    ensure!(
+        valid,
+        "Blob proof does not verify against commitment for {versioned_hash}"
+    );
+    Ok(())
+}
+
+/// Tracks read and write positions while encoding data into a blob.
+struct BlobWriter<'a> {
+    blob: &'a mut Blob,
+    data: &'a [u8],
+    read_offset: usize,
+    write_offset: usize,
+    buf: [u8; 31],
+}
+
+impl BlobWriter<'_> {
+    /// Reads a single byte of data, or zero past the end of the data.
+    fn read1(&mut self) -> u8 {
+        let Some(byte) = self.data.get(self.read_offset) else {
+            return 0;
+        };
+        self.read_offset += 1;
+        *byte
+    }
+
+    /// Reads up to 31 bytes of data into the buffer, zero padding past the end of the data.
+    fn read31(&mut self) {
+        self.buf = [0u8; 31];
+        if self.read_offset >= self.data.len() {
+            return;
+        }
+        let n = (self.data.len() - self.read_offset).min(31);
+        self.buf[..n].copy_from_slice(&self.data[self.read_offset..self.read_offset + n]);
+        self.read_offset += n;
+    }
+
+    /// Writes the first byte of a field element.
+    fn write1(&mut self, byte: u8) {
+        debug_assert_eq!(self.write_offset % 32, 0);
+        self.blob[self.write_offset] = byte;
+        self.write_offset += 1;
+    }
+
+    /// Writes the buffered 31 bytes as the remainder of a field element.
+    fn write31(&mut self) {
+        debug_assert_eq!(self.write_offset % 32, 1);
+        self.blob[self.write_offset..self.write_offset + 31].copy_from_slice(&self.buf);
+        self.write_offset += 31;
+    }
+}
+
+/// Tracks read and write positions while decoding data from a blob.
+struct BlobReader<'a> {
+    blob: &'a Blob,
+    output: &'a mut [u8],
+    read_offset: usize,
+    write_offset: usize,
+}
+
+impl BlobReader<'_> {
+    /// Copies the 31 data bytes of the next field element to the output and returns
+    /// its first byte.
+    fn read_field_element(&mut self) -> Result<u8> {
+        let first = self.blob[self.read_offset];
+        ensure!(
+            first & 0b1100_0000 == 0,
+            "Invalid field element at offset {}",
+            self.read_offset
+        );
+        self.output[self.write_offset..self.write_offset + 31]
+            .copy_from_slice(&self.blob[self.read_offset + 1..self.read_offset + 32]);
+        self.read_offset += 32;
+        self.write_offset += 32;
+        Ok(first)
+    }
+
+    /// Reassembles the 3 data bytes packed into the first bytes of the last four
+    /// field elements, and places them in the gaps left in the output.
+    fn reassemble(&mut self, encoded: &[u8; 4]) {
+        self.write_offset -= 1;
+        let x = (encoded[0] & 0b0011_1111) | ((encoded[1] & 0b0011_0000) << 2);
+        let y = (encoded[1] & 0b0000_1111) | ((encoded[3] & 0b0000_1111) << 4);
+        let z = (encoded[2] & 0b0011_1111) | ((encoded[3] & 0b0011_0000) << 2);
+        self.output[self.write_offset - 32] = z;
+        self.output[self.write_offset - 64] = y;
+        self.output[self.write_offset - 96] = x;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn data(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i % 256) as u8).collect()
+    }
+
+    #[test]
+    fn test_blob_roundtrip() {
+        for len in [0, 1, 27, 28, 127, 128, 4096, MAX_BLOB_DATA_SIZE] {
+            let data = data(len);
+            let blob = encode(&data).unwrap();
+            assert_eq!(decode(&blob).unwrap(), data, "roundtrip of {len} bytes");
+        }
+    }
+
+    #[test]
+    fn test_blob_field_elements_below_modulus() {
+        let blob = encode(&[0xff; MAX_BLOB_DATA_SIZE]).unwrap();
+        assert!(blob.chunks(32).all(|fe| fe[0] & 0b1100_0000 == 0));
+        assert_eq!(decode(&blob).unwrap(), vec![0xff; MAX_BLOB_DATA_SIZE]);
+    }
+
+    #[test]
+    fn test_encode_too_large() {
+        assert!(encode(&data(MAX_BLOB_DATA_SIZE + 1)).is_err());
+    }
+
+    #[test]
+    fn test_decode_rejects_malformed_blobs() {
+        let blob = encode(&data(100)).unwrap();
+
+        let mut version = blob.clone();
+        version[1] = 1;
+        assert!(decode(&version).is_err());
+
+        let mut length = blob.clone();
+        length[2..5].copy_from_slice(&[0xff, 0xff, 0xff]);
+        assert!(decode(&length).is_err());
+
+        let mut field_element = blob.clone();
+        field_element[32] = 0b1000_0000;
+        assert!(decode(&field_element).is_err());
+
+        let mut trailing = blob;
+        trailing[BLOB_SIZE - 1] = 1;
+        assert!(decode(&trailing).is_err());
+    }
+
+    #[test]
+    fn test_verify() {
+        let blob = encode(&data(1000)).unwrap();
+        let kzg = commit(&blob).unwrap();
+        let hash = kzg_to_versioned_hash(kzg.commitment.as_slice());
+        assert_eq!(versioned_hash(&blob).unwrap(), hash);
+        verify(&blob, &kzg, hash).unwrap();
+
+        // The commitment and proof of another blob.
+        let other = commit(&encode(&data(1001)).unwrap()).unwrap();
+
+        let wrong_commitment = BlobKzg {
+            commitment: other.commitment,
+            ..kzg
+        };
+        assert!(verify(&blob, &wrong_commitment, hash).is_err());
+        // A commitment matching another versioned hash still fails the proof.
+        let other_hash = kzg_to_versioned_hash(other.commitment.as_slice());
+        assert!(verify(&blob, &wrong_commitment, other_hash).is_err());
+
+        let wrong_proof = BlobKzg {
+            proof: other.proof,
+            ..kzg
+        };
+        assert!(verify(&blob, &wrong_proof, hash).is_err());
+
+        // A blob that does not match its commitment is rejected.
+        let mut tampered = blob.clone();
+        tampered[100] ^= 1;
+        assert!(verify(&tampered, &kzg, hash).is_err());
+    }
+}
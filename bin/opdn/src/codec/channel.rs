@@ -0,0 +1,152 @@
+//! Encoding of batches into compressed channels and frames.
+
+use crate::codec::{DERIVATION_VERSION_0, FRAME_OVERHEAD, SINGLE_BATCH_TYPE, SPAN_BATCH_TYPE};
+use alloy_primitives::Bytes;
+use alloy_rlp::Encodable;
+use color_eyre::{
+    eyre::{ensure, eyre},
+    Result,
+};
+use kona_derive::batch::{SingleBatch, SpanBatch};
+use kona_primitives::Frame;
+
+/// The compression level used for zlib compressed channels.
+const ZLIB_COMPRESSION_LEVEL: u8 = 9;
+
+/// A channel identifier.
+pub type ChannelId = [u8; 16];
+
+/// Appends a [SingleBatch] to the uncompressed channel data.
+pub fn push_single_batch(batch: &SingleBatch, channel: &mut Vec<u8>) {
+    let mut encoded = vec![SINGLE_BATCH_TYPE];
+    batch.encode(&mut encoded);
+    encoded.as_slice().encode(channel);
+}
+
+/// Appends a [SpanBatch] to the uncompressed channel data.
+pub fn push_span_batch(batch: &SpanBatch, channel: &mut Vec<u8>) -> Result<()> {
+    let raw = batch
+        .to_raw_span_batch()
+        .map_err(|e| eyre!("Failed to convert span batch: {e:?}"))?;
+    let mut encoded = vec![SPAN_BATCH_TYPE];
+    raw.encode(&mut encoded)
+        .map_err(|e| eyre!("Failed to encode span batch: {e:?}"))?;
+    encoded.as_slice().encode(channel);
+    Ok(())
+}
+
+/// Compresses the uncompressed channel data using zlib.
+pub fn compress(channel: &[u8]) -> Vec<u8> {
+    miniz_oxide::deflate::compress_to_vec_zlib(channel, ZLIB_COMPRESSION_LEVEL)
+}
+
+/// Splits the compressed channel data into [Frame]s whose encoding is at most
+/// `max_frame_size` bytes.
+pub fn split_frames(id: ChannelId, data: &[u8], max_frame_size: usize) -> Result<Vec<Frame>> {
+    ensure!(
+        max_frame_size > FRAME_OVERHEAD,
+        "Max frame size must exceed the frame overhead of {FRAME_OVERHEAD} bytes"
+    );
+    let chunk_size = max_frame_size - FRAME_OVERHEAD;
+    let chunks = data.chunks(chunk_size).collect::<Vec<_>>();
+    ensure!(
+        chunks.len() <= u16::MAX as usize,
+        "Channel requires more than {} frames",
+        u16::MAX
+    );
+
+    // An empty channel still produces a single closing frame.
+    if chunks.is_empty() {
+        return Ok(vec![Frame {
+            id,
+            number: 0,
+            data: vec![],
+            is_last: true,
+        }]);
+    }
+
+    let last = chunks.len() - 1;
+    Ok(chunks
+        .into_iter()
+        .enumerate()
+        .map(|(i, chunk)| Frame {
+            id,
+            number: i as u16,
+            data: chunk.to_vec(),
+            is_last: i == last,
+        })
+        .collect())
+}
+
+/// Encodes the [Frame]s as batcher transaction data.
+pub fn tx_data(frames: &[Frame]) -> Bytes {
+    let mut data = vec![DERIVATION_VERSION_0];
+    for frame in frames {
+        data.extend(frame.encode());
+    }
+    data.into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ID: ChannelId = [0xaa; 16];
+
+    #[test]
+    fn test_split_frames() {
+        let data = (0..100).collect::<Vec<u8>>();
+        let frames = split_frames(ID, &data, FRAME_OVERHEAD + 40).unwrap();
+
+        let sizes = frames.iter().map(|f| f.data.len()).collect::<Vec<_>>();
+        assert_eq!(sizes, [40, 40, 20]);
+        for (i, frame) in frames.iter().enumerate() {
+            assert_eq!(frame.id, ID);
+            assert_eq!(frame.number, i as u16);
+            assert_eq!(frame.is_last, i == 2);
+            assert!(frame.encode().len() <= FRAME_OVERHEAD + 40);
+        }
+        let joined = frames
+            .iter()
+            .flat_map(|f| f.data.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(joined, data);
+    }
+
+    #[test]
+    fn test_split_frames_exact_fit() {
+        let data = [1u8; 40];
+        let frames = split_frames(ID, &data, FRAME_OVERHEAD + 40).unwrap();
+        assert_eq!(frames.len(), 1);
+        assert!(frames[0].is_last);
+        assert_eq!(frames[0].encode().len(), FRAME_OVERHEAD + 40);
+    }
+
+    #[test]
+    fn test_split_frames_empty() {
+        let frames = split_frames(ID, &[], FRAME_OVERHEAD + 1).unwrap();
+        assert_eq!(
+            frames,
+            [Frame {
+                id: ID,
+                number: 0,
+                data: vec![],
+                is_last: true,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_split_frames_overhead() {
+        assert!(split_frames(ID, &[1, 2, 3], FRAME_OVERHEAD).is_err());
+    }
+
+    #[test]
+    fn test_tx_data_roundtrip() {
+        let data = (0..100).collect::<Vec<u8>>();
+        let frames = split_frames(ID, &data, FRAME_OVERHEAD + 40).unwrap();
+        let tx_data = tx_data(&frames);
+        assert_eq!(tx_data[0], DERIVATION_VERSION_0);
+        assert_eq!(Frame::parse_frames(&tx_data).unwrap(), frames);
+    }
+}
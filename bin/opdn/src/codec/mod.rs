@@ -0,0 +1,17 @@
+//! Encoding of L2 batches into the channels, frames and blobs posted by the batcher.
+
+pub mod blob;
+pub mod channel;
+
+/// The derivation version prefixing all batcher transaction data.
+pub const DERIVATION_VERSION_0: u8 = 0;
+
+/// The type byte of a singular batch.
+pub const SINGLE_BATCH_TYPE: u8 = 0;
+
+/// The type byte of a span batch.
+pub const SPAN_BATCH_TYPE: u8 = 1;
+
+/// The number of bytes a frame adds on top of its data.
+/// `channel_id (16) ++ frame_number (2) ++ frame_data_length (4) ++ is_last (1)`
+pub const FRAME_OVERHEAD: usize = 23;
@@ -0,0 +1,230 @@
+//! Decoding of the batcher data in a fixture into frames, channels and batches.
+
+use crate::codec::blob;
+use crate::mutate::l1::{batcher_txs, decode_tx};
+use crate::offline::KonaDerivationFixture;
+use alloy_consensus::{Transaction, TxEnvelope};
+use alloy_primitives::{Bytes, FixedBytes, B256};
+use color_eyre::{eyre::eyre, Result};
+use derivation_driver::{
+    altda::TX_DATA_VERSION_1, assemble_channels, AltDaCommitment, BatchInfo, Channel, PostedFrame,
+};
+use kona_primitives::Frame;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt::Display;
+
+/// The batcher data of a fixture, decoded into frames, channels and batches.
+#[derive(Serialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct Inspection {
+    /// The transactions sent to the batch inbox, in L1 order.
+    pub txs: Vec<BatcherTx>,
+    /// The channels the frames belong to, in the order they were opened.
+    pub channels: Vec<Channel>,
+}
+
+/// A transaction sent to the batch inbox.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BatcherTx {
+    /// The number of the L1 block the transaction is in.
+    pub l1_block: u64,
+    /// The index of the transaction in the L1 block.
+    pub index: usize,
+    /// The transaction hash.
+    pub hash: B256,
+    /// How the transaction carries its frames.
+    pub source: DataSource,
+    /// The frames carried by the transaction.
+    pub frames: Vec<FrameRef>,
+    /// Why the frames of the transaction could not be decoded, if they could not.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// How a batcher transaction carries its frames.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum DataSource {
+    /// The frames are in the calldata.
+    Calldata,
+    /// The frames are in the given number of blobs.
+    Blobs(usize),
+    /// The calldata is a commitment to an input holding the frames.
+    AltDa,
+}
+
+impl Display for DataSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Calldata => write!(f, "calldata"),
+            Self::Blobs(count) => write!(f, "{count} blobs"),
+            Self::AltDa => write!(f, "alt-DA"),
+        }
+    }
+}
+
+/// A frame carried by a batcher transaction.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct FrameRef {
+    /// The channel the frame belongs to.
+    pub channel: FixedBytes<16>,
+    /// The frame number within the channel.
+    pub number: u16,
+    /// The size of the frame data.
+    pub size: usize,
+    /// Whether the frame closes the channel.
+    pub is_last: bool,
+}
+
+/// Decodes the batcher transactions of the fixture into frames, channels and batches.
+///
+/// Alt-DA commitments are resolved from the inputs stored in the fixture. Decoding
+/// errors are recorded on the transaction or channel they occur in, so that the rest
+/// of the fixture can still be inspected.
+pub fn inspect(fixture: &KonaDerivationFixture) -> Result<Inspection> {
+    let inputs = fixture
+        .alt_da_inputs
+        .iter()
+        .map(|input| (input.commitment.clone(), input.data.clone()))
+        .collect::<HashMap<_, _>>();
+
+    let mut inspection = Inspection::default();
+    let mut frames = Vec::new();
+    for tx_ref in batcher_txs(fixture)? {
+        let l1_block = &fixture.l1_blocks[tx_ref.block];
+        let tx = decode_tx(&l1_block.transactions[tx_ref.tx])?;
+        let (source, payloads) = match &tx {
+            TxEnvelope::Eip4844(_) => (
+                DataSource::Blobs(tx_ref.blobs.len()),
+                l1_block
+                    .blobs
+                    .get(tx_ref.blobs.clone())
+                    .ok_or_else(|| eyre!("Blobs missing from the fixture"))
+                    .and_then(|blobs| blobs.iter().map(|b| blob::decode(b)).collect()),
+            ),
+            _ if tx.input().first() == Some(&TX_DATA_VERSION_1) => {
+                (DataSource::AltDa, resolve_alt_da(tx.input(), &inputs))
+            }
+            _ => (DataSource::Calldata, Ok(vec![tx.input().to_vec()])),
+        };
+
+        let mut batcher_tx = BatcherTx {
+            l1_block: l1_block.header.number,
+            index: tx_ref.tx,
+            hash: *tx.tx_hash(),
+            source,
+            frames: vec![],
+            error: None,
+        };
+        let parsed = payloads.and_then(|payloads| {
+            payloads.iter().try_fold(Vec::new(), |mut parsed, payload| {
+                parsed.extend(Frame::parse_frames(payload).map_err(|e| eyre!(e))?);
+                Ok(parsed)
+            })
+        });
+        match parsed {
+            Ok(parsed) => {
+                for frame in parsed {
+                    batcher_tx.frames.push(FrameRef {
+                        channel: frame.id.into(),
+                        number: frame.number,
+                        size: frame.data.len(),
+                        is_last: frame.is_last,
+                    });
+                    frames.push(PostedFrame {
+                        l1_block: l1_block.header.number,
+                        tx_hash: batcher_tx.hash,
+                        frame,
+                    });
+                }
+            }
+            Err(e) => batcher_tx.error = Some(e.to_string()),
+        }
+        inspection.txs.push(batcher_tx);
+    }
+
+    inspection.channels = assemble_channels(&fixture.rollup_config, frames);
+    Ok(inspection)
+}
+
+/// Returns the input the alt-DA commitment in the calldata refers to.
+fn resolve_alt_da(data: &[u8], inputs: &HashMap<Bytes, Bytes>) -> Result<Vec<Vec<u8>>> {
+    let commitment = AltDaCommitment::decode(&data[1..]).map_err(|e| eyre!(e))?;
+    let input = inputs
+        .get(&commitment.encode())
+        .ok_or_else(|| eyre!("Alt-DA input {} not in the fixture", commitment.encode()))?;
+    Ok(vec![input.to_vec()])
+}
+
+impl Display for Inspection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut l1_block = None;
+        for tx in &self.txs {
+            if l1_block != Some(tx.l1_block) {
+                writeln!(f, "L1 block {}", tx.l1_block)?;
+                l1_block = Some(tx.l1_block);
+            }
+            writeln!(f, "├─ tx {} {} ({})", tx.index, tx.hash, tx.source)?;
+            if let Some(e) = &tx.error {
+                writeln!(f, "│  └─ error: {e}")?;
+            }
+            for (i, frame) in tx.frames.iter().enumerate() {
+                let branch = if i + 1 == tx.frames.len() {
+                    "└─"
+                } else {
+                    "├─"
+                };
+                writeln!(
+                    f,
+                    "│  {branch} frame {} of channel {} ({} bytes{})",
+                    frame.number,
+                    frame.channel,
+                    frame.size,
+                    if frame.is_last { ", last" } else { "" }
+                )?;
+            }
+        }
+
+        for channel in &self.channels {
+            writeln!(f)?;
+            write!(
+                f,
+                "Channel {}: frames {:?}, opened in L1 block {}",
+                channel.id, channel.frames, channel.opened_in
+            )?;
+            if let Some(closed_in) = channel.closed_in {
+                write!(f, ", closed in L1 block {closed_in}")?;
+            }
+            if let Some(size) = channel.decompressed_size {
+                write!(f, ", {} -> {size} bytes", channel.compressed_size)?;
+            }
+            writeln!(f)?;
+            for batch in &channel.batches {
+                match batch {
+                    BatchInfo::Single { block, .. } => {
+                        writeln!(f, "├─ single batch")?;
+                        writeln!(f, "│  └─ {block}")?;
+                    }
+                    BatchInfo::Span { blocks, .. } => {
+                        writeln!(f, "├─ span batch of {} blocks", blocks.len())?;
+                        for (i, block) in blocks.iter().enumerate() {
+                            let branch = if i + 1 == blocks.len() {
+                                "└─"
+                            } else {
+                                "├─"
+                            };
+                            writeln!(f, "│  {branch} {block}")?;
+                        }
+                    }
+                }
+            }
+            if let Some(e) = &channel.error {
+                writeln!(f, "└─ error: {e}")?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -5,3 +5,11 @@
 
 pub mod cmd;
 pub use cmd::Cli;
+
+pub mod codec;
+pub mod inspect;
+pub mod mutate;
+pub mod offline;
+pub mod prune;
+pub mod synth;
+pub mod trie;
@@ -0,0 +1,361 @@
+//! Helpers for editing the batcher transactions of a fixture's L1 blocks.
+
+use crate::codec::blob;
+use crate::offline::KonaDerivationFixture;
+use crate::trie::ordered_trie_root;
+use alloy_consensus::{
+    Header, Receipt, ReceiptEnvelope, ReceiptWithBloom, SignableTransaction, Transaction,
+    TxEnvelope, TxType,
+};
+use alloy_eips::{
+    eip2718::{Decodable2718, Encodable2718},
+    eip4844::DATA_GAS_PER_BLOB,
+};
+use alloy_primitives::{Bloom, Bytes, TxKind};
+use alloy_signer::SignerSync;
+use alloy_signer_local::PrivateKeySigner;
+use color_eyre::{
+    eyre::{bail, ensure, eyre},
+    Result,
+};
+use hashbrown::HashMap;
+use kona_primitives::{Blob, BlockID, Frame};
+use op_test_vectors::derivation::FixtureBlock;
+use std::ops::Range;
+
+/// The L1 block time assumed when the fixture has a single L1 block.
+const DEFAULT_L1_BLOCK_TIME: u64 = 12;
+
+/// The location of a batcher transaction in a fixture.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BatcherTxRef {
+    /// The index of the L1 block in the fixture.
+    pub block: usize,
+    /// The index of the transaction in the block.
+    pub tx: usize,
+    /// The range of the block's blobs referenced by the transaction.
+    pub blobs: Range<usize>,
+}
+
+/// A batcher transaction removed from a block, with its receipt and blobs.
+#[derive(Debug, Clone)]
+pub struct DetachedTx {
+    /// The EIP-2718 encoded transaction.
+    pub tx: Bytes,
+    /// The transaction receipt.
+    pub receipt: Receipt,
+    /// The gas used by the transaction.
+    pub gas_used: u128,
+    /// The blobs referenced by the transaction.
+    pub blobs: Vec<Box<Blob>>,
+}
+
+/// Decodes an EIP-2718 encoded transaction.
+pub fn decode_tx(tx: &Bytes) -> Result<TxEnvelope> {
+    TxEnvelope::decode_2718(&mut tx.as_ref())
+        .map_err(|e| eyre!("Failed to decode transaction: {e}"))
+}
+
+/// Returns every transaction sent to the batch inbox, in order.
+///
+/// Blobs are assumed to be stored in the order of the blob transactions sent to the
+/// batch inbox, which is how fixtures store them.
+pub fn batcher_txs(fixture: &KonaDerivationFixture) -> Result<Vec<BatcherTxRef>> {
+    let inbox = TxKind::Call(fixture.rollup_config.batch_inbox_address);
+    let mut refs = Vec::new();
+    for (block, l1_block) in fixture.l1_blocks.iter().enumerate() {
+        let mut blob_index = 0;
+        for (index, raw) in l1_block.transactions.iter().enumerate() {
+            let tx = decode_tx(raw)?;
+            if tx.to() != inbox {
+                continue;
+            }
+            let blob_count = match &tx {
+                TxEnvelope::Eip4844(signed) => signed.tx().tx().blob_versioned_hashes.len(),
+                _ => 0,
+            };
+            refs.push(BatcherTxRef {
+                block,
+                tx: index,
+                blobs: blob_index..blob_index + blob_count,
+            });
+            blob_index += blob_count;
+        }
+    }
+    Ok(refs)
+}
+
+/// Returns the frames carried by the given batcher transaction.
+pub fn frames(fixture: &KonaDerivationFixture, tx_ref: &BatcherTxRef) -> Result<Vec<Frame>> {
+    let l1_block = &fixture.l1_blocks[tx_ref.block];
+    let tx = decode_tx(&l1_block.transactions[tx_ref.tx])?;
+    let payloads = match &tx {
+        TxEnvelope::Eip4844(_) => l1_block.blobs[tx_ref.blobs.clone()]
+            .iter()
+            .map(|b| blob::decode(b))
+            .collect::<Result<Vec<_>>>()?,
+        _ => vec![tx.input().to_vec()],
+    };
+    let mut frames = Vec::new();
+    for payload in payloads {
+        frames.extend(Frame::parse_frames(&payload).map_err(|e| eyre!(e))?);
+    }
+    Ok(frames)
+}
+
+/// Returns the gas used by the transaction at the given index of the block.
+fn gas_used(l1_block: &FixtureBlock<Blob>, index: usize) -> u128 {
+    let previous = index
+        .checked_sub(1)
+        .map(|i| l1_block.receipts[i].cumulative_gas_used)
+        .unwrap_or_default();
+    l1_block.receipts[index].cumulative_gas_used - previous
+}
+
+/// Removes the given batcher transaction from its block, and deducts its gas from
+/// the cumulative gas of the following receipts.
+pub fn detach(fixture: &mut KonaDerivationFixture, tx_ref: &BatcherTxRef) -> DetachedTx {
+    let l1_block = &mut fixture.l1_blocks[tx_ref.block];
+    let gas_used = gas_used(l1_block, tx_ref.tx);
+    for receipt in &mut l1_block.receipts[tx_ref.tx + 1..] {
+        receipt.cumulative_gas_used -= gas_used;
+    }
+    DetachedTx {
+        tx: l1_block.transactions.remove(tx_ref.tx),
+        receipt: l1_block.receipts.remove(tx_ref.tx),
+        gas_used,
+        blobs: l1_block.blobs.drain(tx_ref.blobs.clone()).collect(),
+    }
+}
+
+/// Returns a copy of the given batcher transaction.
+pub fn copy(fixture: &KonaDerivationFixture, tx_ref: &BatcherTxRef) -> DetachedTx {
+    let l1_block = &fixture.l1_blocks[tx_ref.block];
+    DetachedTx {
+        tx: l1_block.transactions[tx_ref.tx].clone(),
+        receipt: l1_block.receipts[tx_ref.tx].clone(),
+        gas_used: gas_used(l1_block, tx_ref.tx),
+        blobs: l1_block.blobs[tx_ref.blobs.clone()].to_vec(),
+    }
+}
+
+/// Appends a detached transaction to the end of the given block.
+pub fn attach(fixture: &mut KonaDerivationFixture, block: usize, tx: DetachedTx) {
+    let l1_block = &mut fixture.l1_blocks[block];
+    let cumulative_gas_used = l1_block
+        .receipts
+        .last()
+        .map(|r| r.cumulative_gas_used)
+        .unwrap_or_default();
+    l1_block.transactions.push(tx.tx);
+    l1_block.receipts.push(Receipt {
+        cumulative_gas_used: cumulative_gas_used + tx.gas_used,
+        ..tx.receipt
+    });
+    l1_block.blobs.extend(tx.blobs);
+}
+
+/// Moves a batcher transaction to the end of another block.
+pub fn move_tx(fixture: &mut KonaDerivationFixture, tx_ref: &BatcherTxRef, block: usize) {
+    let tx = detach(fixture, tx_ref);
+    attach(fixture, block, tx);
+}
+
+/// Returns the index of the L1 block with the given number, appending empty L1
+/// blocks to the fixture until it exists.
+pub fn block_index(fixture: &mut KonaDerivationFixture, number: u64) -> Result<usize> {
+    let last = fixture
+        .l1_blocks
+        .last()
+        .ok_or_else(|| eyre!("Fixture has no L1 blocks"))?;
+    let block_time = fixture
+        .l1_blocks
+        .iter()
+        .rev()
+        .nth(1)
+        .map(|b| last.header.timestamp.saturating_sub(b.header.timestamp))
+        .unwrap_or(DEFAULT_L1_BLOCK_TIME);
+    loop {
+        let parent = &fixture.l1_blocks[fixture.l1_blocks.len() - 1].header;
+        if parent.number >= number {
+            break;
+        }
+        let header = Header {
+            parent_hash: parent.hash_slow(),
+            number: parent.number + 1,
+            timestamp: parent.timestamp + block_time,
+            ..parent.clone()
+        };
+        let mut block = FixtureBlock {
+            header,
+            transactions: vec![],
+            blobs: vec![],
+            kzg_commitments: vec![],
+            kzg_proofs: vec![],
+            receipts: vec![],
+            pruned: None,
+        };
+        seal(&mut block)?;
+        fixture.l1_blocks.push(block);
+    }
+    fixture
+        .l1_blocks
+        .iter()
+        .position(|b| b.header.number == number)
+        .ok_or_else(|| eyre!("L1 block {number} not found in fixture"))
+}
+
+/// Recomputes the header fields of the block that commit to its transactions and
+/// receipts: the transactions and receipts roots, the gas used, the logs bloom and
+/// the blob gas used.
+pub fn seal(block: &mut FixtureBlock<Blob>) -> Result<()> {
+    ensure!(
+        block.pruned.is_none(),
+        "Cannot reseal pruned L1 block {}",
+        block.header.number
+    );
+    let txs = block
+        .transactions
+        .iter()
+        .map(decode_tx)
+        .collect::<Result<Vec<_>>>()?;
+    ensure!(
+        txs.len() == block.receipts.len(),
+        "L1 block {} has {} transactions but {} receipts",
+        block.header.number,
+        txs.len(),
+        block.receipts.len()
+    );
+    let raw_receipts = txs
+        .iter()
+        .zip(&block.receipts)
+        .map(|(tx, receipt)| encode_receipt(tx, receipt))
+        .collect::<Result<Vec<_>>>()?;
+    let raw_txs = block
+        .transactions
+        .iter()
+        .map(|tx| tx.to_vec())
+        .collect::<Vec<_>>();
+    let blob_count = txs
+        .iter()
+        .map(|tx| match tx {
+            TxEnvelope::Eip4844(signed) => signed.tx().tx().blob_versioned_hashes.len(),
+            _ => 0,
+        })
+        .sum::<usize>();
+
+    let header = &mut block.header;
+    header.transactions_root = ordered_trie_root(&raw_txs);
+    header.receipts_root = ordered_trie_root(&raw_receipts);
+    header.gas_used = block
+        .receipts
+        .last()
+        .map(|r| r.cumulative_gas_used)
+        .unwrap_or_default();
+    header.logs_bloom = block.receipts.iter().fold(Bloom::ZERO, |mut bloom, r| {
+        r.logs.iter().for_each(|log| bloom.accrue_log(log));
+        bloom
+    });
+    header.blob_gas_used = header
+        .blob_gas_used
+        .map(|_| blob_count as u128 * DATA_GAS_PER_BLOB as u128);
+    Ok(())
+}
+
+/// Reseals the L1 blocks whose transactions or receipts differ from the original
+/// fixture, and relinks the chain: every block is renumbered to follow its parent
+/// and points at the new hash of its parent.
+///
+/// The L1 origins of the L2 block infos and the rollup genesis are updated to the new
+/// hashes and numbers. Batches keep the L1 origins they were posted with, so batches
+/// referencing a changed L1 block are no longer valid.
+pub fn reseal(fixture: &mut KonaDerivationFixture, original: &KonaDerivationFixture) -> Result<()> {
+    let originals = original
+        .l1_blocks
+        .iter()
+        .map(|b| (b.header.hash_slow(), b))
+        .collect::<HashMap<_, _>>();
+    let mut remapped = HashMap::new();
+    let mut parent: Option<BlockID> = None;
+    for block in &mut fixture.l1_blocks {
+        let hash = block.header.hash_slow();
+        let unchanged = originals
+            .get(&hash)
+            .is_some_and(|o| o.transactions == block.transactions && o.receipts == block.receipts);
+        if !unchanged {
+            seal(block)?;
+        }
+        if let Some(parent) = parent {
+            block.header.number = parent.number + 1;
+            block.header.parent_hash = parent.hash;
+        }
+        let id = BlockID {
+            hash: block.header.hash_slow(),
+            number: block.header.number,
+        };
+        if id.hash != hash {
+            remapped.insert(hash, id);
+        }
+        parent = Some(id);
+    }
+
+    let remap = |origin: &mut BlockID| {
+        if let Some(id) = remapped.get(&origin.hash) {
+            *origin = *id;
+        }
+    };
+    fixture
+        .l2_block_infos
+        .values_mut()
+        .for_each(|info| remap(&mut info.l1_origin));
+    remap(&mut fixture.rollup_config.genesis.l1);
+    Ok(())
+}
+
+/// Encodes a receipt as a leaf of the receipts trie, which depends on the type of
+/// its transaction.
+pub fn encode_receipt(tx: &TxEnvelope, receipt: &Receipt) -> Result<Vec<u8>> {
+    let mut bloom = Bloom::ZERO;
+    for log in &receipt.logs {
+        bloom.accrue_log(log);
+    }
+    let with_bloom = ReceiptWithBloom::new(receipt.clone(), bloom);
+    let envelope = match tx.tx_type() {
+        TxType::Legacy => ReceiptEnvelope::Legacy(with_bloom),
+        TxType::Eip2930 => ReceiptEnvelope::Eip2930(with_bloom),
+        TxType::Eip1559 => ReceiptEnvelope::Eip1559(with_bloom),
+        TxType::Eip4844 => ReceiptEnvelope::Eip4844(with_bloom),
+        #[allow(unreachable_patterns)]
+        ty => bail!("Unsupported transaction type {ty:?}"),
+    };
+    Ok(envelope.encoded_2718())
+}
+
+/// Re-signs the given batcher transaction with another key.
+pub fn resign(
+    fixture: &mut KonaDerivationFixture,
+    tx_ref: &BatcherTxRef,
+    signer: &PrivateKeySigner,
+) -> Result<()> {
+    let raw = &mut fixture.l1_blocks[tx_ref.block].transactions[tx_ref.tx];
+    let resigned: TxEnvelope = match decode_tx(raw)? {
+        TxEnvelope::Legacy(signed) => sign(signed.strip_signature(), signer)?.into(),
+        TxEnvelope::Eip2930(signed) => sign(signed.strip_signature(), signer)?.into(),
+        TxEnvelope::Eip1559(signed) => sign(signed.strip_signature(), signer)?.into(),
+        TxEnvelope::Eip4844(signed) => sign(signed.strip_signature(), signer)?.into(),
+        _ => return Err(eyre!("Unsupported batcher transaction type")),
+    };
+    *raw = resigned.encoded_2718().into();
+    Ok(())
+}
+
+/// Signs a transaction with the given key.
+fn sign<T: SignableTransaction<alloy_primitives::Signature>>(
+    tx: T,
+    signer: &PrivateKeySigner,
+) -> Result<alloy_consensus::Signed<T>> {
+    let signature = signer
+        .sign_hash_sync(&tx.signature_hash())
+        .map_err(|e| eyre!(e))?;
+    Ok(tx.into_signed(signature))
+}
@@ -0,0 +1,387 @@
+//! Mutations that turn a derivation fixture into adversarial variants.
+//!
+//! Mutated L1 blocks are resealed, so that their headers commit to their new
+//! transactions and receipts, and the L1 chain is relinked on top of them. Batches
+//! referencing an L1 origin at or after the first mutated block are therefore no
+//! longer valid. The expected `l2_payloads` are recomputed by running the derivation
+//! pipeline offline over the mutated fixture.
+
+use crate::offline::{self, KonaDerivationFixture};
+use alloy_primitives::{b256, B256};
+use alloy_signer_local::PrivateKeySigner;
+use clap::ValueEnum;
+use color_eyre::{
+    eyre::{ensure, eyre},
+    Result,
+};
+use std::sync::Arc;
+use tracing::{debug, info};
+
+pub mod l1;
+use l1::BatcherTxRef;
+
+/// The logging target to use for [tracing].
+const TARGET: &str = "mutate";
+
+/// The key used to sign batcher transactions for [Mutation::WrongSigner], the second
+/// well-known anvil development account.
+const WRONG_SIGNER_KEY: B256 =
+    b256!("59c6995e998f97a5a0044966f0945389dc9e86dae88c7a8412f4603b6b78690d");
+
+/// A named mutation of a derivation fixture.
+///
+/// Every mutation targets the first batcher transaction, or the first channel, that
+/// it applies to.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mutation {
+    /// Includes the first batcher transaction twice in its L1 block.
+    DuplicateFrame,
+    /// Swaps the first two batcher transactions that are in different L1 blocks.
+    ReorderFrames,
+    /// Drops the batcher transaction carrying the closing frame of the first channel.
+    TruncateChannel,
+    /// Delays the closing frame of the first channel past the channel timeout.
+    ChannelTimeout,
+    /// Delays the first channel past the sequencing window.
+    DelayBatch,
+    /// Signs the first batcher transaction with a key other than the batcher's.
+    WrongSigner,
+    /// Corrupts the first blob posted by the batcher.
+    CorruptBlob,
+    /// Removes the first L1 block containing a batcher transaction.
+    DropL1Block,
+}
+
+impl Mutation {
+    /// Returns the name of the mutation, as accepted on the command line.
+    pub fn name(&self) -> String {
+        self.to_possible_value()
+            .map(|v| v.get_name().to_string())
+            .unwrap_or_default()
+    }
+
+    /// Applies the mutation to the fixture's L1 blocks, and reseals the L1 chain.
+    pub fn apply(&self, fixture: &mut KonaDerivationFixture) -> Result<()> {
+        let original = fixture.clone();
+        let txs = l1::batcher_txs(fixture)?;
+        let first = txs
+            .first()
+            .cloned()
+            .ok_or_else(|| eyre!("Fixture contains no batcher transactions"))?;
+
+        match self {
+            Self::DuplicateFrame => {
+                let tx = l1::copy(fixture, &first);
+                l1::attach(fixture, first.block, tx);
+            }
+            Self::ReorderFrames => {
+                let second = txs
+                    .iter()
+                    .find(|t| t.block != first.block)
+                    .cloned()
+                    .ok_or_else(|| eyre!("Batcher transactions are all in the same L1 block"))?;
+                // Appending to the second block leaves the location of its own tx unchanged.
+                l1::move_tx(fixture, &first, second.block);
+                l1::move_tx(fixture, &second, first.block);
+            }
+            Self::TruncateChannel => {
+                let (_, closing) = first_channel(fixture, &txs)?;
+                l1::detach(fixture, &closing);
+            }
+            Self::ChannelTimeout => {
+                let (channel, closing) = first_channel(fixture, &txs)?;
+                ensure!(
+                    channel.len() > 1,
+                    "First channel is carried by a single transaction"
+                );
+                let opened = fixture.l1_blocks[channel[0].block].header.number;
+                let target =
+                    l1::block_index(fixture, opened + fixture.rollup_config.channel_timeout + 1)?;
+                l1::move_tx(fixture, &closing, target);
+            }
+            Self::DelayBatch => {
+                let (channel, _) = first_channel(fixture, &txs)?;
+                // Detach back to front so that earlier locations stay valid.
+                let mut detached = Vec::new();
+                for tx_ref in channel.iter().rev() {
+                    let number = fixture.l1_blocks[tx_ref.block].header.number;
+                    detached.push((number, l1::detach(fixture, tx_ref)));
+                }
+                for (number, tx) in detached.into_iter().rev() {
+                    let target =
+                        l1::block_index(fixture, number + fixture.rollup_config.seq_window_size)?;
+                    l1::attach(fixture, target, tx);
+                }
+            }
+            Self::WrongSigner => {
+                let signer =
+                    PrivateKeySigner::from_bytes(&WRONG_SIGNER_KEY).map_err(|e| eyre!(e))?;
+                l1::resign(fixture, &first, &signer)?;
+            }
+            Self::CorruptBlob => {
+                let tx_ref = txs
+                    .iter()
+                    .find(|t| !t.blobs.is_empty())
+                    .ok_or_else(|| eyre!("Fixture contains no batcher blobs"))?;
+                let blob = &mut fixture.l1_blocks[tx_ref.block].blobs[tx_ref.blobs.start];
+                // The first data byte holds the derivation version of the frame data.
+                blob[5] ^= 0xFF;
+            }
+            Self::DropL1Block => {
+                fixture.l1_blocks.remove(first.block);
+            }
+        }
+        l1::reseal(fixture, &original)
+    }
+}
+
+/// Returns the batcher transactions carrying frames of the first channel, and the
+/// transaction carrying its closing frame.
+fn first_channel(
+    fixture: &KonaDerivationFixture,
+    txs: &[BatcherTxRef],
+) -> Result<(Vec<BatcherTxRef>, BatcherTxRef)> {
+    let mut channel_id = None;
+    let mut channel = Vec::new();
+    let mut closing = None;
+    for tx_ref in txs {
+        let frames = l1::frames(fixture, tx_ref)?;
+        let Some(first) = frames.first() else {
+            continue;
+        };
+        let id = *channel_id.get_or_insert(first.id);
+        let frames = frames.iter().filter(|f| f.id == id).collect::<Vec<_>>();
+        if frames.is_empty() {
+            continue;
+        }
+        channel.push(tx_ref.clone());
+        if frames.iter().any(|f| f.is_last) {
+            closing = Some(tx_ref.clone());
+            break;
+        }
+    }
+    ensure!(!channel.is_empty(), "Fixture contains no frames");
+    let closing = closing.ok_or_else(|| eyre!("First channel is never closed"))?;
+    debug!(target: TARGET, "First channel spans {} batcher transactions", channel.len());
+    Ok((channel, closing))
+}
+
+/// Applies the mutation to a copy of the fixture and recomputes its expected
+/// `l2_payloads` by deriving offline.
+pub async fn mutate(
+    fixture: &KonaDerivationFixture,
+    mutation: Mutation,
+) -> Result<KonaDerivationFixture> {
+    let mut mutated = fixture.clone();
+    mutation.apply(&mut mutated)?;
+    // Mutations move and corrupt blobs, so the stored KZG data no longer lines up.
+    for block in &mut mutated.l1_blocks {
+        block.kzg_commitments.clear();
+        block.kzg_proofs.clear();
+    }
+    mutated.l2_payloads = offline::derive(Arc::new(mutated.clone())).await?;
+    info!(
+        target: TARGET,
+        "Mutation {} derives {} of {} L2 payloads",
+        mutation.name(),
+        mutated.l2_payloads.len(),
+        fixture.l2_payloads.len()
+    );
+    Ok(mutated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::synth::{tests::fixture, BatcherSettings, DataAvailability};
+    use alloy_consensus::TxEnvelope;
+    use alloy_signer::SignerSync;
+
+    /// Returns a calldata fixture of 6 L2 blocks in channels of 2 blocks, with every
+    /// channel split over several batcher transactions.
+    async fn calldata_fixture() -> KonaDerivationFixture {
+        let batcher = BatcherSettings {
+            blocks_per_channel: 2,
+            max_frame_size: 40,
+            ..Default::default()
+        };
+        fixture(batcher, 6).await
+    }
+
+    /// Asserts that every L1 block commits to its contents and links to its parent.
+    fn assert_sealed(fixture: &KonaDerivationFixture) {
+        for (i, block) in fixture.l1_blocks.iter().enumerate() {
+            let mut sealed = block.clone();
+            l1::seal(&mut sealed).unwrap();
+            assert_eq!(sealed.header, block.header, "L1 block {i} is not sealed");
+            if let Some(parent) = i.checked_sub(1).map(|i| &fixture.l1_blocks[i].header) {
+                assert_eq!(block.header.number, parent.number + 1);
+                assert_eq!(block.header.parent_hash, parent.hash_slow());
+            }
+        }
+        assert_eq!(
+            fixture.rollup_config.genesis.l1.hash,
+            fixture.l1_blocks[0].header.hash_slow()
+        );
+    }
+
+    /// Applies the mutation, and checks that the mutated L1 chain is consistent.
+    async fn apply(fixture: &KonaDerivationFixture, mutation: Mutation) -> KonaDerivationFixture {
+        let mutated = mutate(fixture, mutation).await.unwrap();
+        assert_sealed(&mutated);
+        mutated
+    }
+
+    /// Returns the number of transactions in every L1 block.
+    fn tx_counts(fixture: &KonaDerivationFixture) -> Vec<usize> {
+        fixture
+            .l1_blocks
+            .iter()
+            .map(|b| b.transactions.len())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_frame() {
+        let fixture = calldata_fixture().await;
+        assert_sealed(&fixture);
+        let mutated = apply(&fixture, Mutation::DuplicateFrame).await;
+
+        let mut expected = tx_counts(&fixture);
+        expected[1] += 1;
+        assert_eq!(tx_counts(&mutated), expected);
+        // The duplicate is ignored, and the first channel still derives.
+        for number in [1u64, 2] {
+            assert_eq!(
+                mutated.l2_payloads.get(&number),
+                fixture.l2_payloads.get(&number)
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reorder_frames() {
+        let fixture = calldata_fixture().await;
+        let mutated = apply(&fixture, Mutation::ReorderFrames).await;
+
+        assert_eq!(tx_counts(&mutated), tx_counts(&fixture));
+        assert_ne!(
+            mutated.l1_blocks[1].transactions,
+            fixture.l1_blocks[1].transactions
+        );
+        // Frames of a channel may arrive out of order within the channel timeout.
+        for number in [1u64, 2] {
+            assert_eq!(
+                mutated.l2_payloads.get(&number),
+                fixture.l2_payloads.get(&number)
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_truncate_channel() {
+        let fixture = calldata_fixture().await;
+        let mutated = apply(&fixture, Mutation::TruncateChannel).await;
+
+        let txs = l1::batcher_txs(&fixture).unwrap();
+        let mut expected = tx_counts(&fixture);
+        expected[txs[0].block] -= 1;
+        assert_eq!(tx_counts(&mutated), expected);
+        assert!(!mutated.l2_payloads.contains_key(&1u64));
+    }
+
+    #[tokio::test]
+    async fn test_channel_timeout() {
+        let fixture = calldata_fixture().await;
+        let mutated = apply(&fixture, Mutation::ChannelTimeout).await;
+
+        let opened = fixture.l1_blocks[1].header.number;
+        let last = mutated.l1_blocks.last().unwrap();
+        assert_eq!(
+            last.header.number,
+            opened + fixture.rollup_config.channel_timeout + 1
+        );
+        assert_eq!(last.transactions.len(), 1);
+        assert!(!mutated.l2_payloads.contains_key(&1u64));
+    }
+
+    #[tokio::test]
+    async fn test_delay_batch() {
+        let fixture = calldata_fixture().await;
+        let mutated = apply(&fixture, Mutation::DelayBatch).await;
+
+        let delayed = fixture.l1_blocks[1].header.number + fixture.rollup_config.seq_window_size;
+        let last = mutated.l1_blocks.last().unwrap();
+        assert_eq!(last.header.number, delayed);
+        assert_eq!(
+            last.transactions.len(),
+            fixture.l1_blocks[1].transactions.len()
+        );
+        // Once the sequencing window has passed, the block is derived without its batch.
+        let derived = &mutated.l2_payloads[&1u64];
+        assert_eq!(derived.transactions.len(), 1);
+        assert_ne!(Some(derived), fixture.l2_payloads.get(&1u64));
+    }
+
+    #[tokio::test]
+    async fn test_wrong_signer() {
+        let fixture = calldata_fixture().await;
+        let mutated = apply(&fixture, Mutation::WrongSigner).await;
+
+        assert_eq!(tx_counts(&mutated), tx_counts(&fixture));
+        let TxEnvelope::Eip1559(tx) = l1::decode_tx(&mutated.l1_blocks[1].transactions[0]).unwrap()
+        else {
+            panic!("Batcher transaction is not an EIP-1559 transaction");
+        };
+        let signer = PrivateKeySigner::from_bytes(&WRONG_SIGNER_KEY).unwrap();
+        let signature = signer.sign_hash_sync(&tx.signature_hash()).unwrap();
+        assert_eq!(*tx.signature(), signature);
+        assert!(!mutated.l2_payloads.contains_key(&1u64));
+    }
+
+    #[tokio::test]
+    async fn test_corrupt_blob() {
+        let batcher = BatcherSettings {
+            data_availability: DataAvailability::Blobs,
+            blocks_per_channel: 2,
+            ..Default::default()
+        };
+        let fixture = fixture(batcher, 6).await;
+        let mutated = apply(&fixture, Mutation::CorruptBlob).await;
+
+        // Blobs are not committed to by the header, which is left unchanged.
+        for (block, original) in mutated.l1_blocks.iter().zip(&fixture.l1_blocks) {
+            assert_eq!(block.header, original.header);
+        }
+        assert_ne!(mutated.l1_blocks[1].blobs, fixture.l1_blocks[1].blobs);
+        assert!(!mutated.l2_payloads.contains_key(&1u64));
+    }
+
+    #[tokio::test]
+    async fn test_drop_l1_block() {
+        let fixture = calldata_fixture().await;
+        let mutated = apply(&fixture, Mutation::DropL1Block).await;
+
+        assert_eq!(mutated.l1_blocks.len(), fixture.l1_blocks.len() - 1);
+        let mut expected = tx_counts(&fixture);
+        expected.remove(1);
+        assert_eq!(tx_counts(&mutated), expected);
+        // The following blocks take the place of the dropped block.
+        assert_eq!(
+            mutated.l1_blocks[1].header.number,
+            fixture.l1_blocks[1].header.number
+        );
+        assert_eq!(
+            mutated.l1_blocks[1].transactions,
+            fixture.l1_blocks[2].transactions
+        );
+        assert!(!mutated.l2_payloads.contains_key(&1u64));
+    }
+
+    #[tokio::test]
+    async fn test_mutate_pruned_fixture() {
+        let fixture = calldata_fixture().await;
+        let pruned = crate::prune::prune(&fixture).await.unwrap();
+        assert!(mutate(&pruned, Mutation::DuplicateFrame).await.is_err());
+    }
+}
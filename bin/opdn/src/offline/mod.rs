@@ -0,0 +1,257 @@
+//! Offline derivation over the data contained in a derivation fixture.
+
+use color_eyre::{eyre::eyre, Result};
+use derivation_driver::{DerivationSession, L2Reads, RecordingL2Provider, RetryPolicy};
+use hashbrown::HashMap;
+use kona_derive::traits::L2ChainProvider;
+use kona_primitives::{Blob, L2BlockInfo, L2PayloadAttributes, RollupConfig, SystemConfig};
+use op_test_vectors::derivation::DerivationFixture;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::debug;
+
+mod provider;
+pub use provider::{block_info, FixtureProvider};
+
+/// The logging target to use for [tracing].
+const TARGET: &str = "offline";
+
+/// The maximum number of consecutive pipeline steps that may pass without
+/// advancing the origin or producing attributes before offline derivation stops.
+const MAX_IDLE_STEPS: usize = 10_000;
+
+/// The retry policy of offline derivation. The fixture never changes, so there is
+/// nothing to wait for between failed pipeline steps.
+const RETRY_POLICY: RetryPolicy = RetryPolicy {
+    max_attempts: 1,
+    initial_backoff: Duration::ZERO,
+    max_backoff: Duration::ZERO,
+};
+
+/// A [DerivationFixture] instantiated with the kona types.
+pub type KonaDerivationFixture =
+    DerivationFixture<RollupConfig, L2PayloadAttributes, SystemConfig, L2BlockInfo, Blob>;
+
+/// Runs the derivation pipeline over the L1 blocks of the given fixture, starting at
+/// its `l2_cursor_start`, and returns the derived payload attributes keyed by
+/// L2 block number.
+///
+/// Fixtures with alt-DA inputs are derived with an alt-DA data source, which resolves
+/// the commitments posted to L1 from those inputs.
+///
+/// Derivation stops once the cursor reaches `l2_cursor_end`, the fixture runs out of
+/// L1 blocks, the pipeline stops making progress, or the fixture is missing the L2
+/// block info for the next safe head. Fixtures with malformed batcher data therefore
+/// yield the payloads derived up to that point.
+pub async fn derive(
+    fixture: Arc<KonaDerivationFixture>,
+) -> Result<HashMap<u64, L2PayloadAttributes>> {
+    Ok(derive_recorded(fixture).await?.0)
+}
+
+/// Runs offline derivation like [derive], and also returns the L2 chain data the
+/// pipeline read from the fixture.
+pub async fn derive_recorded(
+    fixture: Arc<KonaDerivationFixture>,
+) -> Result<(HashMap<u64, L2PayloadAttributes>, L2Reads)> {
+    let cfg = Arc::new(fixture.rollup_config.clone());
+    let mut provider = FixtureProvider::new(fixture.clone());
+    let l2_cursor = provider
+        .l2_block_info_by_number(fixture.l2_cursor_start)
+        .await
+        .map_err(|e| eyre!(e))?;
+    let l2_recorder = RecordingL2Provider::new(provider.clone());
+    let session = if fixture.alt_da_inputs.is_empty() {
+        DerivationSession::new(
+            cfg,
+            provider.clone(),
+            l2_recorder.clone(),
+            provider,
+            l2_cursor,
+        )
+        .await?
+    } else {
+        DerivationSession::new_alt_da(
+            cfg,
+            provider.clone(),
+            l2_recorder.clone(),
+            provider.clone(),
+            provider,
+            l2_cursor,
+        )
+        .await?
+    };
+    let mut session = session
+        .with_end_block(fixture.l2_cursor_end)
+        .with_stop_on_provider_error(true)
+        .with_retry_policy(RETRY_POLICY)
+        .with_max_idle_steps(MAX_IDLE_STEPS);
+
+    let mut payloads = HashMap::new();
+    while let Some(block) = session.next_block().await? {
+        debug!(target: TARGET, "Derived attributes for L2 block {}", block.number());
+        payloads.insert(block.number(), block.attributes.attributes);
+    }
+
+    Ok((payloads, l2_recorder.take()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::synth::{tests::fixture, BatcherSettings};
+    use async_trait::async_trait;
+    use derivation_driver::{
+        stalled_exit_code, DerivationHooks, DerivedBlock, StallArgs, StalledError,
+        STALLED_EXIT_CODE,
+    };
+    use std::time::Instant;
+
+    /// Returns a session deriving over the fixture, starting at its cursor.
+    async fn session(fixture: &Arc<KonaDerivationFixture>) -> DerivationSession<FixtureProvider> {
+        let provider = FixtureProvider::new(fixture.clone());
+        let cursor = fixture.l2_block_infos[&fixture.l2_cursor_start];
+        DerivationSession::new(
+            Arc::new(fixture.rollup_config.clone()),
+            provider.clone(),
+            provider.clone(),
+            provider,
+            cursor,
+        )
+        .await
+        .unwrap()
+        .with_stop_on_provider_error(true)
+        .with_retry_policy(RETRY_POLICY)
+    }
+
+    fn batcher() -> BatcherSettings {
+        BatcherSettings {
+            blocks_per_channel: 2,
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_session_derives_fixture() {
+        let fixture = Arc::new(fixture(batcher(), 6).await);
+        let mut session = session(&fixture).await;
+
+        let mut parent = session.cursor();
+        for number in 1..=6u64 {
+            let block = session.next_block().await.unwrap().unwrap();
+            assert_eq!(block.number(), number);
+            assert_eq!(block.l2_block_info, fixture.l2_block_infos[&number]);
+            assert_eq!(block.attributes.parent, parent);
+            assert_eq!(block.attributes.attributes, fixture.l2_payloads[&number]);
+            assert!(block.l1_origin.number > block.l2_block_info.l1_origin.number);
+            assert_eq!(session.cursor(), block.l2_block_info);
+            parent = block.l2_block_info;
+        }
+        assert!(session.next_block().await.unwrap().is_none());
+        assert_eq!(session.cursor().block_info.number, 6);
+    }
+
+    #[tokio::test]
+    async fn test_session_end_block() {
+        let fixture = Arc::new(fixture(batcher(), 6).await);
+        let mut session = session(&fixture).await.with_end_block(3);
+
+        let mut numbers = Vec::new();
+        while let Some(block) = session.next_block().await.unwrap() {
+            numbers.push(block.number());
+        }
+        assert_eq!(numbers, [1, 2, 3]);
+    }
+
+    /// Collects the numbers of the derived blocks.
+    #[derive(Debug, Default)]
+    struct Collect(Vec<u64>);
+
+    #[async_trait]
+    impl DerivationHooks for Collect {
+        async fn on_block(&mut self, block: DerivedBlock) -> Result<()> {
+            self.0.push(block.number());
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_session_run() {
+        let fixture = Arc::new(fixture(batcher(), 4).await);
+        let mut hooks = Collect::default();
+        session(&fixture).await.run(&mut hooks).await.unwrap();
+        assert_eq!(hooks.0, [1, 2, 3, 4]);
+    }
+
+    #[tokio::test]
+    async fn test_session_stalls() {
+        let fixture = Arc::new(fixture(batcher(), 4).await);
+        let mut session = session(&fixture)
+            .await
+            .with_stop_on_provider_error(false)
+            .with_max_stalled_steps(0);
+
+        // No step before the first channel is complete derives attributes.
+        let err = session.next_block().await.unwrap_err();
+        assert_eq!(stalled_exit_code(&err), Some(STALLED_EXIT_CODE));
+        let stalled = err.downcast_ref::<StalledError>().unwrap();
+        assert_eq!(stalled.cursor, fixture.l2_cursor_start);
+        assert!(stalled.l1_origin.is_some());
+        assert_eq!(stalled.reason, "no progress after 0 pipeline steps");
+    }
+
+    #[tokio::test]
+    async fn test_session_does_not_back_off_without_data() {
+        // Frames split over many batcher transactions leave the pipeline without
+        // enough data for most steps.
+        let batcher = BatcherSettings {
+            max_frame_size: 40,
+            ..batcher()
+        };
+        let fixture = Arc::new(fixture(batcher, 6).await);
+        let retry = StallArgs::default().retry_policy();
+        assert!(retry.initial_backoff > Duration::ZERO);
+        let mut hooks = Collect::default();
+
+        let start = Instant::now();
+        session(&fixture)
+            .await
+            .with_retry_policy(retry)
+            .run(&mut hooks)
+            .await
+            .unwrap();
+        assert_eq!(hooks.0, [1, 2, 3, 4, 5, 6]);
+        assert!(
+            start.elapsed() < retry.initial_backoff,
+            "derivation took {:?}",
+            start.elapsed()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_session_retry_bound() {
+        let fixture = Arc::new(fixture(batcher(), 4).await);
+        let mut hooks = Collect::default();
+        let err = session(&fixture)
+            .await
+            .with_stop_on_provider_error(false)
+            .with_retry_policy(RetryPolicy {
+                max_attempts: 3,
+                ..RETRY_POLICY
+            })
+            .with_max_stalled_steps(MAX_IDLE_STEPS)
+            .run(&mut hooks)
+            .await
+            .unwrap_err();
+
+        // Every block in the fixture is derived before the provider runs out of data.
+        assert_eq!(hooks.0, [1, 2, 3, 4]);
+        assert_eq!(stalled_exit_code(&err), Some(STALLED_EXIT_CODE));
+        let stalled = err.downcast_ref::<StalledError>().unwrap();
+        assert!(
+            stalled.reason.contains("after 3 attempts"),
+            "{}",
+            stalled.reason
+        );
+    }
+}
@@ -0,0 +1,255 @@
+//! Derivation pipeline providers backed by a derivation fixture.
+
+use crate::cmd::blobs::SidecarProvider;
+use crate::codec::blob::{self, BlobKzg};
+use crate::offline::KonaDerivationFixture;
+use alloy_consensus::{Header, Receipt, TxEnvelope};
+use alloy_eips::eip2718::Decodable2718;
+use alloy_primitives::{Bytes, B256};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use color_eyre::eyre::{ensure, eyre};
+use derivation_driver::{AltDaCommitment, InputFetcher};
+use hashbrown::HashMap;
+use kona_derive::errors::BlobProviderError;
+use kona_derive::traits::{BlobProvider, ChainProvider, L2ChainProvider};
+use kona_primitives::{
+    Blob, BlobSidecar, BlockInfo, IndexedBlobHash, L2BlockInfo, L2ExecutionPayload,
+    L2ExecutionPayloadEnvelope, RollupConfig, SystemConfig,
+};
+use op_test_vectors::derivation::FixtureBlock;
+use std::sync::Arc;
+
+/// A provider that serves L1 blocks, blobs and L2 chain data from a derivation fixture.
+///
+/// Implements [ChainProvider], [L2ChainProvider], [BlobProvider] and [InputFetcher] so
+/// that the derivation pipeline can be run entirely offline, and [SidecarProvider] so
+/// that fixtures can be built from another fixture.
+#[derive(Debug, Clone)]
+pub struct FixtureProvider {
+    /// The fixture to serve data from.
+    fixture: Arc<KonaDerivationFixture>,
+    /// L1 block hashes mapped to their index in the fixture's L1 blocks.
+    hashes: Arc<HashMap<B256, usize>>,
+    /// L1 block numbers mapped to their index in the fixture's L1 blocks.
+    numbers: Arc<HashMap<u64, usize>>,
+    /// Encoded alt-DA commitments mapped to their index in the fixture's alt-DA inputs.
+    commitments: Arc<HashMap<Bytes, usize>>,
+}
+
+impl FixtureProvider {
+    /// Creates a new [FixtureProvider] serving data from the given fixture.
+    pub fn new(fixture: Arc<KonaDerivationFixture>) -> Self {
+        let hashes = fixture
+            .l1_blocks
+            .iter()
+            .enumerate()
+            .map(|(i, b)| (b.header.hash_slow(), i))
+            .collect();
+        let numbers = fixture
+            .l1_blocks
+            .iter()
+            .enumerate()
+            .map(|(i, b)| (b.header.number, i))
+            .collect();
+        let commitments = fixture
+            .alt_da_inputs
+            .iter()
+            .enumerate()
+            .map(|(i, input)| (input.commitment.clone(), i))
+            .collect();
+        Self {
+            fixture,
+            hashes: Arc::new(hashes),
+            numbers: Arc::new(numbers),
+            commitments: Arc::new(commitments),
+        }
+    }
+
+    /// Returns the fixture backing this provider.
+    pub fn fixture(&self) -> &KonaDerivationFixture {
+        &self.fixture
+    }
+
+    /// Returns the L1 block with the given hash.
+    fn block_by_hash(&self, hash: B256) -> Result<&FixtureBlock<Blob>> {
+        self.hashes
+            .get(&hash)
+            .map(|i| &self.fixture.l1_blocks[*i])
+            .ok_or_else(|| anyhow!("L1 block {hash} not found in fixture"))
+    }
+
+    /// Returns the L1 block with the given number.
+    fn block_by_number(&self, number: u64) -> Result<&FixtureBlock<Blob>> {
+        self.numbers
+            .get(&number)
+            .map(|i| &self.fixture.l1_blocks[*i])
+            .ok_or_else(|| anyhow!("L1 block {number} not found in fixture"))
+    }
+}
+
+/// Returns the [BlockInfo] for the given header.
+pub fn block_info(header: &Header) -> BlockInfo {
+    BlockInfo {
+        hash: header.hash_slow(),
+        number: header.number,
+        parent_hash: header.parent_hash,
+        timestamp: header.timestamp,
+    }
+}
+
+#[async_trait]
+impl ChainProvider for FixtureProvider {
+    async fn header_by_hash(&mut self, hash: B256) -> Result<Header> {
+        Ok(self.block_by_hash(hash)?.header.clone())
+    }
+
+    async fn block_info_by_number(&mut self, number: u64) -> Result<BlockInfo> {
+        Ok(block_info(&self.block_by_number(number)?.header))
+    }
+
+    async fn receipts_by_hash(&mut self, hash: B256) -> Result<Vec<Receipt>> {
+        Ok(self.block_by_hash(hash)?.receipts.clone())
+    }
+
+    async fn block_info_and_transactions_by_hash(
+        &mut self,
+        hash: B256,
+    ) -> Result<(BlockInfo, Vec<TxEnvelope>)> {
+        let block = self.block_by_hash(hash)?;
+        let txs = block
+            .transactions
+            .iter()
+            .map(|tx| {
+                TxEnvelope::decode_2718(&mut tx.as_ref())
+                    .map_err(|e| anyhow!("Failed to decode transaction: {e}"))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok((block_info(&block.header), txs))
+    }
+}
+
+#[async_trait]
+impl L2ChainProvider for FixtureProvider {
+    async fn l2_block_info_by_number(&mut self, number: u64) -> Result<L2BlockInfo> {
+        self.fixture
+            .l2_block_infos
+            .get(&number)
+            .copied()
+            .ok_or_else(|| anyhow!("L2 block info {number} not found in fixture"))
+    }
+
+    async fn payload_by_number(&mut self, number: u64) -> Result<L2ExecutionPayloadEnvelope> {
+        let info = self.l2_block_info_by_number(number).await?;
+        let payload = self
+            .fixture
+            .ref_payloads
+            .get(&number)
+            .or_else(|| self.fixture.l2_payloads.get(&number))
+            .ok_or_else(|| anyhow!("L2 payload {number} not found in fixture"))?;
+        Ok(L2ExecutionPayloadEnvelope {
+            parent_beacon_block_root: payload.parent_beacon_block_root,
+            execution_payload: L2ExecutionPayload {
+                parent_hash: info.block_info.parent_hash,
+                block_hash: info.block_info.hash,
+                block_number: number,
+                timestamp: payload.timestamp,
+                fee_recipient: payload.fee_recipient,
+                prev_randao: payload.prev_randao,
+                gas_limit: payload.gas_limit.unwrap_or_default() as u128,
+                withdrawals: payload.withdrawals.clone(),
+                transactions: payload.transactions.iter().map(|tx| tx.0.clone()).collect(),
+                ..Default::default()
+            },
+        })
+    }
+
+    async fn system_config_by_number(
+        &mut self,
+        number: u64,
+        _: Arc<RollupConfig>,
+    ) -> Result<SystemConfig> {
+        self.fixture
+            .l2_system_configs
+            .get(&number)
+            .cloned()
+            .ok_or_else(|| anyhow!("System config {number} not found in fixture"))
+    }
+}
+
+#[async_trait]
+impl BlobProvider for FixtureProvider {
+    async fn get_blobs(
+        &mut self,
+        block_ref: &BlockInfo,
+        blob_hashes: &[IndexedBlobHash],
+    ) -> Result<Vec<Blob>, BlobProviderError> {
+        let block = self
+            .block_by_number(block_ref.number)
+            .map_err(BlobProviderError::Custom)?;
+        if block.blobs.len() != blob_hashes.len() {
+            return Err(BlobProviderError::SidecarLengthMismatch(
+                blob_hashes.len(),
+                block.blobs.len(),
+            ));
+        }
+        Ok(block.blobs.iter().map(|blob| **blob).collect())
+    }
+}
+
+#[async_trait]
+impl SidecarProvider for FixtureProvider {
+    async fn blob_sidecars(
+        &mut self,
+        block_ref: &BlockInfo,
+        blob_hashes: &[IndexedBlobHash],
+    ) -> color_eyre::Result<Vec<BlobSidecar>> {
+        let block = self
+            .block_by_number(block_ref.number)
+            .map_err(|e| eyre!(e))?;
+        ensure!(
+            block.blobs.len() == blob_hashes.len(),
+            "L1 block {} has {} blobs, requested {}",
+            block_ref.number,
+            block.blobs.len(),
+            blob_hashes.len()
+        );
+        block
+            .blobs
+            .iter()
+            .zip(blob_hashes)
+            .enumerate()
+            .map(|(i, (blob, hash))| {
+                // Fixtures only store the KZG commitments and proofs on request, so
+                // missing ones are computed from the blobs.
+                let kzg = match (block.kzg_commitments.get(i), block.kzg_proofs.get(i)) {
+                    (Some(commitment), Some(proof)) => BlobKzg {
+                        commitment: *commitment,
+                        proof: *proof,
+                    },
+                    _ => blob::commit(blob)?,
+                };
+                Ok(BlobSidecar {
+                    blob: **blob,
+                    index: hash.index as u64,
+                    kzg_commitment: kzg.commitment,
+                    kzg_proof: kzg.proof,
+                })
+            })
+            .collect()
+    }
+}
+
+#[async_trait]
+impl InputFetcher for FixtureProvider {
+    async fn get_input(&mut self, commitment: &AltDaCommitment) -> Result<Bytes> {
+        let encoded = commitment.encode();
+        let input = self
+            .commitments
+            .get(&encoded)
+            .map(|i| &self.fixture.alt_da_inputs[*i])
+            .ok_or_else(|| anyhow!("Alt-DA input {encoded} not found in fixture"))?;
+        commitment.verify(&input.data)?;
+        Ok(input.data.clone())
+    }
+}
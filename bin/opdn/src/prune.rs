@@ -0,0 +1,285 @@
+//! Pruning of derivation fixtures down to the data derivation reads.
+
+use crate::mutate::l1::{decode_tx, encode_receipt};
+use crate::offline::{self, KonaDerivationFixture};
+use crate::trie::ordered_trie_proof;
+use alloy_consensus::Transaction;
+use alloy_primitives::TxKind;
+use color_eyre::{eyre::ensure, Result};
+use kona_primitives::{Blob, RollupConfig};
+use op_test_vectors::derivation::{FixtureBlock, PrunedBlock};
+use std::sync::Arc;
+use tracing::debug;
+
+/// The logging target to use for [tracing].
+const TARGET: &str = "prune";
+
+/// Prunes the fixture down to the data derivation reads.
+///
+/// Each L1 block keeps its header, the transactions sent to the batch inbox, and the
+/// receipts with logs emitted by the deposit contract or the `SystemConfig` contract.
+/// The kept transactions and receipts are proven against the header roots, see
+/// [PrunedBlock]. L2 block infos, reference payloads and system configs that are
+/// neither read by the pipeline nor part of the derived range are removed.
+///
+/// The pruned fixture is derived again, and must produce the same payloads as the
+/// original fixture.
+pub async fn prune(fixture: &KonaDerivationFixture) -> Result<KonaDerivationFixture> {
+    let (expected, reads) = offline::derive_recorded(Arc::new(fixture.clone())).await?;
+
+    let mut pruned = fixture.clone();
+    for block in &mut pruned.l1_blocks {
+        prune_block(&fixture.rollup_config, block)?;
+    }
+
+    let derived = fixture.l2_cursor_start..=fixture.l2_cursor_end;
+    pruned
+        .l2_block_infos
+        .retain(|n, _| derived.contains(n) || reads.block_infos.contains_key(n));
+    pruned
+        .l2_system_configs
+        .retain(|n, _| derived.contains(n) || reads.system_configs.contains_key(n));
+    pruned
+        .ref_payloads
+        .retain(|n, _| reads.payloads.contains_key(n));
+
+    let payloads = offline::derive(Arc::new(pruned.clone())).await?;
+    ensure!(
+        payloads == expected,
+        "Pruned fixture derives different payloads than the original fixture"
+    );
+    Ok(pruned)
+}
+
+/// Prunes a single L1 block, keeping the transactions and receipts derivation reads
+/// along with their proofs. Blocks that are already pruned are left unchanged.
+pub fn prune_block(cfg: &RollupConfig, block: &mut FixtureBlock<Blob>) -> Result<()> {
+    if block.pruned.is_some() {
+        return Ok(());
+    }
+    let txs = block
+        .transactions
+        .iter()
+        .map(decode_tx)
+        .collect::<Result<Vec<_>>>()?;
+    ensure!(
+        txs.len() == block.receipts.len(),
+        "L1 block {} has {} transactions but {} receipts",
+        block.header.number,
+        txs.len(),
+        block.receipts.len()
+    );
+
+    let inbox = TxKind::Call(cfg.batch_inbox_address);
+    let transaction_indices = txs
+        .iter()
+        .enumerate()
+        .filter(|(_, tx)| tx.to() == inbox)
+        .map(|(i, _)| i)
+        .collect::<Vec<_>>();
+    let receipt_indices = block
+        .receipts
+        .iter()
+        .enumerate()
+        .filter(|(_, receipt)| {
+            receipt.logs.iter().any(|log| {
+                log.address == cfg.deposit_contract_address
+                    || log.address == cfg.l1_system_config_address
+            })
+        })
+        .map(|(i, _)| i)
+        .collect::<Vec<_>>();
+
+    let raw_txs = block
+        .transactions
+        .iter()
+        .map(|tx| tx.to_vec())
+        .collect::<Vec<_>>();
+    let (transactions_root, transaction_proof) = ordered_trie_proof(&raw_txs, &transaction_indices);
+    ensure!(
+        transactions_root == block.header.transactions_root,
+        "Transactions of L1 block {} do not match its transactions root",
+        block.header.number
+    );
+    let raw_receipts = txs
+        .iter()
+        .zip(&block.receipts)
+        .map(|(tx, receipt)| encode_receipt(tx, receipt))
+        .collect::<Result<Vec<_>>>()?;
+    let (receipts_root, receipt_proof) = ordered_trie_proof(&raw_receipts, &receipt_indices);
+    ensure!(
+        receipts_root == block.header.receipts_root,
+        "Receipts of L1 block {} do not match its receipts root",
+        block.header.number
+    );
+    debug!(target: TARGET,
+        "Pruned L1 block {} to {}/{} transactions and {}/{} receipts",
+        block.header.number, transaction_indices.len(), txs.len(), receipt_indices.len(), txs.len()
+    );
+
+    let pruned = PrunedBlock {
+        transaction_indices: transaction_indices.iter().map(|i| *i as u64).collect(),
+        transaction_proof,
+        receipt_types: receipt_indices
+            .iter()
+            .map(|i| txs[*i].tx_type() as u8)
+            .collect(),
+        receipt_indices: receipt_indices.iter().map(|i| *i as u64).collect(),
+        receipt_proof,
+    };
+    block.transactions = transaction_indices
+        .iter()
+        .map(|i| block.transactions[*i].clone())
+        .collect();
+    block.receipts = receipt_indices
+        .iter()
+        .map(|i| block.receipts[*i].clone())
+        .collect();
+    block.pruned = Some(pruned);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mutate::l1::seal;
+    use crate::synth::tests::rollup_config;
+    use crate::trie::verify_ordered_trie_proof;
+    use alloy_consensus::{
+        Eip658Value, Header, Receipt, SignableTransaction, TxEip1559, TxEnvelope,
+    };
+    use alloy_eips::eip2718::Encodable2718;
+    use alloy_primitives::{address, b256, Address, Bytes, Log, B256};
+    use alloy_signer::SignerSync;
+    use alloy_signer_local::PrivateKeySigner;
+
+    /// Returns a signed transaction to the given address.
+    fn tx(signer: &PrivateKeySigner, nonce: u64, to: Address) -> Bytes {
+        let tx = TxEip1559 {
+            chain_id: 900,
+            nonce,
+            gas_limit: 100_000,
+            max_fee_per_gas: 1_000_000_000,
+            to: TxKind::Call(to),
+            input: Bytes::from(vec![nonce as u8 + 1; 8]),
+            ..Default::default()
+        };
+        let signature = signer.sign_hash_sync(&tx.signature_hash()).unwrap();
+        TxEnvelope::from(tx.into_signed(signature))
+            .encoded_2718()
+            .into()
+    }
+
+    /// Returns a successful receipt with a log emitted by each of the given addresses.
+    fn receipt(cumulative_gas_used: u128, emitters: &[Address]) -> Receipt {
+        Receipt {
+            status: Eip658Value::Eip658(true),
+            cumulative_gas_used,
+            logs: emitters
+                .iter()
+                .map(|address| Log::new_unchecked(*address, vec![B256::ZERO], Bytes::new()))
+                .collect(),
+        }
+    }
+
+    /// Returns a sealed L1 block with a batcher transaction, a deposit, a system config
+    /// update and two unrelated transactions.
+    fn l1_block(cfg: &RollupConfig) -> FixtureBlock<Blob> {
+        let signer = PrivateKeySigner::from_bytes(&b256!(
+            "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80"
+        ))
+        .unwrap();
+        let other = address!("1000000000000000000000000000000000000001");
+        let mut block = FixtureBlock {
+            header: Header {
+                number: 1,
+                ..Default::default()
+            },
+            transactions: vec![
+                tx(&signer, 0, other),
+                tx(&signer, 1, cfg.batch_inbox_address),
+                tx(&signer, 2, cfg.deposit_contract_address),
+                tx(&signer, 3, other),
+                tx(&signer, 4, cfg.l1_system_config_address),
+            ],
+            blobs: vec![],
+            kzg_commitments: vec![],
+            kzg_proofs: vec![],
+            receipts: vec![
+                receipt(21_000, &[other]),
+                receipt(42_000, &[]),
+                receipt(63_000, &[cfg.deposit_contract_address]),
+                receipt(84_000, &[]),
+                receipt(105_000, &[cfg.l1_system_config_address, other]),
+            ],
+            pruned: None,
+        };
+        seal(&mut block).unwrap();
+        block
+    }
+
+    #[test]
+    fn test_prune_block() {
+        let cfg = rollup_config();
+        let original = l1_block(&cfg);
+        let mut block = original.clone();
+        prune_block(&cfg, &mut block).unwrap();
+        let pruned = block.pruned.clone().unwrap();
+
+        assert_eq!(block.header, original.header);
+        assert_eq!(pruned.transaction_indices, [1]);
+        assert_eq!(block.transactions, [original.transactions[1].clone()]);
+        assert_eq!(pruned.receipt_indices, [2, 4]);
+        assert_eq!(pruned.receipt_types, [2, 2]);
+        assert_eq!(
+            block.receipts,
+            [original.receipts[2].clone(), original.receipts[4].clone()]
+        );
+
+        // The kept transactions and receipts are proven against the original roots.
+        for (index, tx) in pruned.transaction_indices.iter().zip(&block.transactions) {
+            verify_ordered_trie_proof(
+                original.header.transactions_root,
+                &pruned.transaction_proof,
+                *index as usize,
+                tx,
+            )
+            .unwrap();
+        }
+        for (index, receipt) in pruned.receipt_indices.iter().zip(&block.receipts) {
+            let tx = decode_tx(&original.transactions[*index as usize]).unwrap();
+            verify_ordered_trie_proof(
+                original.header.receipts_root,
+                &pruned.receipt_proof,
+                *index as usize,
+                &encode_receipt(&tx, receipt).unwrap(),
+            )
+            .unwrap();
+        }
+        // A transaction that was pruned is not proven.
+        assert!(verify_ordered_trie_proof(
+            original.header.transactions_root,
+            &pruned.transaction_proof,
+            0,
+            &original.transactions[0],
+        )
+        .is_err());
+
+        // Pruning again leaves the block unchanged.
+        let mut again = block.clone();
+        prune_block(&cfg, &mut again).unwrap();
+        assert_eq!(again, block);
+    }
+
+    #[test]
+    fn test_prune_block_rejects_wrong_roots() {
+        let cfg = rollup_config();
+        let mut block = l1_block(&cfg);
+        block.transactions.swap(0, 3);
+        assert!(prune_block(&cfg, &mut block).is_err());
+
+        let mut block = l1_block(&cfg);
+        block.receipts[0].cumulative_gas_used += 1;
+        assert!(prune_block(&cfg, &mut block).is_err());
+    }
+}
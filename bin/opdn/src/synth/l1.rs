@@ -0,0 +1,225 @@
+//! Assembly of synthetic L1 blocks carrying batcher transactions.
+
+use crate::synth::L1Settings;
+use crate::trie::ordered_trie_root;
+use alloy_consensus::{
+    Eip658Value, Header, Receipt, ReceiptEnvelope, ReceiptWithBloom, SignableTransaction,
+    TxEip1559, TxEip4844, TxEnvelope, EMPTY_OMMER_ROOT_HASH, EMPTY_ROOT_HASH,
+};
+use alloy_eips::{eip2718::Encodable2718, eip4844::DATA_GAS_PER_BLOB};
+use alloy_primitives::{keccak256, Address, Bloom, Bytes, TxKind, B256, U256};
+use alloy_signer::SignerSync;
+use alloy_signer_local::PrivateKeySigner;
+use color_eyre::{eyre::eyre, Result};
+use kona_primitives::Blob;
+use op_test_vectors::derivation::FixtureBlock;
+
+/// The gas limit of synthetic L1 blocks.
+const L1_GAS_LIMIT: u128 = 30_000_000;
+
+/// The intrinsic gas of a transaction.
+const TX_BASE_GAS: u128 = 21_000;
+
+/// The calldata gas cost per non-zero byte.
+const TX_DATA_GAS: u128 = 16;
+
+/// The calldata gas cost per zero byte.
+const TX_DATA_ZERO_GAS: u128 = 4;
+
+/// A batcher transaction and the blobs it carries.
+#[derive(Debug, Clone)]
+pub struct BatcherTx {
+    /// The signed transaction.
+    pub tx: TxEnvelope,
+    /// The gas used by the transaction.
+    pub gas_used: u128,
+    /// The blobs referenced by the transaction, if any.
+    pub blobs: Vec<Box<Blob>>,
+}
+
+/// Signs batcher transactions sent to the batch inbox.
+#[derive(Debug, Clone)]
+pub struct BatcherSigner {
+    /// The batcher key.
+    signer: PrivateKeySigner,
+    /// The L1 chain id.
+    chain_id: u64,
+    /// The batch inbox address.
+    inbox: Address,
+    /// The next nonce to use.
+    nonce: u64,
+    /// The L1 base fee, used as the max fee per gas.
+    max_fee_per_gas: u128,
+}
+
+impl BatcherSigner {
+    /// Creates a new [BatcherSigner].
+    pub fn new(signer: PrivateKeySigner, chain_id: u64, inbox: Address, l1: &L1Settings) -> Self {
+        Self {
+            signer,
+            chain_id,
+            inbox,
+            nonce: 0,
+            max_fee_per_gas: l1.base_fee,
+        }
+    }
+
+    /// Returns the batcher address.
+    pub fn address(&self) -> Address {
+        self.signer.address()
+    }
+
+    /// Signs a transaction carrying the given data as calldata.
+    pub fn calldata_tx(&mut self, data: Bytes) -> Result<BatcherTx> {
+        let gas_limit = TX_BASE_GAS + calldata_gas(&data);
+        let tx = TxEip1559 {
+            chain_id: self.chain_id,
+            nonce: self.next_nonce(),
+            gas_limit,
+            max_fee_per_gas: self.max_fee_per_gas,
+            max_priority_fee_per_gas: 0,
+            to: TxKind::Call(self.inbox),
+            value: U256::ZERO,
+            access_list: Default::default(),
+            input: data,
+        };
+        let signature = self
+            .signer
+            .sign_hash_sync(&tx.signature_hash())
+            .map_err(|e| eyre!(e))?;
+        Ok(BatcherTx {
+            tx: tx.into_signed(signature).into(),
+            gas_used: gas_limit,
+            blobs: vec![],
+        })
+    }
+
+    /// Signs a transaction carrying the given blobs.
+    pub fn blob_tx(&mut self, blobs: Vec<Box<Blob>>) -> Result<BatcherTx> {
+        let blob_versioned_hashes = blobs
+            .iter()
+            .map(|blob| crate::codec::blob::versioned_hash(blob))
+            .collect::<Result<Vec<_>>>()?;
+        let tx = TxEip4844 {
+            chain_id: self.chain_id,
+            nonce: self.next_nonce(),
+            gas_limit: TX_BASE_GAS,
+            max_fee_per_gas: self.max_fee_per_gas,
+            max_priority_fee_per_gas: 0,
+            to: self.inbox,
+            value: U256::ZERO,
+            access_list: Default::default(),
+            blob_versioned_hashes,
+            max_fee_per_blob_gas: 1,
+            input: Bytes::new(),
+        };
+        let signature = self
+            .signer
+            .sign_hash_sync(&tx.signature_hash())
+            .map_err(|e| eyre!(e))?;
+        Ok(BatcherTx {
+            tx: tx.into_signed(signature).into(),
+            gas_used: TX_BASE_GAS,
+            blobs,
+        })
+    }
+
+    /// Returns the next nonce and increments it.
+    fn next_nonce(&mut self) -> u64 {
+        let nonce = self.nonce;
+        self.nonce += 1;
+        nonce
+    }
+}
+
+/// Returns the calldata gas cost of the given transaction input.
+fn calldata_gas(data: &[u8]) -> u128 {
+    let zeros = data.iter().filter(|b| **b == 0).count() as u128;
+    TX_DATA_ZERO_GAS * zeros + TX_DATA_GAS * (data.len() as u128 - zeros)
+}
+
+/// Builds a synthetic L1 [FixtureBlock] on top of the given parent hash.
+///
+/// The header commits to the transactions and receipts of the block so that the
+/// block hash is consistent with its contents.
+pub fn build_block(
+    number: u64,
+    timestamp: u64,
+    parent_hash: B256,
+    txs: Vec<BatcherTx>,
+    l1: &L1Settings,
+) -> FixtureBlock<Blob> {
+    let mut transactions = Vec::with_capacity(txs.len());
+    let mut receipts = Vec::with_capacity(txs.len());
+    let mut encoded_receipts = Vec::with_capacity(txs.len());
+    let mut blobs = Vec::new();
+    let mut gas_used = 0;
+    for tx in txs {
+        gas_used += tx.gas_used;
+        let receipt = Receipt {
+            status: Eip658Value::Eip658(true),
+            cumulative_gas_used: gas_used,
+            logs: vec![],
+        };
+        let with_bloom = ReceiptWithBloom::new(receipt.clone(), Bloom::ZERO);
+        let envelope = match tx.tx {
+            TxEnvelope::Eip4844(_) => ReceiptEnvelope::Eip4844(with_bloom),
+            _ => ReceiptEnvelope::Eip1559(with_bloom),
+        };
+        encoded_receipts.push(envelope.encoded_2718());
+        transactions.push(tx.tx.encoded_2718());
+        receipts.push(receipt);
+        blobs.extend(tx.blobs);
+    }
+
+    let header = Header {
+        parent_hash,
+        ommers_hash: EMPTY_OMMER_ROOT_HASH,
+        state_root: EMPTY_ROOT_HASH,
+        transactions_root: ordered_trie_root(&transactions),
+        receipts_root: ordered_trie_root(&encoded_receipts),
+        withdrawals_root: Some(EMPTY_ROOT_HASH),
+        number,
+        gas_limit: L1_GAS_LIMIT,
+        gas_used,
+        timestamp,
+        mix_hash: keccak256(number.to_be_bytes()),
+        base_fee_per_gas: Some(l1.base_fee),
+        blob_gas_used: Some(blobs.len() as u128 * DATA_GAS_PER_BLOB as u128),
+        excess_blob_gas: Some(l1.excess_blob_gas),
+        parent_beacon_block_root: Some(B256::ZERO),
+        ..Default::default()
+    };
+
+    FixtureBlock {
+        header,
+        transactions: transactions.into_iter().map(Into::into).collect(),
+        blobs,
+        kzg_commitments: vec![],
+        kzg_proofs: vec![],
+        receipts,
+        pruned: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::synth::DEFAULT_BATCHER_KEY;
+
+    #[test]
+    fn test_calldata_gas() {
+        assert_eq!(calldata_gas(&[]), 0);
+        assert_eq!(calldata_gas(&[0, 0]), 8);
+        assert_eq!(calldata_gas(&[1, 0, 0xff]), 36);
+
+        let mut signer = BatcherSigner::new(
+            PrivateKeySigner::from_bytes(&DEFAULT_BATCHER_KEY).unwrap(),
+            900,
+            Address::ZERO,
+            &L1Settings::default(),
+        );
+        let tx = signer.calldata_tx(Bytes::from(vec![0, 0, 1])).unwrap();
+        assert_eq!(tx.gas_used, TX_BASE_GAS + 24);
+    }
+}
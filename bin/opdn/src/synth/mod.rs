@@ -0,0 +1,648 @@
+//! Synthetic derivation fixture builder.
+//!
+//! Encodes a list of L2 payloads into batches, channels and frames, packs them as
+//! calldata or blobs into synthetic L1 blocks, and emits a complete
+//! [DerivationFixture](op_test_vectors::derivation::DerivationFixture) without
+//! touching any RPC.
+
+use crate::codec::{blob, channel};
+use crate::offline::{self, block_info, KonaDerivationFixture};
+use alloy_primitives::{b256, keccak256, B256};
+use alloy_signer_local::PrivateKeySigner;
+use color_eyre::{
+    eyre::{bail, ensure, eyre},
+    Result,
+};
+use hashbrown::HashMap;
+use kona_derive::batch::{SingleBatch, SpanBatch};
+use kona_primitives::{
+    BlockID, BlockInfo, L2BlockInfo, L2PayloadAttributes, RawTransaction, RollupConfig,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tracing::{debug, info};
+
+pub mod l1;
+use l1::BatcherSigner;
+
+/// The logging target to use for [tracing].
+const TARGET: &str = "synth";
+
+/// The EIP-2718 type of deposit transactions, which are never batched.
+const DEPOSIT_TX_TYPE: u8 = 0x7E;
+
+/// The default batcher key, the first well-known anvil development account.
+const DEFAULT_BATCHER_KEY: B256 =
+    b256!("ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80");
+
+/// The type of batch to encode the L2 payloads into.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchKind {
+    /// One singular batch per L2 block.
+    #[default]
+    Singular,
+    /// One span batch per channel.
+    Span,
+}
+
+/// How batcher transactions make their data available on L1.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum DataAvailability {
+    /// Frames are posted as transaction calldata.
+    #[default]
+    Calldata,
+    /// Frames are posted as EIP-4844 blobs, one frame per blob.
+    Blobs,
+}
+
+/// Settings for the synthetic batcher.
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+#[serde(default, rename_all = "camelCase")]
+pub struct BatcherSettings {
+    /// The private key used to sign batcher transactions.
+    /// The rollup config's batcher address is replaced with the address of this key.
+    pub private_key: B256,
+    /// The type of batch to encode.
+    pub batch_type: BatchKind,
+    /// How frames are posted to L1.
+    pub data_availability: DataAvailability,
+    /// The maximum encoded size of a single frame.
+    pub max_frame_size: usize,
+    /// The number of L2 blocks to put in each channel.
+    pub blocks_per_channel: usize,
+}
+
+impl Default for BatcherSettings {
+    fn default() -> Self {
+        Self {
+            private_key: DEFAULT_BATCHER_KEY,
+            batch_type: BatchKind::default(),
+            data_availability: DataAvailability::default(),
+            max_frame_size: 120_000,
+            blocks_per_channel: 1,
+        }
+    }
+}
+
+/// Settings for the synthetic L1 chain.
+#[derive(Serialize, Deserialize, Clone, Debug, Eq, PartialEq)]
+#[serde(default, rename_all = "camelCase")]
+pub struct L1Settings {
+    /// The L1 block time in seconds.
+    pub block_time: u64,
+    /// The base fee of every L1 block.
+    pub base_fee: u128,
+    /// The excess blob gas of every L1 block.
+    pub excess_blob_gas: u128,
+}
+
+impl Default for L1Settings {
+    fn default() -> Self {
+        Self {
+            block_time: 12,
+            base_fee: 1_000_000_000,
+            excess_blob_gas: 0,
+        }
+    }
+}
+
+/// The input to the `synth` subcommand.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SynthSpec {
+    /// The rollup config. Its genesis is rewritten to match the synthetic chain.
+    pub rollup_config: RollupConfig,
+    /// The batcher settings.
+    #[serde(default)]
+    pub batcher: BatcherSettings,
+    /// The L1 chain settings.
+    #[serde(default)]
+    pub l1: L1Settings,
+    /// The L2 payloads to batch, in order, starting right after the L2 genesis.
+    pub payloads: Vec<L2PayloadAttributes>,
+}
+
+/// Builds a [KonaDerivationFixture] from a list of L2 payloads.
+#[derive(Debug, Clone)]
+pub struct SynthFixtureBuilder {
+    /// The rollup config.
+    rollup_config: RollupConfig,
+    /// The batcher settings.
+    batcher: BatcherSettings,
+    /// The L1 chain settings.
+    l1: L1Settings,
+    /// The L2 payloads to batch.
+    payloads: Vec<L2PayloadAttributes>,
+}
+
+impl From<SynthSpec> for SynthFixtureBuilder {
+    fn from(spec: SynthSpec) -> Self {
+        Self {
+            rollup_config: spec.rollup_config,
+            batcher: spec.batcher,
+            l1: spec.l1,
+            payloads: spec.payloads,
+        }
+    }
+}
+
+impl SynthFixtureBuilder {
+    /// Creates a new [SynthFixtureBuilder] for the given rollup config.
+    pub fn new(rollup_config: RollupConfig) -> Self {
+        Self {
+            rollup_config,
+            batcher: BatcherSettings::default(),
+            l1: L1Settings::default(),
+            payloads: vec![],
+        }
+    }
+
+    /// Sets the batcher settings.
+    pub fn with_batcher(mut self, batcher: BatcherSettings) -> Self {
+        self.batcher = batcher;
+        self
+    }
+
+    /// Sets the L1 chain settings.
+    pub fn with_l1(mut self, l1: L1Settings) -> Self {
+        self.l1 = l1;
+        self
+    }
+
+    /// Appends an L2 payload.
+    pub fn with_payload(mut self, payload: L2PayloadAttributes) -> Self {
+        self.payloads.push(payload);
+        self
+    }
+
+    /// Appends a list of L2 payloads.
+    pub fn with_payloads(
+        mut self,
+        payloads: impl IntoIterator<Item = L2PayloadAttributes>,
+    ) -> Self {
+        self.payloads.extend(payloads);
+        self
+    }
+
+    /// Builds the fixture.
+    ///
+    /// The expected `l2_payloads` are not taken from the input. They are produced by
+    /// running the derivation pipeline offline over the synthetic L1 blocks, and every
+    /// input payload must be derived with its timestamp and transactions for the build
+    /// to succeed.
+    pub async fn build(self) -> Result<KonaDerivationFixture> {
+        let Self {
+            mut rollup_config,
+            batcher,
+            l1,
+            payloads,
+        } = self;
+        ensure!(!payloads.is_empty(), "At least one L2 payload is required");
+        ensure!(
+            batcher.blocks_per_channel > 0,
+            "Blocks per channel must be greater than zero"
+        );
+        ensure!(l1.block_time > 0, "L1 block time must be greater than zero");
+
+        let genesis = rollup_config.genesis.clone();
+        for (i, payload) in payloads.iter().enumerate() {
+            let expected = genesis.l2_time + (i as u64 + 1) * rollup_config.block_time;
+            ensure!(
+                payload.timestamp == expected,
+                "Payload {i} has timestamp {}, expected {expected}",
+                payload.timestamp
+            );
+        }
+
+        let signer = PrivateKeySigner::from_bytes(&batcher.private_key).map_err(|e| eyre!(e))?;
+        let mut system_config = genesis
+            .system_config
+            .clone()
+            .ok_or_else(|| eyre!("Rollup config is missing the genesis system config"))?;
+        system_config.batcher_address = signer.address();
+        let mut signer = BatcherSigner::new(
+            signer,
+            rollup_config.l1_chain_id,
+            rollup_config.batch_inbox_address,
+            &l1,
+        );
+
+        // Assign every L2 block the latest L1 origin its timestamp allows, advancing
+        // at most one epoch per block.
+        let mut origins = Vec::with_capacity(payloads.len());
+        let mut prev_origin = 0;
+        for payload in &payloads {
+            let origin =
+                ((payload.timestamp - genesis.l2_time) / l1.block_time).min(prev_origin + 1);
+            let origin_time = genesis.l2_time + origin * l1.block_time;
+            ensure!(
+                payload.timestamp <= origin_time + rollup_config.max_sequencer_drift,
+                "L2 block at {} exceeds the max sequencer drift",
+                payload.timestamp
+            );
+            origins.push(origin);
+            prev_origin = origin;
+        }
+
+        // Each channel is posted in its own L1 block, after the origins of its batches.
+        let chunks = payloads
+            .chunks(batcher.blocks_per_channel)
+            .zip(origins.chunks(batcher.blocks_per_channel))
+            .collect::<Vec<_>>();
+        let mut inclusions = Vec::with_capacity(chunks.len());
+        let mut prev_inclusion = 0;
+        for (_, chunk_origins) in &chunks {
+            let first = chunk_origins.first().copied().unwrap_or_default();
+            let last = chunk_origins.last().copied().unwrap_or_default();
+            let inclusion = (prev_inclusion + 1).max(last + 1);
+            ensure!(
+                inclusion - first < rollup_config.seq_window_size,
+                "Channel cannot be included within the sequencing window, use fewer blocks per channel"
+            );
+            inclusions.push(inclusion);
+            prev_inclusion = inclusion;
+        }
+
+        let l1_start = genesis.l1.number;
+        let l1_count = prev_inclusion + 1;
+        let l2_hash =
+            |number: u64| keccak256([b"synth-l2".as_slice(), &number.to_be_bytes()].concat());
+        // The hash of the L1 origin of the genesis is only known once the first L1
+        // block is built, but its number already decides the sequence numbers.
+        let mut genesis_l2 = L2BlockInfo {
+            block_info: BlockInfo {
+                hash: l2_hash(genesis.l2.number),
+                number: genesis.l2.number,
+                parent_hash: B256::ZERO,
+                timestamp: genesis.l2_time,
+            },
+            l1_origin: BlockID {
+                hash: B256::ZERO,
+                number: l1_start,
+            },
+            seq_num: 0,
+        };
+
+        let mut l1_blocks = Vec::with_capacity(l1_count as usize);
+        let mut l1_infos: Vec<BlockInfo> = Vec::with_capacity(l1_count as usize);
+        let mut l2_block_infos = HashMap::new();
+        let mut l2_system_configs = HashMap::new();
+        let mut parent = genesis_l2;
+        let mut chunks = chunks.into_iter().zip(inclusions).enumerate().peekable();
+        for index in 0..l1_count {
+            let mut txs = Vec::new();
+            while let Some((channel_index, ((chunk, chunk_origins), _))) =
+                chunks.next_if(|(_, (_, inclusion))| *inclusion == index)
+            {
+                let mut data = Vec::new();
+                let mut span = SpanBatch {
+                    genesis_timestamp: genesis.l2_time,
+                    chain_id: rollup_config.l2_chain_id,
+                    ..Default::default()
+                };
+                for (payload, origin) in chunk.iter().zip(chunk_origins) {
+                    let epoch = l1_infos[*origin as usize];
+                    let seq_num = if parent.l1_origin.number == epoch.number {
+                        parent.seq_num + 1
+                    } else {
+                        0
+                    };
+                    let batch = SingleBatch {
+                        parent_hash: parent.block_info.hash,
+                        epoch_num: epoch.number,
+                        epoch_hash: epoch.hash,
+                        timestamp: payload.timestamp,
+                        transactions: payload
+                            .transactions
+                            .iter()
+                            .filter(|tx| tx.0.first() != Some(&DEPOSIT_TX_TYPE))
+                            .cloned()
+                            .collect::<Vec<RawTransaction>>(),
+                    };
+                    match batcher.batch_type {
+                        BatchKind::Singular => channel::push_single_batch(&batch, &mut data),
+                        BatchKind::Span => {
+                            ensure!(
+                                rollup_config.is_delta_active(payload.timestamp),
+                                "Span batches require the delta hardfork"
+                            );
+                            span.append_singular_batch(batch, seq_num)
+                                .map_err(|e| eyre!("Failed to append to span batch: {e:?}"))?;
+                        }
+                    }
+
+                    let number = parent.block_info.number + 1;
+                    parent = L2BlockInfo {
+                        block_info: BlockInfo {
+                            hash: l2_hash(number),
+                            number,
+                            parent_hash: parent.block_info.hash,
+                            timestamp: payload.timestamp,
+                        },
+                        l1_origin: BlockID {
+                            hash: epoch.hash,
+                            number: epoch.number,
+                        },
+                        seq_num,
+                    };
+                    l2_block_infos.insert(number, parent);
+                    l2_system_configs.insert(number, system_config.clone());
+                }
+                if batcher.batch_type == BatchKind::Span {
+                    channel::push_span_batch(&span, &mut data)?;
+                }
+
+                let id: channel::ChannelId = keccak256((channel_index as u64).to_be_bytes())[..16]
+                    .try_into()
+                    .map_err(|e| eyre!("Invalid channel id: {e}"))?;
+                let compressed = channel::compress(&data);
+                match batcher.data_availability {
+                    DataAvailability::Calldata => {
+                        for frame in channel::split_frames(id, &compressed, batcher.max_frame_size)?
+                        {
+                            txs.push(signer.calldata_tx(channel::tx_data(&[frame]))?);
+                        }
+                    }
+                    DataAvailability::Blobs => {
+                        let timestamp = genesis.l2_time + index * l1.block_time;
+                        ensure!(
+                            rollup_config.is_ecotone_active(timestamp),
+                            "Blob data availability requires the ecotone hardfork"
+                        );
+                        // The derivation version byte shares the blob with the frame.
+                        let max_frame_size =
+                            batcher.max_frame_size.min(blob::MAX_BLOB_DATA_SIZE - 1);
+                        for frame in channel::split_frames(id, &compressed, max_frame_size)? {
+                            let blob = blob::encode(&channel::tx_data(&[frame]))?;
+                            txs.push(signer.blob_tx(vec![blob])?);
+                        }
+                    }
+                }
+                debug!(target: TARGET, "Posted channel {} with {} blocks in L1 block {}", channel_index, chunk.len(), l1_start + index);
+            }
+
+            let parent_hash = l1_infos.last().map(|b| b.hash).unwrap_or_default();
+            let block = l1::build_block(
+                l1_start + index,
+                genesis.l2_time + index * l1.block_time,
+                parent_hash,
+                txs,
+                &l1,
+            );
+            l1_infos.push(block_info(&block.header));
+            l1_blocks.push(block);
+        }
+
+        // Anchor the genesis to the synthetic chains.
+        genesis_l2.l1_origin.hash = l1_infos[0].hash;
+        l2_block_infos.insert(genesis_l2.block_info.number, genesis_l2);
+        l2_system_configs.insert(genesis_l2.block_info.number, system_config.clone());
+        rollup_config.genesis.l1 = genesis_l2.l1_origin;
+        rollup_config.genesis.l2 = BlockID {
+            hash: genesis_l2.block_info.hash,
+            number: genesis_l2.block_info.number,
+        };
+        rollup_config.genesis.system_config = Some(system_config);
+
+        let mut fixture = KonaDerivationFixture {
+            rollup_config,
+            l1_blocks,
+            l2_payloads: HashMap::new(),
+            ref_payloads: HashMap::new(),
+            l2_system_configs,
+            l2_block_infos,
+            l2_cursor_start: genesis_l2.block_info.number,
+            l2_cursor_end: parent.block_info.number,
+            alt_da_inputs: vec![],
+        };
+
+        let derived = offline::derive(Arc::new(fixture.clone())).await?;
+        check_derived(&payloads, fixture.l2_cursor_start, &derived)?;
+        info!(target: TARGET, "Derived {} L2 blocks from {} synthetic L1 blocks", derived.len(), fixture.l1_blocks.len());
+        fixture.ref_payloads = derived.clone();
+        fixture.l2_payloads = derived;
+        Ok(fixture)
+    }
+}
+
+/// Checks that every input payload, numbered from the block after `l2_start`, was
+/// derived with its timestamp and its transactions other than deposits.
+fn check_derived(
+    payloads: &[L2PayloadAttributes],
+    l2_start: u64,
+    derived: &HashMap<u64, L2PayloadAttributes>,
+) -> Result<()> {
+    let batched = |payload: &L2PayloadAttributes| {
+        payload
+            .transactions
+            .iter()
+            .filter(|tx| tx.0.first() != Some(&DEPOSIT_TX_TYPE))
+            .cloned()
+            .collect::<Vec<_>>()
+    };
+    for (number, payload) in (l2_start + 1..).zip(payloads) {
+        let Some(derived) = derived.get(&number) else {
+            bail!("Synthetic L1 blocks did not derive L2 block {number}");
+        };
+        ensure!(
+            derived.timestamp == payload.timestamp,
+            "L2 block {number} was derived with timestamp {}, expected {}",
+            derived.timestamp,
+            payload.timestamp
+        );
+        ensure!(
+            batched(derived) == batched(payload),
+            "L2 block {number} was derived with different transactions than its payload"
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use alloy_primitives::{address, Bytes};
+    use kona_primitives::{ChainGenesis, SystemConfig};
+
+    /// The L2 gas limit of the synthetic chain.
+    pub(crate) const GAS_LIMIT: u64 = 30_000_000;
+
+    /// Returns a rollup config with every hardfork up to ecotone active at genesis.
+    pub(crate) fn rollup_config() -> RollupConfig {
+        RollupConfig {
+            genesis: ChainGenesis {
+                system_config: Some(SystemConfig {
+                    gas_limit: GAS_LIMIT,
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+            block_time: 2,
+            max_sequencer_drift: 600,
+            seq_window_size: 20,
+            channel_timeout: 10,
+            l1_chain_id: 900,
+            l2_chain_id: 901,
+            regolith_time: Some(0),
+            canyon_time: Some(0),
+            delta_time: Some(0),
+            ecotone_time: Some(0),
+            batch_inbox_address: address!("ff00000000000000000000000000000000000901"),
+            deposit_contract_address: address!("6900000000000000000000000000000000000001"),
+            l1_system_config_address: address!("6900000000000000000000000000000000000002"),
+            ..Default::default()
+        }
+    }
+
+    /// Returns `count` payloads following the genesis of the rollup config, each
+    /// carrying a single opaque transaction.
+    pub(crate) fn payloads(cfg: &RollupConfig, count: u64) -> Vec<L2PayloadAttributes> {
+        (1..=count)
+            .map(|i| L2PayloadAttributes {
+                timestamp: cfg.genesis.l2_time + i * cfg.block_time,
+                transactions: vec![RawTransaction(Bytes::from(vec![0x02, i as u8, 0xaa]))],
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    /// Builds a synthetic fixture of `count` L2 blocks with the given batcher settings.
+    pub(crate) async fn fixture(batcher: BatcherSettings, count: u64) -> KonaDerivationFixture {
+        let cfg = rollup_config();
+        SynthFixtureBuilder::new(cfg.clone())
+            .with_batcher(batcher)
+            .with_payloads(payloads(&cfg, count))
+            .build()
+            .await
+            .unwrap()
+    }
+
+    /// Asserts that the fixture derived exactly the input payloads of the rollup config,
+    /// each preceded by its L1 info deposit.
+    fn assert_derived(fixture: &KonaDerivationFixture, cfg: &RollupConfig, count: u64) {
+        let inputs = payloads(cfg, count);
+        let start = cfg.genesis.l2.number;
+        assert_eq!(fixture.l2_cursor_start, start);
+        assert_eq!(fixture.l2_cursor_end, start + count);
+        assert_eq!(fixture.l2_payloads.len(), inputs.len());
+        for (number, input) in (start + 1..).zip(&inputs) {
+            let derived = &fixture.l2_payloads[&number];
+            assert_eq!(derived.timestamp, input.timestamp);
+            assert_eq!(derived.gas_limit, Some(GAS_LIMIT));
+            assert!(derived.no_tx_pool);
+            assert_eq!(derived.transactions.len(), 2);
+            assert_eq!(derived.transactions[0].0[0], DEPOSIT_TX_TYPE);
+            assert_eq!(derived.transactions[1..], input.transactions[..]);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_synth_calldata() {
+        let batcher = BatcherSettings {
+            blocks_per_channel: 3,
+            // Split every channel over several frames.
+            max_frame_size: 40,
+            ..Default::default()
+        };
+        let fixture = fixture(batcher, 9).await;
+        assert_derived(&fixture, &rollup_config(), 9);
+        let batcher_txs = fixture
+            .l1_blocks
+            .iter()
+            .map(|block| block.transactions.len())
+            .sum::<usize>();
+        assert!(batcher_txs > 3);
+    }
+
+    #[tokio::test]
+    async fn test_synth_blobs() {
+        let batcher = BatcherSettings {
+            data_availability: DataAvailability::Blobs,
+            blocks_per_channel: 2,
+            ..Default::default()
+        };
+        let fixture = fixture(batcher, 6).await;
+        assert_derived(&fixture, &rollup_config(), 6);
+        let blobs = fixture
+            .l1_blocks
+            .iter()
+            .map(|block| block.blobs.len())
+            .sum::<usize>();
+        assert_eq!(blobs, 3);
+    }
+
+    #[tokio::test]
+    async fn test_synth_non_zero_genesis() {
+        let mut cfg = rollup_config();
+        cfg.genesis.l1.number = 100;
+        cfg.genesis.l2.number = 50;
+        cfg.genesis.l2_time = 1_000;
+        let batcher = BatcherSettings {
+            blocks_per_channel: 3,
+            ..Default::default()
+        };
+        let fixture = SynthFixtureBuilder::new(cfg.clone())
+            .with_batcher(batcher)
+            .with_payloads(payloads(&cfg, 9))
+            .build()
+            .await
+            .unwrap();
+        assert_derived(&fixture, &cfg, 9);
+
+        let genesis = fixture.l2_block_infos[&50];
+        assert_eq!(genesis.l1_origin.number, 100);
+        assert_eq!(
+            genesis.l1_origin.hash,
+            fixture.l1_blocks[0].header.hash_slow()
+        );
+        // The first L2 block shares the L1 origin of the genesis.
+        let first = fixture.l2_block_infos[&51];
+        assert_eq!(first.l1_origin, genesis.l1_origin);
+        assert_eq!(first.seq_num, 1);
+    }
+
+    #[test]
+    fn test_check_derived() {
+        let cfg = rollup_config();
+        let inputs = payloads(&cfg, 2);
+        let deposit = RawTransaction(Bytes::from(vec![DEPOSIT_TX_TYPE, 0x01]));
+        let derived = (1u64..)
+            .zip(&inputs)
+            .map(|(number, input)| {
+                let mut derived = input.clone();
+                derived.transactions.insert(0, deposit.clone());
+                (number, derived)
+            })
+            .collect::<HashMap<_, _>>();
+        check_derived(&inputs, 0, &derived).unwrap();
+
+        let mut missing = derived.clone();
+        missing.remove(&2);
+        assert!(check_derived(&inputs, 0, &missing).is_err());
+
+        let mut retimed = derived.clone();
+        retimed.get_mut(&1).unwrap().timestamp += 1;
+        assert!(check_derived(&inputs, 0, &retimed).is_err());
+
+        let mut dropped = derived.clone();
+        dropped.get_mut(&2).unwrap().transactions.truncate(1);
+        assert!(check_derived(&inputs, 0, &dropped).is_err());
+
+        // The numbers are counted from the L2 start.
+        assert!(check_derived(&inputs, 10, &derived).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_synth_rejects_misaligned_timestamps() {
+        let cfg = rollup_config();
+        let mut payloads = payloads(&cfg, 2);
+        payloads[1].timestamp += 1;
+        let result = SynthFixtureBuilder::new(cfg)
+            .with_payloads(payloads)
+            .build()
+            .await;
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,165 @@
+//! Merkle Patricia Trie helpers for block roots.
+
+use alloy_primitives::{keccak256, Bytes, B256};
+use alloy_rlp::{Decodable, Encodable};
+use alloy_trie::{
+    nodes::TrieNode,
+    proof::{verify_proof, ProofRetainer},
+    HashBuilder, Nibbles,
+};
+use color_eyre::{eyre::eyre, Result};
+use std::collections::HashMap;
+
+/// Computes the root of an ordered trie, as used for the transactions and
+/// receipts roots of a block header. Each item is keyed by its RLP encoded index.
+///
+/// The items must be at least 32 bytes long, as the [HashBuilder] does not support
+/// nodes embedded in their parent. Encoded transactions and receipts always are.
+pub fn ordered_trie_root(items: &[Vec<u8>]) -> B256 {
+    ordered_trie_builder(items, HashBuilder::default()).root()
+}
+
+/// Computes the root of an ordered trie, along with the trie nodes that prove the
+/// items at the given indices against it.
+pub fn ordered_trie_proof(items: &[Vec<u8>], indices: &[usize]) -> (B256, Vec<Bytes>) {
+    let targets = indices.iter().map(|i| index_key(*i)).collect();
+    let builder = HashBuilder::default().with_proof_retainer(ProofRetainer::new(targets));
+    let mut builder = ordered_trie_builder(items, builder);
+    let root = builder.root();
+    let proof = builder.take_proofs().into_values().collect();
+    (root, proof)
+}
+
+/// Verifies that the item at the given index is part of the ordered trie with the given
+/// root, using the trie nodes of a proof from [ordered_trie_proof]. The proof may hold
+/// the nodes proving other items as well.
+pub fn verify_ordered_trie_proof(
+    root: B256,
+    proof: &[Bytes],
+    index: usize,
+    item: &[u8],
+) -> Result<()> {
+    let nodes = proof
+        .iter()
+        .map(|node| (keccak256(node), node))
+        .collect::<HashMap<_, _>>();
+    let key = index_key(index);
+
+    // Collect the nodes on the path to the item, starting at the root.
+    let mut path = Vec::new();
+    let mut walked = 0;
+    let mut next = root;
+    while let Some(node) = nodes.get(&next) {
+        path.push(*node);
+        let node = TrieNode::decode(&mut node.as_ref())
+            .map_err(|e| eyre!("Failed to decode trie node: {e}"))?;
+        let child = match node {
+            TrieNode::Branch(branch) => {
+                let Some(&nibble) = key.get(walked) else {
+                    break;
+                };
+                if !branch.state_mask.is_bit_set(nibble) {
+                    break;
+                }
+                walked += 1;
+                let position = (0..nibble)
+                    .filter(|i| branch.state_mask.is_bit_set(*i))
+                    .count();
+                branch.stack[position].clone()
+            }
+            TrieNode::Extension(extension) => {
+                walked += extension.key.len();
+                extension.child
+            }
+            TrieNode::Leaf(_) => break,
+        };
+        // Children shorter than a hash are embedded in their parent.
+        if child.len() != B256::len_bytes() + 1 {
+            break;
+        }
+        next = B256::from_slice(&child[1..]);
+    }
+
+    verify_proof(root, key, Some(item.to_vec()), path)
+        .map_err(|e| eyre!("Invalid proof of trie item {index}: {e}"))
+}
+
+/// Returns the trie key of the item at the given index.
+fn index_key(index: usize) -> Nibbles {
+    let mut key = Vec::new();
+    index.encode(&mut key);
+    Nibbles::unpack(key)
+}
+
+/// Adds the items to the builder as leaves of an ordered trie.
+fn ordered_trie_builder(items: &[Vec<u8>], mut builder: HashBuilder) -> HashBuilder {
+    let mut leaves = items
+        .iter()
+        .enumerate()
+        .map(|(i, item)| (index_key(i), item))
+        .collect::<Vec<_>>();
+    // Leaves must be added in key order.
+    leaves.sort_by(|a, b| a.0.cmp(&b.0));
+
+    for (key, item) in leaves {
+        builder.add_leaf(key, item);
+    }
+    builder
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_consensus::EMPTY_ROOT_HASH;
+    use alloy_primitives::b256;
+
+    /// Returns `count` items of varying lengths, each long enough to be hashed.
+    fn items(count: usize) -> Vec<Vec<u8>> {
+        (0..count).map(|i| vec![i as u8; 32 + i % 40]).collect()
+    }
+
+    #[test]
+    fn test_ordered_trie_root_empty() {
+        assert_eq!(ordered_trie_root(&[]), EMPTY_ROOT_HASH);
+    }
+
+    #[test]
+    fn test_ordered_trie_root_known() {
+        assert_eq!(
+            ordered_trie_root(&items(1)),
+            b256!("6d4fa08198f4ce68ffef70fbc38097c5f1e847f754f8ceae51e7c4de45e0da23")
+        );
+        assert_eq!(
+            ordered_trie_root(&items(3)),
+            b256!("0a73267ee866ab691522412047571ff1474114ec8a8b42e2983adb6a2d40adbf")
+        );
+        // Crosses the 0x7f / 0x80 boundary of the RLP encoded index keys.
+        assert_eq!(
+            ordered_trie_root(&items(130)),
+            b256!("be31ed3b112c7d701ce5b2772be0dd01748b5e5d04305ec047a3125f7905b40c")
+        );
+    }
+
+    #[test]
+    fn test_ordered_trie_proof() {
+        let items = items(130);
+        let (root, proof) = ordered_trie_proof(&items, &[0, 127, 128]);
+        assert_eq!(root, ordered_trie_root(&items));
+        assert!(!proof.is_empty());
+        // The root node is part of every proof.
+        assert!(proof.iter().any(|node| keccak256(node) == root));
+    }
+
+    #[test]
+    fn test_verify_ordered_trie_proof() {
+        let items = items(130);
+        let (root, proof) = ordered_trie_proof(&items, &[0, 127, 128]);
+        for index in [0, 127, 128] {
+            verify_ordered_trie_proof(root, &proof, index, &items[index]).unwrap();
+        }
+        // Items that are not proven, or do not match, are rejected.
+        assert!(verify_ordered_trie_proof(root, &proof, 1, &items[1]).is_err());
+        assert!(verify_ordered_trie_proof(root, &proof, 0, &items[1]).is_err());
+        assert!(verify_ordered_trie_proof(B256::ZERO, &proof, 0, &items[0]).is_err());
+    }
+}
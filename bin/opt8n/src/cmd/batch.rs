@@ -0,0 +1,67 @@
+use clap::Parser;
+use color_eyre::eyre::{ensure, eyre};
+use fixture_manifest::{Batch, BatchArgs, ExecutionEntry, Manifest};
+use tokio::process::Command;
+
+#[derive(Parser, Clone, Debug)]
+pub struct BatchCmd {
+    #[command(flatten)]
+    pub batch: BatchArgs,
+    /// Extra arguments passed to every `opt8n script` run. They are part of the content
+    /// hash of every entry, so changing them regenerates the fixtures.
+    #[arg(last = true, help = "Extra arguments passed to every script run")]
+    pub script_args: Vec<String>,
+}
+
+impl BatchCmd {
+    /// Generates every execution fixture of the manifest that is not up to date, and
+    /// prints a summary table.
+    pub async fn run(self) -> color_eyre::Result<()> {
+        let manifest = Manifest::load(&self.batch.manifest)?;
+
+        let mut batch = Batch::new(&self.batch.manifest, self.batch.force)?;
+        for entry in &manifest.execution {
+            let hash = match entry.content_hash(&manifest, &self.script_args) {
+                Ok(hash) => hash,
+                Err(e) => {
+                    batch.fail("execution", &entry.output, e);
+                    continue;
+                }
+            };
+            let output = manifest.resolve(&entry.output);
+            batch
+                .run("execution", &entry.output, &output, hash, || {
+                    self.generate(&manifest, entry)
+                })
+                .await?;
+        }
+
+        let report = batch.finish();
+        println!("{report}");
+        ensure!(
+            report.failures() == 0,
+            "{} of {} execution fixtures failed",
+            report.failures(),
+            report.rows.len()
+        );
+        Ok(())
+    }
+
+    /// Runs `opt8n script` for the entry in the Forge project of the entry. Each script
+    /// runs in its own process, so that every fixture gets a fresh anvil instance.
+    async fn generate(
+        &self,
+        manifest: &Manifest,
+        entry: &ExecutionEntry,
+    ) -> color_eyre::Result<()> {
+        let status = Command::new(std::env::current_exe()?)
+            .args(entry.args(manifest)?)
+            .args(&self.script_args)
+            .current_dir(entry.dir(manifest))
+            .status()
+            .await
+            .map_err(|e| eyre!("Failed to run opt8n script: {e}"))?;
+        ensure!(status.success(), "opt8n script exited with {status}");
+        Ok(())
+    }
+}
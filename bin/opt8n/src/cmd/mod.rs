@@ -1,3 +1,4 @@
+pub mod batch;
 pub mod repl;
 pub mod script;
 pub mod server;
@@ -20,6 +20,8 @@ impl ReplArgs {
             Some(self.node_args.clone()),
             self.opt8n_args.output.clone(),
             self.opt8n_args.genesis.clone(),
+            self.opt8n_args.hardfork.clone(),
+            self.opt8n_args.l1_info.clone(),
         )
         .await?;
 
@@ -92,7 +94,7 @@ async fn receive_command() -> color_eyre::Result<ReplCommand> {
 async fn execute(opt8n: &mut Opt8n, command: ReplCommand) -> color_eyre::Result<()> {
     match command {
         ReplCommand::Dump => {
-            opt8n.mine_block().await;
+            opt8n.mine_block().await?;
         }
         ReplCommand::Anvil { mut args } => {
             args.insert(0, "anvil".to_string());
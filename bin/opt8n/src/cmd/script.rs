@@ -3,6 +3,7 @@ use clap::{Parser, ValueHint};
 use color_eyre::eyre::eyre;
 use futures::StreamExt;
 
+use crate::l1_info::DEPOSITOR;
 use crate::opt8n::{Opt8n, Opt8nArgs};
 
 #[derive(Parser, Clone, Debug)]
@@ -21,6 +22,8 @@ impl ScriptArgs {
             Some(self.node_args.clone()),
             self.opt8n_args.output.clone(),
             self.opt8n_args.genesis.clone(),
+            self.opt8n_args.hardfork.clone(),
+            self.opt8n_args.l1_info.clone(),
         )
         .await?;
 
@@ -63,7 +66,7 @@ pub async fn run_script(
     let mut opt8n = broadcast_transactions(opt8n, script_args).await?;
 
     // Mine the block and generate the execution fixture
-    opt8n.mine_block().await;
+    opt8n.mine_block().await?;
 
     let block = new_blocks.next().await.ok_or(eyre!("No new block"))?;
     if let Some(block) = opt8n.eth_api.backend.get_block_by_hash(block.hash) {
@@ -103,13 +106,17 @@ async fn broadcast_transactions(
 
     let pending_transactions = tokio::task::spawn(async move {
         loop {
+            // The L1 info deposit queued for the block is not part of the script.
             let pending_tx_count = opt8n
                 .eth_api
                 .txpool_content()
                 .await
                 .expect("Failed to get txpool content")
                 .pending
-                .len();
+                .iter()
+                .filter(|(from, _)| **from != DEPOSITOR)
+                .map(|(_, txs)| txs.len())
+                .sum::<usize>();
 
             if pending_tx_count == tx_count {
                 return opt8n;
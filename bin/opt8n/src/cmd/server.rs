@@ -31,6 +31,8 @@ impl ServerArgs {
             Some(self.node_args.clone()),
             self.opt8n_args.output.clone(),
             self.opt8n_args.genesis.clone(),
+            self.opt8n_args.hardfork.clone(),
+            self.opt8n_args.l1_info.clone(),
         )
         .await?;
 
@@ -90,7 +92,7 @@ async fn mine_block(opt8n: Arc<Mutex<Opt8n>>) -> Result<(), ServerError> {
 
     let mut new_blocks = opt8n.eth_api.backend.new_block_notifications();
 
-    opt8n.mine_block().await;
+    opt8n.mine_block().await.map_err(ServerError::Opt8nError)?;
 
     let block = new_blocks
         .next()
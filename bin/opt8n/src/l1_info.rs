@@ -0,0 +1,184 @@
+//! The L1 attributes deposit transaction that starts every OP block.
+
+use alloy_primitives::{address, keccak256, Address, Bytes, B256, U256};
+use clap::Args;
+use op_test_vectors::execution::OpHardfork;
+use serde_json::{json, Value};
+
+/// The account L1 attributes deposits are sent from.
+pub const DEPOSITOR: Address = address!("deaddeaddeaddeaddeaddeaddeaddeaddead0001");
+
+/// The L1Block predeploy, which L1 attributes deposits update.
+pub const L1_BLOCK: Address = address!("4200000000000000000000000000000000000015");
+
+/// The selector of `setL1BlockValues`, used before Ecotone.
+const SET_L1_BLOCK_VALUES: [u8; 4] = [0x01, 0x5d, 0x8e, 0xb9];
+
+/// The selector of `setL1BlockValuesEcotone`, used as of Ecotone.
+const SET_L1_BLOCK_VALUES_ECOTONE: [u8; 4] = [0x44, 0x0a, 0x5e, 0x20];
+
+/// The L1 context of the L1 attributes deposit prepended to every captured block.
+#[derive(Args, Clone, Debug)]
+pub struct L1InfoArgs {
+    #[clap(long, help = "Do not prepend an L1 info deposit to captured blocks")]
+    pub no_l1_info: bool,
+    #[clap(long, default_value_t = 1, help = "L1 origin block number")]
+    pub l1_block_number: u64,
+    #[clap(long, default_value_t = 0, help = "L1 origin block timestamp")]
+    pub l1_block_timestamp: u64,
+    #[clap(long, default_value_t = B256::ZERO, help = "L1 origin block hash")]
+    pub l1_block_hash: B256,
+    #[clap(long, default_value_t = U256::from(1_000_000_000u64), help = "L1 origin base fee")]
+    pub l1_base_fee: U256,
+    #[clap(
+        long,
+        default_value_t = U256::from(1),
+        help = "L1 origin blob base fee, as of Ecotone"
+    )]
+    pub l1_blob_base_fee: U256,
+    #[clap(long, default_value_t = B256::ZERO, help = "Batcher address, as a bytes32")]
+    pub batcher_hash: B256,
+    #[clap(
+        long,
+        default_value_t = U256::from(188),
+        help = "L1 fee overhead, before Ecotone"
+    )]
+    pub l1_fee_overhead: U256,
+    #[clap(
+        long,
+        default_value_t = U256::from(684_000),
+        help = "L1 fee scalar, before Ecotone"
+    )]
+    pub l1_fee_scalar: U256,
+    #[clap(
+        long,
+        default_value_t = 1368,
+        help = "L1 base fee scalar, as of Ecotone"
+    )]
+    pub base_fee_scalar: u32,
+    #[clap(
+        long,
+        default_value_t = 810_949,
+        help = "L1 blob base fee scalar, as of Ecotone"
+    )]
+    pub blob_base_fee_scalar: u32,
+}
+
+impl L1InfoArgs {
+    /// Returns the calldata of the L1 attributes deposit of a block under the hardfork,
+    /// whose parent block ran under `parent_fork`.
+    ///
+    /// Like op-node, the Ecotone format is only used once Ecotone was active at the
+    /// parent block. The Ecotone activation block still calls `setL1BlockValues`, since
+    /// the `L1Block` predeploy is only upgraded by the deposits of that block.
+    pub fn calldata(
+        &self,
+        fork: OpHardfork,
+        parent_fork: OpHardfork,
+        sequence_number: u64,
+    ) -> Bytes {
+        let mut data = Vec::new();
+        if fork >= OpHardfork::Ecotone && parent_fork >= OpHardfork::Ecotone {
+            data.extend(SET_L1_BLOCK_VALUES_ECOTONE);
+            data.extend(self.base_fee_scalar.to_be_bytes());
+            data.extend(self.blob_base_fee_scalar.to_be_bytes());
+            data.extend(sequence_number.to_be_bytes());
+            data.extend(self.l1_block_timestamp.to_be_bytes());
+            data.extend(self.l1_block_number.to_be_bytes());
+            data.extend(self.l1_base_fee.to_be_bytes::<32>());
+            data.extend(self.l1_blob_base_fee.to_be_bytes::<32>());
+            data.extend(self.l1_block_hash);
+            data.extend(self.batcher_hash);
+        } else {
+            data.extend(SET_L1_BLOCK_VALUES);
+            data.extend(U256::from(self.l1_block_number).to_be_bytes::<32>());
+            data.extend(U256::from(self.l1_block_timestamp).to_be_bytes::<32>());
+            data.extend(self.l1_base_fee.to_be_bytes::<32>());
+            data.extend(self.l1_block_hash);
+            data.extend(U256::from(sequence_number).to_be_bytes::<32>());
+            data.extend(self.batcher_hash);
+            data.extend(self.l1_fee_overhead.to_be_bytes::<32>());
+            data.extend(self.l1_fee_scalar.to_be_bytes::<32>());
+        }
+        data.into()
+    }
+
+    /// Returns the `eth_sendTransaction` request of the L1 attributes deposit of a block
+    /// under the hardfork, with the deposit fields anvil reads in optimism mode.
+    pub fn deposit_request(
+        &self,
+        fork: OpHardfork,
+        parent_fork: OpHardfork,
+        sequence_number: u64,
+    ) -> Value {
+        // Bedrock deposits are system transactions, with a larger gas limit.
+        let (gas, is_system_tx) = match fork {
+            OpHardfork::Bedrock => (150_000_000u64, true),
+            _ => (1_000_000, false),
+        };
+        json!({
+            "from": DEPOSITOR,
+            "to": L1_BLOCK,
+            "gas": U256::from(gas),
+            "value": U256::ZERO,
+            "input": self.calldata(fork, parent_fork, sequence_number),
+            "sourceHash": source_hash(self.l1_block_hash, sequence_number),
+            "mint": U256::ZERO,
+            "isSystemTx": is_system_tx,
+        })
+    }
+}
+
+/// Returns the source hash of the L1 attributes deposit of the L2 block with the
+/// sequence number in the epoch of the L1 block.
+pub fn source_hash(l1_block_hash: B256, sequence_number: u64) -> B256 {
+    let mut deposit_id = [0u8; 64];
+    deposit_id[..32].copy_from_slice(l1_block_hash.as_slice());
+    deposit_id[32..].copy_from_slice(&U256::from(sequence_number).to_be_bytes::<32>());
+
+    // The L1 info deposit source hash domain is 1.
+    let mut domain = [0u8; 64];
+    domain[31] = 1;
+    domain[32..].copy_from_slice(keccak256(deposit_id).as_slice());
+    keccak256(domain)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    #[derive(Parser)]
+    struct Cli {
+        #[command(flatten)]
+        l1_info: L1InfoArgs,
+    }
+
+    #[test]
+    fn test_l1_info_calldata() {
+        let args = Cli::parse_from(["opt8n", "--l1-block-number", "7"]).l1_info;
+
+        let bedrock = args.calldata(OpHardfork::Canyon, OpHardfork::Canyon, 3);
+        assert_eq!(bedrock.len(), 4 + 8 * 32);
+        assert_eq!(bedrock[..4], SET_L1_BLOCK_VALUES);
+        assert_eq!(bedrock[4 + 31], 7);
+        assert_eq!(bedrock[4 + 4 * 32 + 31], 3);
+
+        let ecotone = args.calldata(OpHardfork::Fjord, OpHardfork::Ecotone, 3);
+        assert_eq!(ecotone.len(), 164);
+        assert_eq!(ecotone[..4], SET_L1_BLOCK_VALUES_ECOTONE);
+        assert_eq!(ecotone[12..20], 3u64.to_be_bytes());
+        assert_eq!(ecotone[28..36], 7u64.to_be_bytes());
+
+        // The Ecotone activation block still uses the format from before Ecotone.
+        let activation = args.calldata(OpHardfork::Ecotone, OpHardfork::Canyon, 3);
+        assert_eq!(activation, bedrock);
+    }
+
+    #[test]
+    fn test_source_hash_depends_on_sequence_number() {
+        let hash = B256::repeat_byte(0xab);
+        assert_ne!(source_hash(hash, 0), source_hash(hash, 1));
+        assert_eq!(source_hash(hash, 1), source_hash(hash, 1));
+    }
+}
@@ -1,8 +1,10 @@
 pub mod cmd;
+pub mod l1_info;
 pub mod opt8n;
 
 use crate::cmd::script::ScriptArgs;
 use clap::Parser;
+use cmd::batch::BatchCmd;
 use cmd::repl::ReplArgs;
 use cmd::server::ServerArgs;
 use color_eyre::eyre;
@@ -19,6 +21,7 @@ pub enum Commands {
     Repl(ReplArgs),
     Script(ScriptArgs),
     Server(ServerArgs),
+    Batch(BatchCmd),
 }
 
 #[tokio::main]
@@ -30,6 +33,7 @@ async fn main() -> eyre::Result<()> {
         Commands::Repl(cmd) => cmd.run().await?,
         Commands::Script(cmd) => cmd.run().await?,
         Commands::Server(cmd) => cmd.run().await?,
+        Commands::Batch(cmd) => cmd.run().await?,
     }
 
     Ok(())
@@ -2,27 +2,37 @@
 
 use alloy_eips::eip2718::Encodable2718;
 use alloy_eips::BlockId;
+use alloy_primitives::Address;
 use alloy_rpc_types::{
-    trace::geth::{PreStateConfig, PreStateFrame},
-    TransactionReceipt,
+    trace::geth::{AccountState, PreStateConfig, PreStateFrame, PreStateMode},
+    BlockNumberOrTag, TransactionReceipt,
+};
+use anvil::{
+    cmd::NodeArgs,
+    eth::{pool::transactions::TransactionOrder, EthApi},
+    Hardfork, NodeConfig, NodeHandle,
 };
-use anvil::{cmd::NodeArgs, eth::EthApi, NodeConfig, NodeHandle};
 use anvil_core::eth::transaction::{PendingTransaction, TypedTransaction};
 use anvil_core::eth::{block::Block, transaction::TypedReceipt};
 use cast::traces::{GethTraceBuilder, TracingInspectorConfig};
-use clap::Parser;
+use clap::{Args, Parser};
 use op_alloy_consensus::{
     OpDepositReceipt, OpDepositReceiptWithBloom, OpReceiptEnvelope, OpTypedTransaction, TxDeposit,
 };
 use op_alloy_rpc_types::OpTransactionReceipt;
 use std::{
+    collections::{hash_map::Entry, HashMap},
     error::Error,
     fs::{self, File},
     path::PathBuf,
 };
 
+use crate::l1_info::L1InfoArgs;
+
 use color_eyre::eyre::{ensure, eyre, Result};
-use op_test_vectors::execution::{ExecutionEnvironment, ExecutionFixture, ExecutionResult};
+use op_test_vectors::execution::{
+    ExecutionEnvironment, ExecutionFixture, ExecutionResult, OpHardfork,
+};
 use revm::{
     db::{AlloyDB, CacheDB},
     primitives::{BlobExcessGasAndPrice, BlockEnv, CfgEnv, Env, SpecId, U256},
@@ -35,6 +45,113 @@ pub struct Opt8nArgs {
     pub output: PathBuf,
     #[clap(long, help = "Path to genesis state")]
     pub genesis: Option<PathBuf>,
+    #[command(flatten)]
+    pub hardfork: OpHardforkArgs,
+    #[command(flatten)]
+    pub l1_info: L1InfoArgs,
+}
+
+/// Selects the OP hardfork the transactions run under.
+///
+/// Anvil only knows Ethereum hardforks, so it runs the Ethereum hardfork the selected OP
+/// hardfork activates for every block. The activation timestamps must therefore not fall
+/// after the captured blocks, so that the EVM capturing the pre and post state runs the
+/// same rules as anvil.
+#[derive(Args, Clone, Debug, Default)]
+pub struct OpHardforkArgs {
+    #[clap(
+        long,
+        help = "OP hardfork to run under: bedrock, regolith, canyon, ecotone or fjord"
+    )]
+    pub op_hardfork: Option<OpHardfork>,
+    #[clap(
+        long,
+        requires = "op_hardfork",
+        help = "Regolith activation timestamp, active from genesis by default"
+    )]
+    pub regolith_time: Option<u64>,
+    #[clap(
+        long,
+        requires = "op_hardfork",
+        help = "Canyon activation timestamp, active from genesis by default"
+    )]
+    pub canyon_time: Option<u64>,
+    #[clap(
+        long,
+        requires = "op_hardfork",
+        help = "Ecotone activation timestamp, active from genesis by default"
+    )]
+    pub ecotone_time: Option<u64>,
+    #[clap(
+        long,
+        requires = "op_hardfork",
+        help = "Fjord activation timestamp, active from genesis by default"
+    )]
+    pub fjord_time: Option<u64>,
+}
+
+impl OpHardforkArgs {
+    /// Returns the OP hardfork active at the timestamp, or `None` if none was selected.
+    ///
+    /// Hardforks up to the selected one activate in order at their activation
+    /// timestamps, and later ones never activate.
+    pub fn active_at(&self, timestamp: u64) -> Option<OpHardfork> {
+        let selected = self.op_hardfork?;
+        OpHardfork::ALL
+            .into_iter()
+            .take_while(|fork| {
+                *fork <= selected && self.activation_time(*fork).map_or(true, |t| t <= timestamp)
+            })
+            .last()
+    }
+
+    /// Returns the OP hardfork the block runs under, or `None` if none was selected.
+    ///
+    /// Fails if the selected hardfork is not active yet at the block timestamp, since
+    /// anvil mined the block under it regardless.
+    pub fn block_hardfork(&self, number: u64, timestamp: u64) -> Result<Option<OpHardfork>> {
+        let Some(selected) = self.op_hardfork else {
+            return Ok(None);
+        };
+        let active = self.active_at(timestamp).unwrap_or(OpHardfork::Bedrock);
+        ensure!(
+            active == selected,
+            "Block {number} at timestamp {timestamp} is under {active}, but anvil runs \
+             {selected}: activation times must not fall after the mined blocks"
+        );
+        Ok(Some(selected))
+    }
+
+    /// Returns the activation timestamp override of the hardfork.
+    fn activation_time(&self, fork: OpHardfork) -> Option<u64> {
+        match fork {
+            OpHardfork::Bedrock => None,
+            OpHardfork::Regolith => self.regolith_time,
+            OpHardfork::Canyon => self.canyon_time,
+            OpHardfork::Ecotone => self.ecotone_time,
+            OpHardfork::Fjord => self.fjord_time,
+        }
+    }
+}
+
+/// Returns the Ethereum hardfork anvil runs for the OP hardfork.
+fn anvil_hardfork(fork: OpHardfork) -> Hardfork {
+    match fork {
+        OpHardfork::Bedrock | OpHardfork::Regolith => Hardfork::Paris,
+        OpHardfork::Canyon => Hardfork::Shanghai,
+        OpHardfork::Ecotone | OpHardfork::Fjord => Hardfork::Cancun,
+    }
+}
+
+/// Returns the revm spec of the OP hardfork.
+fn op_spec_id(fork: OpHardfork) -> SpecId {
+    match fork {
+        OpHardfork::Bedrock => SpecId::BEDROCK,
+        OpHardfork::Regolith => SpecId::REGOLITH,
+        OpHardfork::Canyon => SpecId::CANYON,
+        OpHardfork::Ecotone => SpecId::ECOTONE,
+        OpHardfork::Fjord => SpecId::FJORD,
+    }
 }
 
 pub struct Opt8n {
@@ -43,6 +160,10 @@ pub struct Opt8n {
     pub execution_fixture: ExecutionFixture,
     pub node_config: NodeConfig,
     pub output_file: PathBuf,
+    pub hardfork: OpHardforkArgs,
+    pub l1_info: L1InfoArgs,
+    /// The sequence number of the next L1 info deposit.
+    pub sequence_number: u64,
 }
 
 impl Opt8n {
@@ -50,14 +171,27 @@ impl Opt8n {
         node_args: Option<NodeArgs>,
         output_file: PathBuf,
         genesis: Option<PathBuf>,
+        hardfork: OpHardforkArgs,
+        l1_info: L1InfoArgs,
     ) -> Result<Self> {
         let node_config = if let Some(node_args) = node_args {
-            if node_args.evm_opts.fork_url.is_some()
-                || node_args.evm_opts.fork_block_number.is_some()
-            {
-                return Err(eyre!(
-                    "Forking is not supported in opt8n, please specify prestate with a genesis file"
-                ));
+            // When forking, the prestate is fetched lazily from the fork, and only the
+            // accounts and storage slots the transactions touch end up in the fixture.
+            // The fork block is required so that the fixture can be reproduced.
+            if node_args.evm_opts.fork_url.is_some() {
+                ensure!(
+                    genesis.is_none(),
+                    "A genesis file cannot be used together with --fork-url"
+                );
+                ensure!(
+                    node_args.evm_opts.fork_block_number.is_some(),
+                    "--fork-url requires --fork-block-number to pin the prestate"
+                );
+            } else {
+                ensure!(
+                    node_args.evm_opts.fork_block_number.is_none(),
+                    "--fork-block-number requires --fork-url"
+                );
             }
 
             Some(node_args.into_node_config())
@@ -71,25 +205,78 @@ impl Opt8n {
             None
         };
 
-        let node_config = node_config
+        let mut node_config = node_config
             .unwrap_or_default()
             .with_optimism(true)
             .with_no_mining(true)
             .with_genesis(genesis);
+        if let Some(fork) = hardfork.op_hardfork {
+            node_config = node_config.with_hardfork(Some(anvil_hardfork(fork)));
+        }
+        // The L1 info deposit is queued before the transactions of its block, and must be
+        // mined first.
+        if !l1_info.no_l1_info {
+            node_config = node_config.with_transaction_order(TransactionOrder::Fifo);
+        }
 
         let (eth_api, node_handle) = anvil::spawn(node_config.clone()).await;
         eth_api.anvil_set_logging(false).await?;
 
-        Ok(Self {
+        let mut opt8n = Self {
             eth_api,
             node_handle,
             execution_fixture: ExecutionFixture::default(),
             node_config,
             output_file,
-        })
+            hardfork,
+            l1_info,
+            sequence_number: 0,
+        };
+        opt8n.queue_l1_info().await?;
+        Ok(opt8n)
+    }
+
+    /// Adds the L1 info deposit of the next block to the transaction pool, so that it is
+    /// mined as the first transaction of the block, as in a real OP block.
+    ///
+    /// The deposit uses the format of the OP hardfork active at the timestamp of the
+    /// pending block and of its parent, or Ecotone if no OP hardfork is selected,
+    /// matching anvil's default Cancun rules.
+    pub async fn queue_l1_info(&mut self) -> Result<()> {
+        if self.l1_info.no_l1_info {
+            return Ok(());
+        }
+        let timestamp = self.eth_api.backend.time().current_call_timestamp();
+        let parent_timestamp = self
+            .eth_api
+            .block_by_number(BlockNumberOrTag::Latest)
+            .await?
+            .ok_or_else(|| eyre!("Failed to get the latest block"))?
+            .header
+            .timestamp;
+        let active_at = |timestamp| {
+            self.hardfork
+                .active_at(timestamp)
+                .unwrap_or(OpHardfork::Ecotone)
+        };
+        let request = self.l1_info.deposit_request(
+            active_at(timestamp),
+            active_at(parent_timestamp),
+            self.sequence_number,
+        );
+        self.eth_api
+            .send_transaction(serde_json::from_value(request)?)
+            .await?;
+        self.sequence_number += 1;
+        Ok(())
     }
 
     /// Updates the pre and post state allocations of the [ExecutionFixture] from Revm.
+    ///
+    /// The pre state holds every account and storage slot the transactions touch, as it
+    /// was before the first transaction touching it, so that the fixture does not depend
+    /// on the rest of the state, such as the chain it was forked from. The post state
+    /// holds the same accounts and slots after the block.
     pub fn capture_pre_post_alloc(&mut self, block: &Block) -> Result<()> {
         let revm_db = CacheDB::new(
             AlloyDB::new(
@@ -99,11 +286,18 @@ impl Opt8n {
             .ok_or_else(|| eyre!("Failed to create AlloyDB"))?,
         );
 
+        let hardfork = self
+            .hardfork
+            .block_hardfork(block.header.number, block.header.timestamp)?;
+        let spec_id = match hardfork {
+            Some(fork) => op_spec_id(fork),
+            None => SpecId::from(self.node_config.hardfork.unwrap_or_default()),
+        };
         let mut evm = evm(
             block,
             self.eth_api.chain_id(),
             CacheDB::new(revm_db),
-            SpecId::from(self.node_config.hardfork.unwrap_or_default()),
+            spec_id,
         );
 
         for tx in block.transactions.iter() {
@@ -118,30 +312,48 @@ impl Opt8n {
             let result = evm.transact()?;
 
             let db = &mut evm.context.evm.db;
-            let pre_state_frame = GethTraceBuilder::new(vec![], TracingInspectorConfig::default())
-                .geth_prestate_traces(
-                    &result,
-                    PreStateConfig {
-                        diff_mode: Some(true),
-                    },
-                    &db,
-                )?;
+            let prestate = |diff_mode| {
+                GethTraceBuilder::new(vec![], TracingInspectorConfig::default())
+                    .geth_prestate_traces(
+                        &result,
+                        PreStateConfig {
+                            diff_mode: Some(diff_mode),
+                        },
+                        &db,
+                    )
+            };
+            let (touched, diff) = (prestate(false)?, prestate(true)?);
             db.commit(result.state);
 
-            if let PreStateFrame::Diff(diff) = pre_state_frame {
-                diff.pre.into_iter().for_each(|(account, state)| {
-                    self.execution_fixture.alloc.entry(account).or_insert(state);
-                });
-                diff.post.into_iter().for_each(|(account, state)| {
-                    self.execution_fixture.out_alloc.insert(account, state);
-                });
+            // Accounts and slots touched for the first time are unchanged by the earlier
+            // transactions, so their state before this transaction is their pre state.
+            if let PreStateFrame::Default(PreStateMode(touched)) = touched {
+                for (account, state) in touched {
+                    merge_untouched(&mut self.execution_fixture.alloc, account, state.clone());
+                    merge_untouched(&mut self.execution_fixture.out_alloc, account, state);
+                }
+            }
+            if let PreStateFrame::Diff(diff) = diff {
+                for account in diff.pre.keys().filter(|a| !diff.post.contains_key(a)) {
+                    self.execution_fixture.out_alloc.remove(account);
+                }
+                for (account, state) in diff.post {
+                    let post = self.execution_fixture.out_alloc.entry(account).or_default();
+                    post.balance = state.balance.or(post.balance);
+                    post.nonce = state.nonce.or(post.nonce);
+                    post.code = state.code.or(post.code.take());
+                    post.storage.extend(state.storage);
+                }
             }
         }
         Ok(())
     }
 
-    pub async fn mine_block(&mut self) {
+    /// Mines the pending transactions into a block, and queues the L1 info deposit of
+    /// the next block.
+    pub async fn mine_block(&mut self) -> Result<()> {
         self.eth_api.mine_one().await;
+        self.queue_l1_info().await
     }
 
     /// Generates an execution fixture from a block.
@@ -182,6 +394,9 @@ impl Opt8n {
             current_number: U256::from(block.header.number),
             current_timestamp: U256::from(block_header.timestamp),
             block_hashes: None,
+            hardfork: self
+                .hardfork
+                .block_hardfork(block_header.number, block_header.timestamp)?,
         };
 
         self.execution_fixture.env = execution_environment;
@@ -201,6 +416,29 @@ impl Opt8n {
     }
 }
 
+/// Adds the account state to the allocation, keeping the state the allocation already
+/// holds for the account, and adding only the storage slots it does not hold yet.
+///
+/// Prestate traces omit a zero nonce and empty code, so the fields of an account that
+/// is already allocated are never filled in from a later state.
+fn merge_untouched(
+    alloc: &mut HashMap<Address, AccountState>,
+    account: Address,
+    state: AccountState,
+) {
+    match alloc.entry(account) {
+        Entry::Vacant(entry) => {
+            entry.insert(state);
+        }
+        Entry::Occupied(mut entry) => {
+            let entry = entry.get_mut();
+            for (slot, value) in state.storage {
+                entry.storage.entry(slot).or_insert(value);
+            }
+        }
+    }
+}
+
 // TODO: Consider adding `From` implementation for
 // `TypedTransaction` -> `OpTypedTransaction` in `op-alloy-consensus`
 fn typed_tx_to_op_typed_tx(tx: &TypedTransaction) -> OpTypedTransaction {
@@ -328,13 +566,214 @@ where
 
 #[cfg(test)]
 mod tests {
-    #[tokio::test]
+    use super::*;
+    use crate::l1_info::{DEPOSITOR, L1_BLOCK};
+    use alloy_primitives::{address, bytes, Bytes, TxKind, B256};
+    use futures::StreamExt;
+    use serde_json::json;
+
+    #[derive(Parser)]
+    struct Cli {
+        #[command(flatten)]
+        hardfork: OpHardforkArgs,
+        #[command(flatten)]
+        l1_info: L1InfoArgs,
+    }
+
+    /// Spawns an opt8n node with the given opt8n arguments.
+    async fn opt8n(args: &[&str]) -> Opt8n {
+        let cli = Cli::parse_from(std::iter::once("opt8n").chain(args.iter().copied()));
+        Opt8n::new(
+            None,
+            std::env::temp_dir().join("opt8n-test.json"),
+            None,
+            cli.hardfork,
+            cli.l1_info,
+        )
+        .await
+        .unwrap()
+    }
+
+    /// Sends a transaction from an anvil dev account to the pool.
+    async fn send(opt8n: &Opt8n, request: serde_json::Value) {
+        opt8n
+            .eth_api
+            .send_transaction(serde_json::from_value(request).unwrap())
+            .await
+            .unwrap();
+    }
+
+    /// Mines the pending transactions, and returns the mined block.
+    async fn mine(opt8n: &mut Opt8n) -> Block {
+        let mut new_blocks = opt8n.eth_api.backend.new_block_notifications();
+        opt8n.mine_block().await.unwrap();
+        let block = new_blocks.next().await.unwrap();
+        opt8n.eth_api.backend.get_block_by_hash(block.hash).unwrap()
+    }
+
+    fn account(balance: u64, nonce: Option<u64>, storage: &[(u8, u8)]) -> AccountState {
+        AccountState {
+            balance: Some(U256::from(balance)),
+            code: None,
+            nonce,
+            storage: storage
+                .iter()
+                .map(|(slot, value)| (B256::with_last_byte(*slot), B256::with_last_byte(*value)))
+                .collect(),
+        }
+    }
+
+    fn hardfork(args: &[&str]) -> OpHardforkArgs {
+        Cli::parse_from(std::iter::once("opt8n").chain(args.iter().copied())).hardfork
+    }
+
+    #[test]
+    fn test_active_at() {
+        assert_eq!(hardfork(&[]).active_at(0), None);
+
+        let args = hardfork(&["--op-hardfork", "ecotone"]);
+        assert_eq!(args.active_at(0), Some(OpHardfork::Ecotone));
+
+        let args = hardfork(&[
+            "--op-hardfork",
+            "ecotone",
+            "--canyon-time",
+            "100",
+            "--ecotone-time",
+            "200",
+            "--fjord-time",
+            "300",
+        ]);
+        assert_eq!(args.active_at(99), Some(OpHardfork::Regolith));
+        assert_eq!(args.active_at(100), Some(OpHardfork::Canyon));
+        assert_eq!(args.active_at(199), Some(OpHardfork::Canyon));
+        assert_eq!(args.active_at(200), Some(OpHardfork::Ecotone));
+        // Hardforks after the selected one never activate.
+        assert_eq!(args.active_at(300), Some(OpHardfork::Ecotone));
+
+        // Hardforks activate in order, so a later hardfork waits for the earlier ones.
+        let args = hardfork(&[
+            "--op-hardfork",
+            "canyon",
+            "--regolith-time",
+            "200",
+            "--canyon-time",
+            "100",
+        ]);
+        assert_eq!(args.active_at(150), Some(OpHardfork::Bedrock));
+        assert_eq!(args.active_at(200), Some(OpHardfork::Canyon));
+    }
+
+    #[test]
+    fn test_block_hardfork() {
+        assert_eq!(hardfork(&[]).block_hardfork(1, 0).unwrap(), None);
+
+        let args = hardfork(&["--op-hardfork", "fjord", "--fjord-time", "100"]);
+        assert_eq!(
+            args.block_hardfork(1, 100).unwrap(),
+            Some(OpHardfork::Fjord)
+        );
+        let err = args.block_hardfork(1, 99).unwrap_err();
+        assert!(err.to_string().contains("under ecotone"), "{err}");
+    }
+
+    #[test]
+    fn test_merge_untouched() {
+        let (a, b) = (
+            address!("000000000000000000000000000000000000000a"),
+            address!("000000000000000000000000000000000000000b"),
+        );
+        let mut alloc = HashMap::new();
+        merge_untouched(&mut alloc, a, account(0, None, &[(1, 1)]));
+        // A later state of the account only adds the storage slots not held yet.
+        merge_untouched(&mut alloc, a, account(5, Some(1), &[(1, 2), (2, 2)]));
+        merge_untouched(&mut alloc, b, account(7, Some(3), &[]));
+
+        assert_eq!(alloc[&a], account(0, None, &[(1, 1), (2, 2)]));
+        assert_eq!(alloc[&b], account(7, Some(3), &[]));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
     pub async fn test_update_alloc() {
-        // TODO:
+        let mut opt8n = opt8n(&["--no-l1-info", "--op-hardfork", "canyon"]).await;
+        let accounts = opt8n.eth_api.accounts().unwrap();
+        let (deployer, sender) = (accounts[0], accounts[1]);
+        let recipient = address!("00000000000000000000000000000000000b0b00");
+
+        // A contract that selfdestructs to its caller when called, deployed in block 1
+        // with a balance of 7 wei. Its init code returns the runtime code `CALLER
+        // SELFDESTRUCT`.
+        let contract = deployer.create(0);
+        let runtime: Bytes = bytes!("33ff");
+        send(
+            &opt8n,
+            json!({
+                "from": deployer,
+                "value": U256::from(7),
+                "input": bytes!("6133ff60005260026000601ef3"),
+            }),
+        )
+        .await;
+        mine(&mut opt8n).await;
+
+        // Block 2 touches the recipient and sender twice, and destroys the contract.
+        for value in [1u64, 2] {
+            send(
+                &opt8n,
+                json!({ "from": sender, "to": recipient, "value": U256::from(value) }),
+            )
+            .await;
+        }
+        send(&opt8n, json!({ "from": deployer, "to": contract })).await;
+        let block = mine(&mut opt8n).await;
+        assert_eq!(block.transactions.len(), 3);
+        opt8n.capture_pre_post_alloc(&block).unwrap();
+
+        // The pre state is the state before the first transaction touching an account.
+        let alloc = &opt8n.execution_fixture.alloc;
+        assert_eq!(alloc[&recipient], account(0, None, &[]));
+        assert_eq!(alloc[&sender].nonce, None);
+        assert_eq!(alloc[&contract].code, Some(runtime));
+        assert_eq!(alloc[&contract].balance, Some(U256::from(7)));
+        assert_eq!(alloc[&deployer].nonce, Some(1));
+
+        // Destroyed accounts are not in the post state.
+        let out_alloc = &opt8n.execution_fixture.out_alloc;
+        assert_eq!(out_alloc[&recipient].balance, Some(U256::from(3)));
+        assert_eq!(out_alloc[&sender].nonce, Some(2));
+        assert_eq!(out_alloc[&deployer].nonce, Some(2));
+        assert!(!out_alloc.contains_key(&contract));
     }
 
-    #[tokio::test]
+    #[tokio::test(flavor = "multi_thread")]
     pub async fn test_dump_execution_fixture() {
-        // TODO:
+        let mut opt8n = opt8n(&["--op-hardfork", "ecotone"]).await;
+        let sender = opt8n.eth_api.accounts().unwrap()[0];
+        let recipient = address!("00000000000000000000000000000000000b0b00");
+        send(
+            &opt8n,
+            json!({ "from": sender, "to": recipient, "value": U256::from(1) }),
+        )
+        .await;
+        let block = mine(&mut opt8n).await;
+        opt8n.generate_execution_fixture(block).await.unwrap();
+
+        // The block starts with the L1 info deposit, ahead of the queued transaction.
+        let fixture = &opt8n.execution_fixture;
+        assert_eq!(fixture.transactions.len(), 2);
+        let OpTypedTransaction::Deposit(deposit) = &fixture.transactions[0] else {
+            panic!("First transaction is not a deposit");
+        };
+        assert_eq!(deposit.from, DEPOSITOR);
+        assert_eq!(deposit.to, TxKind::Call(L1_BLOCK));
+        assert_eq!(fixture.result.receipts.len(), 2);
+        assert_eq!(fixture.env.hardfork, Some(OpHardfork::Ecotone));
+
+        for alloc in [&fixture.alloc, &fixture.out_alloc] {
+            assert!(alloc.contains_key(&DEPOSITOR));
+            assert!(alloc.contains_key(&L1_BLOCK));
+            assert!(alloc.contains_key(&recipient));
+        }
+        assert!(opt8n.output_file.is_file());
     }
 }
@@ -1,16 +1,50 @@
 //! CLI for the range-finder.
 
+use crate::index::Index;
+use crate::report::{self, BlockRecord, OutputFormat};
+use crate::server;
+use crate::stats::{DataUsage, Stats};
 use clap::{ArgAction, Parser};
-use color_eyre::eyre::{eyre, Result};
-use kona_derive::{errors::StageError, online::*};
-use kona_primitives::L2BlockInfo;
+use color_eyre::eyre::{bail, ensure, eyre, Result};
+use derivation_driver::{
+    assemble_channels,
+    online::{start_cursor, OnlineProviders},
+    posted_frames, Channel, DerivationSession, RecordingDataSource, RollupConfigArgs, StallArgs,
+};
+use kona_derive::{sources::EthereumDataSource, traits::ChainProvider};
+use kona_primitives::RollupConfig;
 use reqwest::Url;
-use std::sync::Arc;
-use superchain_registry::ROLLUP_CONFIGS;
-use tracing::Level;
-use tracing::{debug, error, trace, warn};
+use rpc_cache::RpcCacheArgs;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::{debug, info, warn, Level};
 
-const LOG_TARGET: &str = "range_finder";
+/// The logging target to use for [tracing].
+const TARGET: &str = "range-finder";
+
+/// Where derivation stops.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Until {
+    /// Stop after the given L2 block.
+    L2Block(u64),
+    /// Stop before the first L2 block with an L1 origin past the given L1 block.
+    L1Origin(u64),
+}
+
+/// The L2 blocks derived from a range, with the batcher data they were derived from.
+#[derive(Debug, Clone)]
+pub struct Derivation {
+    /// How every L2 block was derived.
+    pub records: Vec<BlockRecord>,
+    /// The channels read by the pipeline.
+    pub channels: Vec<Channel>,
+    /// How the batcher data read by the pipeline was posted.
+    pub data: DataUsage,
+}
 
 /// Range Finder Cli
 ///
@@ -23,20 +57,128 @@ pub struct Cli {
     #[arg(long, short, help = "Verbosity level (0-4)", action = ArgAction::Count)]
     pub v: u8,
     /// Starting L2 Block
-    #[arg(long, short, help = "Starting L2 Block")]
-    pub start_block: u64,
+    #[arg(
+        long,
+        short,
+        required_unless_present_any = ["l1_block", "follow", "serve"],
+        conflicts_with = "l1_block",
+        help = "Starting L2 Block"
+    )]
+    pub start_block: Option<u64>,
     /// Ending L2 Block
-    #[arg(long, short, help = "Ending L2 Block")]
-    pub end_block: u64,
+    #[arg(
+        long,
+        short,
+        required_unless_present_any = ["l1_block", "follow", "serve"],
+        conflicts_with_all = ["l1_block", "follow"],
+        help = "Ending L2 Block"
+    )]
+    pub end_block: Option<u64>,
+    /// An L1 block to find the L2 blocks derived from, instead of an L2 range.
+    #[arg(
+        long,
+        conflicts_with = "follow",
+        help = "L1 block to find the L2 blocks that used it as their origin or were derived from its data"
+    )]
+    pub l1_block: Option<u64>,
+    /// The last L1 block of a reverse lookup, to look up an L1 range.
+    #[arg(
+        long,
+        requires = "l1_block",
+        help = "Ending L1 block, to look up an L1 range instead of a single L1 block"
+    )]
+    pub l1_end_block: Option<u64>,
+    /// How many L1 blocks before the L1 range a reverse lookup starts deriving from.
+    #[arg(
+        long,
+        default_value_t = 300,
+        help = "Number of L1 blocks before the L1 range to start deriving from in a reverse lookup"
+    )]
+    pub lookback: u64,
+    /// An index of derived L2 blocks to answer queries from, and to keep up to date in
+    /// follow mode.
+    #[arg(
+        long,
+        help = "SQLite index of derived L2 blocks to answer queries from"
+    )]
+    pub index: Option<PathBuf>,
+    /// Derives continuously up to the L2 safe head, adding every block to the index.
+    #[arg(
+        long,
+        requires = "index",
+        help = "Follow the L2 safe head, adding every derived block to the index"
+    )]
+    pub follow: bool,
+    /// The address to serve the index over HTTP on.
+    #[arg(
+        long,
+        requires = "index",
+        help = "Address to serve the index over HTTP on, e.g. 127.0.0.1:8547"
+    )]
+    pub serve: Option<SocketAddr>,
+    /// How many L2 blocks follow mode derives before adding them to the index.
+    #[arg(
+        long,
+        default_value_t = 100,
+        help = "Number of L2 blocks to derive at a time in follow mode"
+    )]
+    pub chunk_size: u64,
+    /// How long follow mode waits for the safe head to advance, in seconds.
+    #[arg(
+        long,
+        default_value_t = 12,
+        help = "Seconds to wait for the L2 safe head to advance in follow mode"
+    )]
+    pub poll_interval: u64,
+    /// Reports batch submission statistics over the L2 range instead of its L1 range.
+    #[arg(
+        long,
+        conflicts_with_all = ["l1_block", "follow", "serve"],
+        help = "Report batch submission statistics over the L2 range"
+    )]
+    pub stats: bool,
+    /// The percentage of the sequencing window above which the inclusion latency of a
+    /// block counts towards a window gap.
+    #[arg(
+        long,
+        default_value_t = 80,
+        value_parser = clap::value_parser!(u64).range(0..=100),
+        help = "Percentage of the sequencing window above which blocks are reported as window gaps"
+    )]
+    pub window_threshold: u64,
     /// The L1 PRC url  for fetching L1 block info.
     #[arg(long, short, help = "The L1 PRC url for fetching L1 block info.")]
     pub l1_rpc_url: String,
     /// An L2 RPC URL to fetch L2 block data from.
     #[clap(long, help = "RPC url to fetch L2 block data from")]
     pub l2_rpc_url: String,
-    /// A beacon url for fetching blob information.
-    #[arg(long, short, help = "A beacon url for fetching blob information.")]
-    pub beacon_url: String,
+    /// Beacon urls for fetching blob information. The healthiest one is used first.
+    #[arg(
+        long,
+        short,
+        required = true,
+        value_delimiter = ',',
+        help = "Beacon urls for fetching blob information, repeated or comma separated."
+    )]
+    pub beacon_url: Vec<String>,
+    /// A blob archiver for fetching blobs past the beacon retention window.
+    #[arg(
+        long,
+        help = "A blob archiver url for fetching blobs past the beacon retention window."
+    )]
+    pub blob_archiver_url: Option<String>,
+    /// Selects where the rollup config is loaded from.
+    #[command(flatten)]
+    pub rollup: RollupConfigArgs,
+    /// Bounds how long derivation waits for progress before aborting.
+    #[command(flatten)]
+    pub stall: StallArgs,
+    /// Records or replays the RPC and beacon responses.
+    #[command(flatten)]
+    pub rpc_cache: RpcCacheArgs,
+    /// The output format.
+    #[arg(long, value_enum, default_value_t, help = "Output format")]
+    pub format: OutputFormat,
 }
 
 impl Cli {
@@ -51,185 +193,355 @@ impl Cli {
                 3 => Level::DEBUG,
                 _ => Level::TRACE,
             })
+            .with_writer(std::io::stderr)
             .finish();
         tracing::subscriber::set_global_default(subscriber).map_err(|e| eyre!(e))?;
         Ok(self)
     }
 
-    /// Parse the CLI arguments and run the command
+    /// Parse the CLI arguments and run the command.
+    /// Endpoints are routed through the RPC cache if one is configured.
     pub async fn run(&self) -> Result<()> {
-        // Build the pipeline
-        let cfg = Arc::new(self.rollup_config().await?);
-        let mut l1_provider = self.l1_provider()?;
-        let mut l2_provider = self.l2_provider(cfg.clone())?;
-        let attributes = self.attributes(cfg.clone(), &l2_provider, &l1_provider);
-        let blob_provider = self.blob_provider();
-        let dap = self.dap(l1_provider.clone(), blob_provider, &cfg);
-        let mut l2_cursor = self.cursor().await?;
-        let l1_tip = l1_provider
-            .block_info_by_number(l2_cursor.l1_origin.number)
-            .await
-            .expect("Failed to fetch genesis L1 block info for pipeline tip");
-        let mut pipeline = new_online_pipeline(
-            cfg,
-            l1_provider,
-            dap,
-            l2_provider.clone(),
-            attributes,
-            l1_tip,
-        );
+        self.with_rpc_cache().await?.find().await
+    }
 
-        // Run the pipeline
-        loop {
-            // If the cursor is beyond the end block, break the loop.
-            if l2_cursor.block_info.number >= self.end_block {
-                trace!(target: "loop", "Cursor is beyond the end block, breaking loop");
-                break;
+    /// Returns a copy of the command with its endpoints routed through the RPC cache.
+    pub async fn with_rpc_cache(&self) -> Result<Self> {
+        let mut cmd = self.clone();
+        cmd.l1_rpc_url = self.rpc_cache.route("l1", &self.l1_rpc_url).await?;
+        cmd.l2_rpc_url = self.rpc_cache.route("l2", &self.l2_rpc_url).await?;
+        cmd.beacon_url = self.rpc_cache.route_all("beacon", &self.beacon_url).await?;
+        if let Some(url) = &self.blob_archiver_url {
+            cmd.blob_archiver_url = Some(self.rpc_cache.route("blob-archiver", url).await?);
+        }
+        if let Some(url) = &self.rollup.rollup_rpc_url {
+            cmd.rollup.rollup_rpc_url = Some(self.rpc_cache.route("rollup", url).await?);
+        }
+        cmd.rpc_cache = RpcCacheArgs::default();
+        Ok(cmd)
+    }
+
+    /// Derives the L1 range of every L2 block in the range, or the L2 blocks related to
+    /// the L1 range of a reverse lookup, and writes the records in the configured format.
+    ///
+    /// With an index, the query is answered from the index if it covers it, and follow
+    /// mode and the HTTP endpoint run until they fail.
+    pub async fn find(&self) -> Result<()> {
+        let cfg = Arc::new(self.rollup_config().await?);
+        let index = match &self.index {
+            Some(path) => Some(Arc::new(Mutex::new(Index::open(path, cfg.l2_chain_id)?))),
+            None => None,
+        };
+        if let Some(index) = &index {
+            if self.follow || self.serve.is_some() {
+                return self.follow_and_serve(cfg, index.clone()).await;
             }
+            if let Some(records) = self.query_index(index)? {
+                return report::write(self.format, &records);
+            }
+            info!(target: TARGET, "The index does not cover the query, deriving it instead");
+        }
 
-            // Step on the pipeline.
-            match pipeline.step(l2_cursor).await {
-                StepResult::PreparedAttributes => trace!(target: "loop", "Prepared attributes"),
-                StepResult::AdvancedOrigin => trace!(target: "loop", "Advanced origin"),
-                StepResult::OriginAdvanceErr(e) => {
-                    warn!(target: "loop", "Could not advance origin: {:?}", e)
+        let records = match self.l1_block {
+            Some(l1_block) => {
+                let l1_end_block = self.l1_end_block.unwrap_or(l1_block);
+                self.reverse(cfg, l1_block, l1_end_block).await?
+            }
+            None => {
+                let (start_block, end_block) = self
+                    .start_block
+                    .zip(self.end_block)
+                    .ok_or_else(|| eyre!("Either an L2 block range or an L1 block is required"))?;
+                let derivation = self
+                    .derive(cfg.clone(), start_block, Until::L2Block(end_block))
+                    .await?;
+                if self.stats {
+                    return self.write_stats(&cfg, derivation);
                 }
-                StepResult::StepFailed(e) => match e {
-                    StageError::NotEnoughData => {
-                        debug!(target: "loop", "Not enough data to step derivation pipeline");
-                    }
-                    _ => {
-                        error!(target: "loop", "Error stepping derivation pipeline: {:?}", e);
-                    }
-                },
+                derivation.records
             }
+        };
+        report::write(self.format, &records)
+    }
 
-            // Get the attributes if there are some available.
-            let Some(attributes) = pipeline.next() else {
-                continue;
-            };
+    /// Writes the batch submission statistics of the derivation, as a summary table or
+    /// as JSON.
+    fn write_stats(&self, cfg: &RollupConfig, derivation: Derivation) -> Result<()> {
+        let stats = Stats::new(
+            cfg,
+            &derivation.records,
+            &derivation.channels,
+            derivation.data,
+            self.window_threshold,
+        );
+        match self.format {
+            OutputFormat::Text => print!("{}", stats.table()),
+            OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&stats)?),
+            format => bail!("Statistics cannot be written as {format:?}, use text or json"),
+        }
+        Ok(())
+    }
 
-            // Print the L1 range for this L2 Block.
-            let derived = attributes.parent.block_info.number as i64 + 1;
-            let l2_block_info = l2_provider
-                .l2_block_info_by_number(derived as u64)
-                .await
-                .map_err(|e| eyre!(e))?;
-            let origin = pipeline
-                .origin()
-                .ok_or(eyre!("Failed to get pipeline l1 origin"))?;
-            println!(
-                "L2 Block [{}] L1 Range: [{}, {}]",
-                derived, l2_block_info.l1_origin.number, origin.number
-            );
+    /// Answers the query from the index, or returns `None` if the index does not cover it.
+    fn query_index(&self, index: &Mutex<Index>) -> Result<Option<Vec<BlockRecord>>> {
+        // Statistics need the channels read, which the index does not hold.
+        if self.stats {
+            return Ok(None);
+        }
+        let index = index.lock().map_err(|_| eyre!("Index lock poisoned"))?;
+        match (self.l1_block, self.start_block, self.end_block) {
+            (Some(l1_block), _, _) => {
+                index.l1_range(l1_block..=self.l1_end_block.unwrap_or(l1_block))
+            }
+            (None, Some(start_block), Some(end_block)) => index.l2_range(start_block..=end_block),
+            _ => Ok(None),
+        }
+    }
 
-            // Keep trying to advance the cursor in case the fetch fails.
-            loop {
-                match l2_provider
-                    .l2_block_info_by_number(l2_cursor.block_info.number + 1)
-                    .await
-                {
-                    Ok(bi) => {
-                        l2_cursor = bi;
-                        break;
-                    }
-                    Err(e) => {
-                        error!(target: LOG_TARGET, "Failed to fetch next pending l2 safe head: {}, err: {:?}", l2_cursor.block_info.number + 1, e);
-                        // Don't step on the pipeline if we failed to fetch the next l2 safe head.
-                        continue;
-                    }
+    /// Runs follow mode and the HTTP endpoint, whichever are enabled, until one fails.
+    async fn follow_and_serve(
+        &self,
+        cfg: Arc<RollupConfig>,
+        index: Arc<Mutex<Index>>,
+    ) -> Result<()> {
+        match (self.follow, self.serve) {
+            (true, Some(addr)) => {
+                tokio::select! {
+                    result = self.follow(cfg, index.clone()) => result,
+                    result = server::serve(addr, index) => result,
                 }
             }
+            (true, None) => self.follow(cfg, index).await,
+            (false, Some(addr)) => server::serve(addr, index).await,
+            (false, None) => Ok(()),
         }
-
-        Ok(())
     }
 
-    /// Gets the L2 starting block number.
-    /// Returns the genesis L2 block number if the start block is less than the genesis block number.
-    pub fn start_block(&self, cfg: &RollupConfig) -> u64 {
-        if self.start_block < cfg.genesis.l2.number {
-            cfg.genesis.l2.number
-        } else if self.start_block != 0 {
-            self.start_block - 1
-        } else {
-            self.start_block
+    /// Derives the L2 chain up to its safe head in chunks, adding every chunk to the
+    /// index once it is derived, and then keeps up with the safe head.
+    ///
+    /// Follow mode resumes from the tip of the index, so that the indexed blocks stay
+    /// contiguous. A new index starts at the start block.
+    pub async fn follow(&self, cfg: Arc<RollupConfig>, index: Arc<Mutex<Index>>) -> Result<()> {
+        ensure!(self.chunk_size > 0, "Chunk size must be at least 1");
+        let client = reqwest::Client::new();
+        loop {
+            let tip = index
+                .lock()
+                .map_err(|_| eyre!("Index lock poisoned"))?
+                .tip()?;
+            let start_block = match (tip, self.start_block) {
+                (Some(tip), start_block) => {
+                    if start_block.is_some_and(|b| b != tip.l2_block + 1) {
+                        warn!(target: TARGET, "Resuming from the index tip {} instead of the start block", tip.l2_block);
+                    }
+                    tip.l2_block + 1
+                }
+                (None, Some(start_block)) => start_block,
+                (None, None) => {
+                    return Err(eyre!("A start block is required to build a new index"))
+                }
+            };
+            let safe_head = self.safe_head(&client).await?;
+            if start_block > safe_head {
+                tokio::time::sleep(Duration::from_secs(self.poll_interval)).await;
+                continue;
+            }
+
+            let end_block = safe_head.min(start_block + self.chunk_size - 1);
+            let records = self
+                .derive(cfg.clone(), start_block, Until::L2Block(end_block))
+                .await?
+                .records;
+            index
+                .lock()
+                .map_err(|_| eyre!("Index lock poisoned"))?
+                .insert(&records)?;
+            info!(target: TARGET, "Indexed L2 blocks [{}, {}]", start_block, end_block);
         }
     }
 
-    /// Returns an [L2BlockInfo] cursor for the pipeline.
-    pub async fn cursor(&self) -> Result<L2BlockInfo> {
-        let cfg = self.rollup_config().await?;
-        let start_block = self.start_block(&cfg);
-        let mut l2_provider = self.l2_provider(Arc::new(cfg))?;
-        let cursor = l2_provider
-            .l2_block_info_by_number(start_block)
+    /// Returns the number of the L2 safe head.
+    async fn safe_head(&self, client: &reqwest::Client) -> Result<u64> {
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "eth_getBlockByNumber",
+            "params": ["safe", false],
+        });
+        let response: Value = client
+            .post(self.l2_rpc_url()?)
+            .json(&request)
+            .send()
             .await
-            .map_err(|_| eyre!("Failed to fetch genesis L2 block info for pipeline cursor"))?;
-        Ok(cursor)
+            .map_err(|e| eyre!(e))?
+            .json()
+            .await
+            .map_err(|e| eyre!(e))?;
+        let number = response["result"]["number"]
+            .as_str()
+            .ok_or_else(|| eyre!("Invalid eth_getBlockByNumber response: {response}"))?;
+        u64::from_str_radix(number.trim_start_matches("0x"), 16).map_err(|e| eyre!(e))
     }
 
-    /// Returns a new [AlloyChainProvider] using the l1 rpc url.
-    pub fn l1_provider(&self) -> Result<AlloyChainProvider> {
-        Ok(AlloyChainProvider::new_http(self.l1_rpc_url()?))
-    }
+    /// Finds the L2 blocks that used a block in the L1 range as their origin, or were
+    /// derived from batcher data posted in it.
+    ///
+    /// Derivation starts from the L2 block at the timestamp of the L1 block `lookback`
+    /// blocks before the range, and stops at the first L2 block with an origin past it.
+    pub async fn reverse(
+        &self,
+        cfg: Arc<RollupConfig>,
+        l1_block: u64,
+        l1_end_block: u64,
+    ) -> Result<Vec<BlockRecord>> {
+        ensure!(
+            l1_end_block >= l1_block,
+            "End block must not come before the start block"
+        );
+        let start = self
+            .providers(cfg.clone())?
+            .l1
+            .block_info_by_number(l1_block.saturating_sub(self.lookback))
+            .await
+            .map_err(|e| eyre!(e))?;
+        let start_block = cfg.genesis.l2.number
+            + start.timestamp.saturating_sub(cfg.genesis.l2_time) / cfg.block_time.max(1);
+        debug!(target: TARGET, "Deriving from L2 block {} for L1 range [{}, {}]", start_block, l1_block, l1_end_block);
 
-    /// Returns a new [AlloyL2ChainProvider] using the l2 rpc url.
-    pub fn l2_provider(&self, cfg: Arc<RollupConfig>) -> Result<AlloyL2ChainProvider> {
-        Ok(AlloyL2ChainProvider::new_http(self.l2_rpc_url()?, cfg))
+        let range = l1_block..=l1_end_block;
+        let mut records = self
+            .derive(cfg, start_block, Until::L1Origin(l1_end_block))
+            .await?
+            .records;
+        if records.first().is_some_and(|r| r.l1_origin >= l1_block) {
+            warn!(
+                target: TARGET,
+                "Derivation started at an L1 origin inside the range, increase --lookback to find earlier L2 blocks"
+            );
+        }
+        for record in &mut records {
+            record.l1_relation = record.relation_to(&range);
+        }
+        records.retain(|r| r.l1_relation.is_some());
+        Ok(records)
     }
 
-    /// Returns a new [StatefulAttributesBuilder] using the l1 and l2 providers.
-    pub fn attributes(
+    /// Derives L2 blocks from the start block until the stop condition, and returns how
+    /// each was derived.
+    ///
+    /// The batcher data the pipeline reads is recorded, and decoded into channels once
+    /// derivation is done, to find the channel and batcher transactions of every block.
+    pub async fn derive(
         &self,
         cfg: Arc<RollupConfig>,
-        l2_provider: &AlloyL2ChainProvider,
-        l1_provider: &AlloyChainProvider,
-    ) -> StatefulAttributesBuilder<AlloyChainProvider, AlloyL2ChainProvider> {
-        StatefulAttributesBuilder::new(cfg, l2_provider.clone(), l1_provider.clone())
-    }
+        start_block: u64,
+        until: Until,
+    ) -> Result<Derivation> {
+        // Build the derivation session
+        let mut providers = self.providers(cfg.clone())?;
+        let l2_cursor = start_cursor(&mut providers.l2, &cfg, start_block).await?;
+        let dap = RecordingDataSource::new(EthereumDataSource::new(
+            providers.l1.clone(),
+            providers.blobs,
+            &cfg,
+        ));
+        let mut session = DerivationSession::with_data_source(
+            cfg.clone(),
+            providers.l1.clone(),
+            providers.l2,
+            dap.clone(),
+            l2_cursor,
+        )
+        .await?
+        .with_stall_args(&self.stall);
+        if let Until::L2Block(end_block) = until {
+            session = session.with_end_block(end_block);
+        }
 
-    /// Returns a new [OnlineBlobProviderWithFallback] using the beacon url.
-    pub fn blob_provider(
-        &self,
-    ) -> OnlineBlobProviderWithFallback<OnlineBeaconClient, OnlineBeaconClient, SimpleSlotDerivation>
-    {
-        OnlineBlobProviderBuilder::new()
-            .with_beacon_client(OnlineBeaconClient::new_http(self.beacon_url.clone()))
-            .build()
+        // Run the pipeline
+        let mut records = Vec::new();
+        let mut timestamps = HashMap::new();
+        while let Some(block) = session.next_block().await? {
+            if let Until::L1Origin(l1_block) = until {
+                if block.l2_block_info.l1_origin.number > l1_block {
+                    break;
+                }
+            }
+            debug!(target: TARGET, "Derived L2 block {}", block.number());
+            timestamps.insert(block.attributes.attributes.timestamp, records.len());
+            records.push(BlockRecord {
+                l2_block: block.number(),
+                l1_origin: block.l2_block_info.l1_origin.number,
+                l1_inclusion: block.l1_origin.number,
+                batcher_txs: vec![],
+                channel_id: None,
+                span_batch: None,
+                l1_relation: None,
+                channel_l1_blocks: vec![],
+                l2_timestamp: block.attributes.attributes.timestamp,
+                l1_inclusion_timestamp: block.l1_origin.timestamp,
+            });
+        }
+
+        // Attribute the blocks to the channels and batcher transactions they came from.
+        let mut frames = Vec::new();
+        let mut usage = DataUsage::default();
+        for data in dap.take().into_iter().filter(|d| !d.items.is_empty()) {
+            let (_, txs) = providers
+                .l1
+                .block_info_and_transactions_by_hash(data.block.hash)
+                .await
+                .map_err(|e| eyre!(e))?;
+            match posted_frames(&cfg, data.batcher, data.block.number, &txs, &data.items) {
+                Ok(posted) => frames.extend(posted),
+                Err(e) => {
+                    warn!(target: TARGET, "Skipping batcher data: {e}");
+                    continue;
+                }
+            }
+            usage.add(&cfg, data.batcher, &txs, &data.items);
+        }
+        let channels = assemble_channels(&cfg, frames);
+        for channel in &channels {
+            for batch in &channel.batches {
+                for block in batch.blocks() {
+                    let Some(&index) = timestamps.get(&block.timestamp) else {
+                        continue;
+                    };
+                    let record = &mut records[index];
+                    if record.channel_id.is_none() {
+                        record.batcher_txs = channel.txs.clone();
+                        record.channel_id = Some(channel.id);
+                        record.span_batch = Some(batch.is_span());
+                        record.channel_l1_blocks = channel.l1_blocks.clone();
+                    }
+                }
+            }
+        }
+
+        Ok(Derivation {
+            records,
+            channels,
+            data: usage,
+        })
     }
 
-    /// Returns a new [EthereumDataSource] using the l1 provider and blob provider.
-    pub fn dap(
-        &self,
-        l1_provider: AlloyChainProvider,
-        blob_provider: OnlineBlobProviderWithFallback<
-            OnlineBeaconClient,
-            OnlineBeaconClient,
-            SimpleSlotDerivation,
-        >,
-        cfg: &RollupConfig,
-    ) -> EthereumDataSource<
-        AlloyChainProvider,
-        OnlineBlobProviderWithFallback<
-            OnlineBeaconClient,
-            OnlineBeaconClient,
-            SimpleSlotDerivation,
-        >,
-    > {
-        EthereumDataSource::new(l1_provider, blob_provider, cfg)
+    /// Returns the [OnlineProviders] for the configured endpoints.
+    pub fn providers(&self, cfg: Arc<RollupConfig>) -> Result<OnlineProviders> {
+        Ok(OnlineProviders::new(
+            cfg,
+            self.l1_rpc_url()?,
+            self.l2_rpc_url()?,
+            self.beacon_url.clone(),
+            self.blob_archiver_url.clone(),
+        ))
     }
 
-    /// Gets the rollup config from the l2 rpc url.
+    /// Gets the rollup config from the rollup config file, the superchain registry, or the
+    /// rollup node.
     pub async fn rollup_config(&self) -> Result<RollupConfig> {
-        let mut l2_provider =
-            AlloyL2ChainProvider::new_http(self.l2_rpc_url()?, Arc::new(Default::default()));
-        let l2_chain_id = l2_provider.chain_id().await.map_err(|e| eyre!(e))?;
-        let cfg = ROLLUP_CONFIGS
-            .get(&l2_chain_id)
-            .cloned()
-            .ok_or_else(|| eyre!("No rollup config found for L2 chain ID: {}", l2_chain_id))?;
-        Ok(cfg)
+        self.rollup.resolve(self.l2_rpc_url()?, None).await
     }
 
     /// Returns the l1 rpc url from CLI or environment variable.
@@ -241,9 +553,4 @@ impl Cli {
     pub fn l2_rpc_url(&self) -> Result<Url> {
         Url::parse(&self.l2_rpc_url).map_err(|e| eyre!(e))
     }
-
-    /// Returns the beacon url from CLI or environment variable.
-    pub fn beacon_url(&self) -> String {
-        self.beacon_url.clone()
-    }
 }
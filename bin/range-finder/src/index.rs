@@ -0,0 +1,418 @@
+//! The on-disk index of derived L2 blocks, kept up to date by follow mode.
+
+use crate::report::BlockRecord;
+use alloy_primitives::B256;
+use color_eyre::{
+    eyre::{ensure, eyre, WrapErr},
+    Result,
+};
+use rusqlite::{params, types::Type, Connection, OptionalExtension, Row};
+use std::ops::RangeInclusive;
+use std::path::Path;
+
+/// The schema of the index. Tables are created if they do not exist yet.
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS meta (
+    key TEXT PRIMARY KEY,
+    value TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS blocks (
+    l2_block INTEGER PRIMARY KEY,
+    l1_origin INTEGER NOT NULL,
+    l1_inclusion INTEGER NOT NULL,
+    channel_id TEXT,
+    span_batch INTEGER,
+    channel_l1_start INTEGER,
+    channel_l1_blocks TEXT NOT NULL,
+    l2_timestamp INTEGER NOT NULL,
+    l1_inclusion_timestamp INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS blocks_l1_origin ON blocks (l1_origin);
+CREATE INDEX IF NOT EXISTS blocks_l1_inclusion ON blocks (l1_inclusion);
+CREATE TABLE IF NOT EXISTS batcher_txs (
+    l2_block INTEGER NOT NULL,
+    position INTEGER NOT NULL,
+    tx_hash TEXT NOT NULL,
+    PRIMARY KEY (l2_block, position)
+);
+CREATE INDEX IF NOT EXISTS batcher_txs_tx_hash ON batcher_txs (tx_hash);
+";
+
+/// The columns of the `blocks` table, in the order [read_record] reads them.
+const BLOCK_COLUMNS: &str = "l2_block, l1_origin, l1_inclusion, channel_id, span_batch, \
+    channel_l1_blocks, l2_timestamp, l1_inclusion_timestamp";
+
+/// An SQLite index of the [BlockRecord]s of a contiguous range of L2 blocks.
+///
+/// Follow mode appends every derived range to the index, so that the blocks in it are
+/// always contiguous from the first block indexed up to the tip.
+#[derive(Debug)]
+pub struct Index {
+    /// The connection to the index database.
+    conn: Connection,
+}
+
+impl Index {
+    /// Opens the index at the given path, creating it if it does not exist.
+    ///
+    /// An index belongs to a single L2 chain, so opening it for another chain fails.
+    pub fn open(path: impl AsRef<Path>, l2_chain_id: u64) -> Result<Self> {
+        let conn = Connection::open(path.as_ref()).map_err(|e| eyre!(e))?;
+        Self::new(conn, l2_chain_id)
+            .wrap_err_with(|| format!("Failed to open the index at {:?}", path.as_ref()))
+    }
+
+    /// Creates the index tables in the database of the connection, if it has none yet.
+    fn new(conn: Connection, l2_chain_id: u64) -> Result<Self> {
+        conn.execute_batch(SCHEMA).map_err(|e| eyre!(e))?;
+        let chain_id: Option<String> = conn
+            .query_row(
+                "SELECT value FROM meta WHERE key = 'l2_chain_id'",
+                [],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| eyre!(e))?;
+        match chain_id {
+            Some(chain_id) => ensure!(
+                chain_id == l2_chain_id.to_string(),
+                "Index belongs to L2 chain {}, not {}",
+                chain_id,
+                l2_chain_id
+            ),
+            None => {
+                conn.execute(
+                    "INSERT INTO meta (key, value) VALUES ('l2_chain_id', ?1)",
+                    [l2_chain_id.to_string()],
+                )
+                .map_err(|e| eyre!(e))?;
+            }
+        }
+        Ok(Self { conn })
+    }
+
+    /// Returns the last indexed record, if any.
+    pub fn tip(&self) -> Result<Option<BlockRecord>> {
+        let query = format!("SELECT {BLOCK_COLUMNS} FROM blocks ORDER BY l2_block DESC LIMIT 1");
+        let record = self
+            .conn
+            .query_row(&query, [], read_record)
+            .optional()
+            .map_err(|e| eyre!(e))?;
+        record.map(|r| self.with_txs(r)).transpose()
+    }
+
+    /// Returns the first indexed record, if any.
+    pub fn first(&self) -> Result<Option<BlockRecord>> {
+        let query = format!("SELECT {BLOCK_COLUMNS} FROM blocks ORDER BY l2_block ASC LIMIT 1");
+        let record = self
+            .conn
+            .query_row(&query, [], read_record)
+            .optional()
+            .map_err(|e| eyre!(e))?;
+        record.map(|r| self.with_txs(r)).transpose()
+    }
+
+    /// Adds the records to the index, replacing the ones already indexed.
+    pub fn insert(&mut self, records: &[BlockRecord]) -> Result<()> {
+        let tx = self.conn.transaction().map_err(|e| eyre!(e))?;
+        for r in records {
+            tx.execute(
+                "INSERT OR REPLACE INTO blocks (l2_block, l1_origin, l1_inclusion, channel_id, \
+                 span_batch, channel_l1_start, channel_l1_blocks, l2_timestamp, \
+                 l1_inclusion_timestamp) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                params![
+                    r.l2_block,
+                    r.l1_origin,
+                    r.l1_inclusion,
+                    r.channel_id.map(|id| id.to_string()),
+                    r.span_batch,
+                    r.channel_l1_blocks.first(),
+                    serde_json::to_string(&r.channel_l1_blocks)?,
+                    r.l2_timestamp,
+                    r.l1_inclusion_timestamp,
+                ],
+            )
+            .map_err(|e| eyre!(e))?;
+            tx.execute("DELETE FROM batcher_txs WHERE l2_block = ?1", [r.l2_block])
+                .map_err(|e| eyre!(e))?;
+            for (position, hash) in r.batcher_txs.iter().enumerate() {
+                tx.execute(
+                    "INSERT INTO batcher_txs (l2_block, position, tx_hash) VALUES (?1, ?2, ?3)",
+                    params![r.l2_block, position, hash.to_string()],
+                )
+                .map_err(|e| eyre!(e))?;
+            }
+        }
+        tx.commit().map_err(|e| eyre!(e))
+    }
+
+    /// Returns the records of the L2 range, or `None` if the index does not hold every
+    /// block in it.
+    pub fn l2_range(&self, range: RangeInclusive<u64>) -> Result<Option<Vec<BlockRecord>>> {
+        let records = self.query("l2_block BETWEEN ?1 AND ?2", [*range.start(), *range.end()])?;
+        let expected = range.end().saturating_sub(*range.start()) + 1;
+        Ok((records.len() as u64 == expected).then_some(records))
+    }
+
+    /// Returns the records of the L2 blocks related to the L1 range, with their
+    /// relation set, or `None` if the index does not cover the L1 range.
+    ///
+    /// The index covers the L1 range if its first block was included before the range,
+    /// and its tip has both its L1 origin and channel past the range.
+    pub fn l1_range(&self, range: RangeInclusive<u64>) -> Result<Option<Vec<BlockRecord>>> {
+        let (Some(first), Some(tip)) = (self.first()?, self.tip()?) else {
+            return Ok(None);
+        };
+        let tip_start = tip
+            .channel_l1_blocks
+            .first()
+            .map_or(tip.l1_origin, |&b| b.min(tip.l1_origin));
+        if first.l1_inclusion >= *range.start() || tip_start <= *range.end() {
+            return Ok(None);
+        }
+
+        // Every L1 block a record relates to is between its earliest L1 block and its
+        // inclusion block.
+        let mut records = self.query(
+            "l1_inclusion >= ?1 AND MIN(l1_origin, COALESCE(channel_l1_start, l1_origin)) <= ?2",
+            [*range.start(), *range.end()],
+        )?;
+        for record in &mut records {
+            record.l1_relation = record.relation_to(&range);
+        }
+        records.retain(|r| r.l1_relation.is_some());
+        Ok(Some(records))
+    }
+
+    /// Returns the records of the L2 blocks delivered by the batcher transaction.
+    pub fn batcher_tx(&self, hash: B256) -> Result<Vec<BlockRecord>> {
+        self.query(
+            "l2_block IN (SELECT l2_block FROM batcher_txs WHERE tx_hash = ?1)",
+            [hash.to_string()],
+        )
+    }
+
+    /// Returns the records matching the `WHERE` clause, ordered by L2 block.
+    fn query(&self, filter: &str, params: impl rusqlite::Params) -> Result<Vec<BlockRecord>> {
+        let query = format!("SELECT {BLOCK_COLUMNS} FROM blocks WHERE {filter} ORDER BY l2_block");
+        let mut stmt = self.conn.prepare(&query).map_err(|e| eyre!(e))?;
+        let rows = stmt.query_map(params, read_record).map_err(|e| eyre!(e))?;
+        rows.map(|row| self.with_txs(row.map_err(|e| eyre!(e))?))
+            .collect()
+    }
+
+    /// Fills in the batcher transactions of the record.
+    fn with_txs(&self, mut record: BlockRecord) -> Result<BlockRecord> {
+        let mut stmt = self
+            .conn
+            .prepare_cached("SELECT tx_hash FROM batcher_txs WHERE l2_block = ?1 ORDER BY position")
+            .map_err(|e| eyre!(e))?;
+        record.batcher_txs = stmt
+            .query_map([record.l2_block], |row| row.get::<_, String>(0))
+            .map_err(|e| eyre!(e))?
+            .map(|hash| {
+                hash.map_err(|e| eyre!(e))?
+                    .parse::<B256>()
+                    .map_err(|e| eyre!(e))
+            })
+            .collect::<Result<_>>()?;
+        Ok(record)
+    }
+}
+
+/// Reads a record without its batcher transactions from a row of [BLOCK_COLUMNS].
+fn read_record(row: &Row<'_>) -> rusqlite::Result<BlockRecord> {
+    let channel_id: Option<String> = row.get(3)?;
+    let channel_l1_blocks: String = row.get(5)?;
+    Ok(BlockRecord {
+        l2_block: row.get(0)?,
+        l1_origin: row.get(1)?,
+        l1_inclusion: row.get(2)?,
+        batcher_txs: vec![],
+        channel_id: channel_id.map(|id| parse(3, id.parse())).transpose()?,
+        span_batch: row.get(4)?,
+        l1_relation: None,
+        channel_l1_blocks: parse(5, serde_json::from_str(&channel_l1_blocks))?,
+        l2_timestamp: row.get(6)?,
+        l1_inclusion_timestamp: row.get(7)?,
+    })
+}
+
+/// Maps an error parsing the text in the given column to an [rusqlite::Error].
+fn parse<T, E>(column: usize, parsed: std::result::Result<T, E>) -> rusqlite::Result<T>
+where
+    E: std::error::Error + Send + Sync + 'static,
+{
+    parsed.map_err(|e| rusqlite::Error::FromSqlConversionFailure(column, Type::Text, Box::new(e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::L1Relation;
+    use alloy_primitives::FixedBytes;
+
+    fn record(
+        l2_block: u64,
+        l1_origin: u64,
+        l1_inclusion: u64,
+        channel_l1_blocks: Vec<u64>,
+        batcher_txs: Vec<B256>,
+    ) -> BlockRecord {
+        BlockRecord {
+            l2_block,
+            l1_origin,
+            l1_inclusion,
+            batcher_txs,
+            channel_id: Some(FixedBytes::repeat_byte(channel_l1_blocks[0] as u8)),
+            span_batch: Some(false),
+            l1_relation: None,
+            channel_l1_blocks,
+            l2_timestamp: l2_block * 2,
+            l1_inclusion_timestamp: l1_inclusion * 12,
+        }
+    }
+
+    fn tx(n: u8) -> B256 {
+        B256::repeat_byte(n)
+    }
+
+    /// Returns the records of L2 blocks 1 to 5, whose channels were posted in L1 blocks
+    /// 11 to 15.
+    fn records() -> Vec<BlockRecord> {
+        vec![
+            record(1, 10, 11, vec![11], vec![tx(1)]),
+            record(2, 10, 12, vec![12], vec![tx(2)]),
+            record(3, 11, 12, vec![12], vec![tx(2)]),
+            record(4, 12, 14, vec![13, 14], vec![tx(3), tx(4)]),
+            record(5, 14, 15, vec![15], vec![tx(5)]),
+        ]
+    }
+
+    /// Returns an empty in-memory index of L2 chain 10.
+    fn empty() -> Index {
+        Index::new(Connection::open_in_memory().unwrap(), 10).unwrap()
+    }
+
+    /// Returns an in-memory index of the [records].
+    fn indexed() -> Index {
+        let mut index = empty();
+        index.insert(&records()).unwrap();
+        index
+    }
+
+    #[test]
+    fn test_open_rejects_other_chain() {
+        let index = Index::new(empty().conn, 10).unwrap();
+        let err = Index::new(index.conn, 11).unwrap_err();
+        assert_eq!(err.to_string(), "Index belongs to L2 chain 10, not 11");
+    }
+
+    #[test]
+    fn test_first_and_tip() {
+        let index = empty();
+        assert_eq!(index.first().unwrap(), None);
+        assert_eq!(index.tip().unwrap(), None);
+
+        let index = indexed();
+        let records = records();
+        assert_eq!(index.first().unwrap().as_ref(), records.first());
+        assert_eq!(index.tip().unwrap().as_ref(), records.last());
+    }
+
+    #[test]
+    fn test_insert_replaces() {
+        let mut index = indexed();
+        let replaced = BlockRecord {
+            channel_id: None,
+            span_batch: None,
+            ..record(4, 12, 16, vec![16], vec![tx(6)])
+        };
+        index.insert(std::slice::from_ref(&replaced)).unwrap();
+
+        assert_eq!(index.l2_range(4..=4).unwrap(), Some(vec![replaced]));
+        assert_eq!(index.batcher_tx(tx(3)).unwrap(), []);
+        assert_eq!(index.batcher_tx(tx(4)).unwrap(), []);
+        assert_eq!(index.l2_range(1..=5).unwrap().unwrap().len(), 5);
+    }
+
+    #[test]
+    fn test_l2_range() {
+        let mut index = indexed();
+        assert_eq!(index.l2_range(1..=5).unwrap(), Some(records()));
+        assert_eq!(
+            index.l2_range(2..=3).unwrap(),
+            Some(records()[1..3].to_vec())
+        );
+        assert_eq!(index.l2_range(5..=6).unwrap(), None);
+
+        index
+            .insert(&[record(7, 15, 16, vec![16], vec![])])
+            .unwrap();
+        assert_eq!(index.l2_range(5..=7).unwrap(), None);
+        assert_eq!(index.l2_range(7..=7).unwrap().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_l1_range() {
+        let index = indexed();
+        let relations = |range| {
+            index.l1_range(range).unwrap().map(|records| {
+                records
+                    .into_iter()
+                    .map(|r| (r.l2_block, r.l1_relation.unwrap()))
+                    .collect::<Vec<_>>()
+            })
+        };
+
+        assert_eq!(
+            relations(12..=12),
+            Some(vec![
+                (2, L1Relation::Data),
+                (3, L1Relation::Data),
+                (4, L1Relation::Origin),
+            ])
+        );
+        assert_eq!(
+            relations(12..=13),
+            Some(vec![
+                (2, L1Relation::Data),
+                (3, L1Relation::Data),
+                (4, L1Relation::OriginAndData),
+            ])
+        );
+        // The first block must have been included before the range.
+        assert_eq!(relations(11..=12), None);
+        // The tip must have its L1 origin past the range.
+        assert_eq!(relations(12..=14), None);
+    }
+
+    #[test]
+    fn test_l1_range_tip_channel() {
+        let mut index = indexed();
+        assert!(index.l1_range(12..=13).unwrap().is_some());
+
+        // The tip must also have its channel past the range.
+        index
+            .insert(&[record(6, 15, 16, vec![13, 16], vec![])])
+            .unwrap();
+        assert_eq!(index.l1_range(12..=13).unwrap(), None);
+        assert!(index.l1_range(12..=12).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_l1_range_empty() {
+        let index = empty();
+        assert_eq!(index.l1_range(1..=2).unwrap(), None);
+    }
+
+    #[test]
+    fn test_batcher_tx() {
+        let index = indexed();
+        let records = records();
+        assert_eq!(index.batcher_tx(tx(2)).unwrap(), records[1..3]);
+        assert_eq!(index.batcher_tx(tx(4)).unwrap(), records[3..4]);
+        assert_eq!(index.batcher_tx(tx(9)).unwrap(), []);
+    }
+}
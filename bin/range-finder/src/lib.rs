@@ -5,3 +5,8 @@
 
 pub mod cli;
 pub use cli::Cli;
+
+pub mod index;
+pub mod report;
+pub mod server;
+pub mod stats;
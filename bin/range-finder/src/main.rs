@@ -1,6 +1,16 @@
 use clap::Parser;
+use derivation_driver::stalled_exit_code;
 
 #[tokio::main]
 async fn main() -> color_eyre::Result<()> {
-    range_finder::Cli::parse().init_telemetry()?.run().await
+    let result = range_finder::Cli::parse().init_telemetry()?.run().await;
+    // Stalled derivation exits with a distinct code so that unattended runs can tell
+    // it apart from other failures.
+    if let Err(e) = &result {
+        if let Some(code) = stalled_exit_code(e) {
+            eprintln!("Error: {e:?}");
+            std::process::exit(code);
+        }
+    }
+    result
 }
@@ -0,0 +1,222 @@
+//! The records range-finder reports for every derived L2 block, and their formats.
+
+use alloy_primitives::{FixedBytes, B256};
+use clap::ValueEnum;
+use color_eyre::Result;
+use serde::Serialize;
+use std::io::Write;
+use std::ops::RangeInclusive;
+
+/// The output format of the range-finder.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// One `L2 Block [n] L1 Range: [a, b]` line per L2 block.
+    #[default]
+    Text,
+    /// A JSON object with the records and the summary.
+    Json,
+    /// One CSV row per L2 block. The summary is written to stderr.
+    Csv,
+    /// One JSON object per line for every L2 block. The summary is written to stderr.
+    Ndjson,
+}
+
+/// How an L2 block was derived.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockRecord {
+    /// The L2 block number.
+    pub l2_block: u64,
+    /// The L1 origin of the L2 block.
+    pub l1_origin: u64,
+    /// The L1 origin of the pipeline when the L2 block was derived, which is the L1
+    /// block that completed its batch.
+    pub l1_inclusion: u64,
+    /// The batcher transactions that posted the channel carrying the batch.
+    pub batcher_txs: Vec<B256>,
+    /// The channel carrying the batch, if it was read in full.
+    pub channel_id: Option<FixedBytes<16>>,
+    /// Whether the batch is a span batch, if the channel was read in full.
+    pub span_batch: Option<bool>,
+    /// How the L2 block relates to the L1 range of a reverse lookup.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub l1_relation: Option<L1Relation>,
+    /// The L1 blocks the channel carrying the batch was posted in, if it was read.
+    #[serde(skip)]
+    pub channel_l1_blocks: Vec<u64>,
+    /// The timestamp of the L2 block.
+    #[serde(skip)]
+    pub l2_timestamp: u64,
+    /// The timestamp of the inclusion block.
+    #[serde(skip)]
+    pub l1_inclusion_timestamp: u64,
+}
+
+impl BlockRecord {
+    /// Returns how the L2 block relates to the L1 range, or `None` if it does not.
+    ///
+    /// The batch counts as posted in the range if any frame of its channel was, or if
+    /// its inclusion block is in the range when the channel was not read in full.
+    pub fn relation_to(&self, range: &RangeInclusive<u64>) -> Option<L1Relation> {
+        let data = if self.channel_l1_blocks.is_empty() {
+            range.contains(&self.l1_inclusion)
+        } else {
+            self.channel_l1_blocks.iter().any(|b| range.contains(b))
+        };
+        L1Relation::new(range.contains(&self.l1_origin), data)
+    }
+}
+
+/// How an L2 block relates to the L1 range of a reverse lookup.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum L1Relation {
+    /// The L1 origin of the L2 block is in the range.
+    Origin,
+    /// The batch of the L2 block was posted in the range.
+    Data,
+    /// Both the L1 origin and the batch of the L2 block are in the range.
+    OriginAndData,
+}
+
+impl L1Relation {
+    /// Returns the relation for whether the origin and the batch data are in the
+    /// range, or `None` if neither is.
+    pub fn new(origin: bool, data: bool) -> Option<Self> {
+        match (origin, data) {
+            (true, true) => Some(Self::OriginAndData),
+            (true, false) => Some(Self::Origin),
+            (false, true) => Some(Self::Data),
+            (false, false) => None,
+        }
+    }
+
+    /// Returns the name of the relation, as written in the output.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Origin => "origin",
+            Self::Data => "data",
+            Self::OriginAndData => "origin-and-data",
+        }
+    }
+}
+
+/// The L1 range needed to derive a range of L2 blocks.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct Summary {
+    /// The first L2 block derived.
+    pub l2_start: u64,
+    /// The last L2 block derived.
+    pub l2_end: u64,
+    /// The first L1 block needed, which is the earliest L1 origin or channel opening.
+    pub l1_start: u64,
+    /// The last L1 block needed, which is the latest inclusion block.
+    pub l1_end: u64,
+}
+
+impl Summary {
+    /// Returns the summary of the records, or `None` if there are none.
+    pub fn new(records: &[BlockRecord]) -> Option<Self> {
+        Some(Self {
+            l2_start: records.iter().map(|r| r.l2_block).min()?,
+            l2_end: records.iter().map(|r| r.l2_block).max()?,
+            l1_start: records
+                .iter()
+                .flat_map(|r| {
+                    std::iter::once(r.l1_origin).chain(r.channel_l1_blocks.first().copied())
+                })
+                .min()?,
+            l1_end: records.iter().map(|r| r.l1_inclusion).max()?,
+        })
+    }
+}
+
+/// The JSON output of the range-finder.
+#[derive(Serialize, Debug)]
+pub struct JsonOutput<'a> {
+    /// The records.
+    pub blocks: &'a [BlockRecord],
+    /// The summary, if any block was derived.
+    pub summary: Option<Summary>,
+}
+
+impl<'a> JsonOutput<'a> {
+    /// Creates the JSON output of the records.
+    pub fn new(records: &'a [BlockRecord]) -> Self {
+        Self {
+            blocks: records,
+            summary: Summary::new(records),
+        }
+    }
+}
+
+/// Writes the records and their summary in the given format.
+pub fn write(format: OutputFormat, records: &[BlockRecord]) -> Result<()> {
+    let summary = Summary::new(records);
+    let mut out = std::io::stdout().lock();
+    match format {
+        OutputFormat::Text => {
+            for r in records {
+                write!(
+                    out,
+                    "L2 Block [{}] L1 Range: [{}, {}]",
+                    r.l2_block, r.l1_origin, r.l1_inclusion
+                )?;
+                match r.l1_relation {
+                    Some(relation) => writeln!(out, " ({})", relation.name())?,
+                    None => writeln!(out)?,
+                }
+            }
+            if let Some(s) = summary {
+                writeln!(out, "Minimal L1 Range: [{}, {}]", s.l1_start, s.l1_end)?;
+            }
+        }
+        OutputFormat::Json => {
+            serde_json::to_writer_pretty(&mut out, &JsonOutput::new(records))?;
+            writeln!(out)?;
+        }
+        OutputFormat::Csv => {
+            writeln!(
+                out,
+                "l2_block,l1_origin,l1_inclusion,batcher_txs,channel_id,span_batch,l1_relation"
+            )?;
+            for r in records {
+                let txs = r
+                    .batcher_txs
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(";");
+                writeln!(
+                    out,
+                    "{},{},{},{},{},{},{}",
+                    r.l2_block,
+                    r.l1_origin,
+                    r.l1_inclusion,
+                    txs,
+                    r.channel_id.map(|id| id.to_string()).unwrap_or_default(),
+                    r.span_batch.map(|s| s.to_string()).unwrap_or_default(),
+                    r.l1_relation.map(|r| r.name()).unwrap_or_default()
+                )?;
+            }
+            write_summary(summary)?;
+        }
+        OutputFormat::Ndjson => {
+            for r in records {
+                serde_json::to_writer(&mut out, r)?;
+                writeln!(out)?;
+            }
+            write_summary(summary)?;
+        }
+    }
+    Ok(())
+}
+
+/// Writes the summary to stderr, so that stdout holds only records.
+fn write_summary(summary: Option<Summary>) -> Result<()> {
+    if let Some(summary) = summary {
+        eprintln!("{}", serde_json::to_string(&summary)?);
+    }
+    Ok(())
+}
@@ -0,0 +1,106 @@
+//! The HTTP/JSON endpoint serving the [Index] to other tooling.
+
+use crate::index::Index;
+use crate::report::{BlockRecord, JsonOutput};
+use alloy_primitives::B256;
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::Json;
+use color_eyre::{eyre::eyre, Result};
+use serde::Deserialize;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use tokio::net::TcpListener;
+use tracing::{info, warn};
+
+/// The logging target to use for [tracing].
+const TARGET: &str = "range-finder";
+
+/// An inclusive block range, given as `?start=<n>&end=<n>`. The end defaults to the start.
+#[derive(Deserialize, Debug, Clone, Copy)]
+struct RangeQuery {
+    /// The first block of the range.
+    start: u64,
+    /// The last block of the range.
+    end: Option<u64>,
+}
+
+/// Serves the index on the given address until the server fails.
+///
+/// - `GET /tip` returns the last indexed record.
+/// - `GET /l2?start=<n>&end=<n>` returns the records of an L2 range, like `--format json`.
+/// - `GET /l1?start=<n>&end=<n>` returns the L2 blocks related to an L1 range, like a
+///   reverse lookup with `--format json`.
+/// - `GET /tx/<hash>` returns the L2 blocks delivered by a batcher transaction.
+///
+/// Ranges the index does not cover yet are answered with `404 Not Found`.
+pub async fn serve(addr: SocketAddr, index: Arc<Mutex<Index>>) -> Result<()> {
+    let router = axum::Router::new()
+        .route("/tip", get(tip))
+        .route("/l2", get(l2_range))
+        .route("/l1", get(l1_range))
+        .route("/tx/:hash", get(batcher_tx))
+        .with_state(index);
+    let listener = TcpListener::bind(addr).await?;
+    info!(target: TARGET, "Serving the index on http://{}", listener.local_addr()?);
+    axum::serve(listener, router.into_make_service()).await?;
+    Ok(())
+}
+
+/// Handles `GET /tip`.
+async fn tip(State(index): State<Arc<Mutex<Index>>>) -> Response {
+    respond(with_index(&index, Index::tip).map(|tip| tip.map(Json)))
+}
+
+/// Handles `GET /l2`.
+async fn l2_range(
+    State(index): State<Arc<Mutex<Index>>>,
+    Query(range): Query<RangeQuery>,
+) -> Response {
+    let records = with_index(&index, |index| {
+        index.l2_range(range.start..=range.end.unwrap_or(range.start))
+    });
+    respond(records.map(|records| records.map(|r| json(&r))))
+}
+
+/// Handles `GET /l1`.
+async fn l1_range(
+    State(index): State<Arc<Mutex<Index>>>,
+    Query(range): Query<RangeQuery>,
+) -> Response {
+    let records = with_index(&index, |index| {
+        index.l1_range(range.start..=range.end.unwrap_or(range.start))
+    });
+    respond(records.map(|records| records.map(|r| json(&r))))
+}
+
+/// Handles `GET /tx/<hash>`.
+async fn batcher_tx(State(index): State<Arc<Mutex<Index>>>, Path(hash): Path<B256>) -> Response {
+    let records = with_index(&index, |index| index.batcher_tx(hash));
+    respond(records.map(|records| (!records.is_empty()).then(|| json(&records))))
+}
+
+/// Runs the query against the index.
+fn with_index<T>(index: &Mutex<Index>, query: impl FnOnce(&Index) -> Result<T>) -> Result<T> {
+    let index = index.lock().map_err(|_| eyre!("Index lock poisoned"))?;
+    query(&index)
+}
+
+/// Returns the records as the JSON output of the range-finder.
+fn json(records: &[BlockRecord]) -> Response {
+    Json(JsonOutput::new(records)).into_response()
+}
+
+/// Turns the result of a query into a response, with `404 Not Found` for `None`.
+fn respond<T: IntoResponse>(result: Result<Option<T>>) -> Response {
+    match result {
+        Ok(Some(response)) => response.into_response(),
+        Ok(None) => (StatusCode::NOT_FOUND, "Not indexed").into_response(),
+        Err(e) => {
+            warn!(target: TARGET, "Index query failed: {e}");
+            (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response()
+        }
+    }
+}
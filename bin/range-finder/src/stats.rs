@@ -0,0 +1,622 @@
+//! Batch submission statistics over a range of derived L2 blocks.
+
+use crate::report::BlockRecord;
+use alloy_consensus::TxEnvelope;
+use alloy_primitives::{Address, Bytes, FixedBytes};
+use derivation_driver::{batcher_txs, data_items, Channel};
+use kona_primitives::RollupConfig;
+use serde::Serialize;
+use std::fmt::Display;
+
+/// The batch submission statistics of a range of L2 blocks.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Stats {
+    /// The statistics over the whole range.
+    pub summary: StatsSummary,
+    /// The inclusion latency of every L2 block.
+    pub blocks: Vec<BlockLatency>,
+    /// Every channel read by the pipeline, including ones carrying blocks outside the
+    /// range.
+    pub channels: Vec<ChannelStats>,
+    /// The runs of L2 blocks that were included close to the end of their sequencing
+    /// window.
+    pub window_gaps: Vec<WindowGap>,
+}
+
+/// The statistics over a whole range of L2 blocks.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct StatsSummary {
+    /// The number of L2 blocks derived.
+    pub l2_blocks: usize,
+    /// The inclusion latency, in L1 blocks.
+    pub latency_blocks: Option<Distribution>,
+    /// The inclusion latency, in seconds.
+    pub latency_seconds: Option<Distribution>,
+    /// The number of channels read.
+    pub channels: usize,
+    /// The number of channels that could be decoded.
+    pub decoded_channels: usize,
+    /// The number of frames per channel.
+    pub frames_per_channel: Option<Distribution>,
+    /// The compressed size of the decoded channels.
+    pub compressed_bytes: usize,
+    /// The decompressed size of the decoded channels.
+    pub decompressed_bytes: usize,
+    /// The ratio of the decompressed to the compressed size of the decoded channels.
+    pub compression_ratio: Option<f64>,
+    /// The number of singular batches.
+    pub single_batches: usize,
+    /// The number of span batches.
+    pub span_batches: usize,
+    /// How the batcher data was posted.
+    pub data: DataUsage,
+    /// The sequencing window size, in L1 blocks.
+    pub seq_window_size: u64,
+    /// The latency, in L1 blocks, above which a block counts towards a window gap.
+    pub window_threshold: u64,
+}
+
+/// The minimum, maximum and mean of a set of values.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+pub struct Distribution {
+    /// The smallest value.
+    pub min: u64,
+    /// The largest value.
+    pub max: u64,
+    /// The mean value.
+    pub mean: f64,
+}
+
+impl Distribution {
+    /// Returns the distribution of the values, or `None` if there are none.
+    pub fn new(values: impl IntoIterator<Item = u64>) -> Option<Self> {
+        let (mut min, mut max, mut sum, mut count) = (u64::MAX, 0, 0u128, 0usize);
+        for value in values {
+            min = min.min(value);
+            max = max.max(value);
+            sum += value as u128;
+            count += 1;
+        }
+        (count > 0).then(|| Self {
+            min,
+            max,
+            mean: sum as f64 / count as f64,
+        })
+    }
+}
+
+impl Display for Distribution {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "min {}, mean {:.2}, max {}",
+            self.min, self.mean, self.max
+        )
+    }
+}
+
+/// How long it took for an L2 block to be included on L1.
+#[derive(Serialize, Debug, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockLatency {
+    /// The L2 block number.
+    pub l2_block: u64,
+    /// The L1 origin of the L2 block.
+    pub l1_origin: u64,
+    /// The L1 block that completed the batch of the L2 block.
+    pub l1_inclusion: u64,
+    /// The number of L1 blocks from the L1 origin to the inclusion block.
+    pub latency_blocks: u64,
+    /// The number of seconds from the L2 block to the inclusion block.
+    pub latency_seconds: u64,
+}
+
+/// The statistics of a channel.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ChannelStats {
+    /// The channel id.
+    pub id: FixedBytes<16>,
+    /// The L1 block carrying the first frame of the channel.
+    pub opened_in: u64,
+    /// The L1 block carrying the last frame of the channel, if it is complete.
+    pub closed_in: Option<u64>,
+    /// The number of frames received.
+    pub frames: usize,
+    /// The number of transactions that posted the frames.
+    pub txs: usize,
+    /// The size of the compressed channel data.
+    pub compressed_size: usize,
+    /// The size of the decompressed channel data, if it could be decompressed.
+    pub decompressed_size: Option<usize>,
+    /// The ratio of the decompressed to the compressed size.
+    pub compression_ratio: Option<f64>,
+    /// The number of singular batches in the channel.
+    pub single_batches: usize,
+    /// The number of span batches in the channel.
+    pub span_batches: usize,
+    /// The number of L2 blocks carried by the channel.
+    pub l2_blocks: usize,
+    /// Why the channel could not be decoded, if it could not.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl From<&Channel> for ChannelStats {
+    fn from(channel: &Channel) -> Self {
+        let span_batches = channel.batches.iter().filter(|b| b.is_span()).count();
+        Self {
+            id: channel.id,
+            opened_in: channel.opened_in,
+            closed_in: channel.closed_in,
+            frames: channel.frames.len(),
+            txs: channel.txs.len(),
+            compressed_size: channel.compressed_size,
+            decompressed_size: channel.decompressed_size,
+            compression_ratio: channel
+                .decompressed_size
+                .filter(|_| channel.compressed_size > 0)
+                .map(|size| size as f64 / channel.compressed_size as f64),
+            single_batches: channel.batches.len() - span_batches,
+            span_batches,
+            l2_blocks: channel.batches.iter().map(|b| b.blocks().len()).sum(),
+            error: channel.error.clone(),
+        }
+    }
+}
+
+/// How the batcher posted its data.
+#[derive(Serialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct DataUsage {
+    /// The number of batcher transactions posting calldata.
+    pub calldata_txs: usize,
+    /// The size of the calldata posted.
+    pub calldata_bytes: usize,
+    /// The number of batcher transactions posting blobs.
+    pub blob_txs: usize,
+    /// The number of blobs posted.
+    pub blobs: usize,
+    /// The size of the data in the blobs posted.
+    pub blob_bytes: usize,
+}
+
+impl DataUsage {
+    /// Adds the batcher data read from an L1 block with the given transactions.
+    ///
+    /// The data items are matched to the batcher transactions as the data source yields
+    /// them, one per calldata transaction and one per blob.
+    pub fn add(
+        &mut self,
+        cfg: &RollupConfig,
+        batcher: Address,
+        txs: &[TxEnvelope],
+        items: &[Bytes],
+    ) {
+        let mut items = items.iter();
+        for tx in batcher_txs(cfg, batcher, txs) {
+            let count = data_items(tx);
+            let bytes = items
+                .by_ref()
+                .take(count)
+                .map(|item| item.len())
+                .sum::<usize>();
+            match tx {
+                TxEnvelope::Eip4844(_) => {
+                    self.blob_txs += 1;
+                    self.blobs += count;
+                    self.blob_bytes += bytes;
+                }
+                _ => {
+                    self.calldata_txs += 1;
+                    self.calldata_bytes += bytes;
+                }
+            }
+        }
+    }
+}
+
+/// A run of consecutive L2 blocks included close to the end of their sequencing window.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct WindowGap {
+    /// The first L2 block of the run.
+    pub l2_start: u64,
+    /// The last L2 block of the run.
+    pub l2_end: u64,
+    /// The largest inclusion latency in the run, in L1 blocks.
+    pub max_latency: u64,
+}
+
+impl Stats {
+    /// Computes the statistics of the derived records and the channels read.
+    ///
+    /// Blocks included at least `window_threshold` percent of the sequencing window
+    /// after their L1 origin count towards a window gap.
+    pub fn new(
+        cfg: &RollupConfig,
+        records: &[BlockRecord],
+        channels: &[Channel],
+        data: DataUsage,
+        window_threshold: u64,
+    ) -> Self {
+        let blocks = records
+            .iter()
+            .map(|r| BlockLatency {
+                l2_block: r.l2_block,
+                l1_origin: r.l1_origin,
+                l1_inclusion: r.l1_inclusion,
+                latency_blocks: r.l1_inclusion.saturating_sub(r.l1_origin),
+                latency_seconds: r.l1_inclusion_timestamp.saturating_sub(r.l2_timestamp),
+            })
+            .collect::<Vec<_>>();
+        let channels = channels.iter().map(ChannelStats::from).collect::<Vec<_>>();
+
+        let threshold = cfg.seq_window_size * window_threshold / 100;
+        let mut window_gaps: Vec<WindowGap> = Vec::new();
+        for block in blocks.iter().filter(|b| b.latency_blocks >= threshold) {
+            match window_gaps.last_mut() {
+                Some(gap) if gap.l2_end + 1 == block.l2_block => {
+                    gap.l2_end = block.l2_block;
+                    gap.max_latency = gap.max_latency.max(block.latency_blocks);
+                }
+                _ => window_gaps.push(WindowGap {
+                    l2_start: block.l2_block,
+                    l2_end: block.l2_block,
+                    max_latency: block.latency_blocks,
+                }),
+            }
+        }
+
+        let decoded = channels.iter().filter(|c| c.decompressed_size.is_some());
+        let compressed_bytes = decoded.clone().map(|c| c.compressed_size).sum::<usize>();
+        let decompressed_bytes = decoded
+            .clone()
+            .filter_map(|c| c.decompressed_size)
+            .sum::<usize>();
+        let summary = StatsSummary {
+            l2_blocks: blocks.len(),
+            latency_blocks: Distribution::new(blocks.iter().map(|b| b.latency_blocks)),
+            latency_seconds: Distribution::new(blocks.iter().map(|b| b.latency_seconds)),
+            channels: channels.len(),
+            decoded_channels: decoded.count(),
+            frames_per_channel: Distribution::new(channels.iter().map(|c| c.frames as u64)),
+            compressed_bytes,
+            decompressed_bytes,
+            compression_ratio: (compressed_bytes > 0)
+                .then(|| decompressed_bytes as f64 / compressed_bytes as f64),
+            single_batches: channels.iter().map(|c| c.single_batches).sum(),
+            span_batches: channels.iter().map(|c| c.span_batches).sum(),
+            data,
+            seq_window_size: cfg.seq_window_size,
+            window_threshold: threshold,
+        };
+
+        Self {
+            summary,
+            blocks,
+            channels,
+            window_gaps,
+        }
+    }
+
+    /// Formats the summary as a table with one row per statistic.
+    pub fn table(&self) -> String {
+        let s = &self.summary;
+        let or_none =
+            |d: Option<Distribution>| d.map_or_else(|| "-".to_string(), |d| d.to_string());
+        let rows = [
+            ("L2 blocks", s.l2_blocks.to_string()),
+            ("Inclusion latency (L1 blocks)", or_none(s.latency_blocks)),
+            ("Inclusion latency (seconds)", or_none(s.latency_seconds)),
+            (
+                "Channels",
+                format!("{} ({} decoded)", s.channels, s.decoded_channels),
+            ),
+            ("Frames per channel", or_none(s.frames_per_channel)),
+            (
+                "Channel data",
+                format!("{} -> {} bytes", s.compressed_bytes, s.decompressed_bytes),
+            ),
+            (
+                "Compression ratio",
+                s.compression_ratio
+                    .map_or_else(|| "-".to_string(), |r| format!("{r:.2}")),
+            ),
+            (
+                "Batches",
+                format!("{} single, {} span", s.single_batches, s.span_batches),
+            ),
+            (
+                "Calldata",
+                format!(
+                    "{} txs, {} bytes",
+                    s.data.calldata_txs, s.data.calldata_bytes
+                ),
+            ),
+            (
+                "Blobs",
+                format!(
+                    "{} txs, {} blobs, {} bytes",
+                    s.data.blob_txs, s.data.blobs, s.data.blob_bytes
+                ),
+            ),
+            (
+                "Window gaps",
+                format!(
+                    "{} (latency >= {} of {} L1 blocks)",
+                    self.window_gaps.len(),
+                    s.window_threshold,
+                    s.seq_window_size
+                ),
+            ),
+        ];
+        let width = rows
+            .iter()
+            .map(|(name, _)| name.len())
+            .max()
+            .unwrap_or_default();
+        let mut out = String::new();
+        for (name, value) in rows {
+            out.push_str(&format!("{name:<width$}  {value}\n"));
+        }
+        for gap in &self.window_gaps {
+            out.push_str(&format!(
+                "  L2 blocks [{}, {}], max latency {}\n",
+                gap.l2_start, gap.l2_end, gap.max_latency
+            ));
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_consensus::{SignableTransaction, TxEip1559, TxEip4844};
+    use alloy_primitives::{address, keccak256, TxKind, B256};
+    use alloy_signer::SignerSync;
+    use alloy_signer_local::PrivateKeySigner;
+    use derivation_driver::{BatchInfo, L2BlockRef};
+
+    const INBOX: Address = address!("ff00000000000000000000000000000000000901");
+
+    fn rollup_config() -> RollupConfig {
+        RollupConfig {
+            seq_window_size: 10,
+            batch_inbox_address: INBOX,
+            ..Default::default()
+        }
+    }
+
+    /// Returns the record of an L2 block included `latency` L1 blocks after its origin.
+    fn record(l2_block: u64, latency: u64) -> BlockRecord {
+        BlockRecord {
+            l2_block,
+            l1_origin: 100,
+            l1_inclusion: 100 + latency,
+            batcher_txs: vec![],
+            channel_id: None,
+            span_batch: None,
+            l1_relation: None,
+            channel_l1_blocks: vec![],
+            l2_timestamp: l2_block * 2,
+            l1_inclusion_timestamp: l2_block * 2 + latency * 12,
+        }
+    }
+
+    fn records() -> Vec<BlockRecord> {
+        [(1, 4), (2, 5), (3, 7), (4, 4), (5, 5), (7, 10)]
+            .into_iter()
+            .map(|(l2_block, latency)| record(l2_block, latency))
+            .collect()
+    }
+
+    fn window_gaps(window_threshold: u64) -> Vec<(u64, u64, u64)> {
+        Stats::new(
+            &rollup_config(),
+            &records(),
+            &[],
+            DataUsage::default(),
+            window_threshold,
+        )
+        .window_gaps
+        .into_iter()
+        .map(|gap| (gap.l2_start, gap.l2_end, gap.max_latency))
+        .collect()
+    }
+
+    #[test]
+    fn test_latency() {
+        let stats = Stats::new(&rollup_config(), &records(), &[], DataUsage::default(), 50);
+        let latencies = stats
+            .blocks
+            .iter()
+            .map(|b| (b.l2_block, b.latency_blocks))
+            .collect::<Vec<_>>();
+        assert_eq!(latencies, [(1, 4), (2, 5), (3, 7), (4, 4), (5, 5), (7, 10)]);
+        assert_eq!(
+            stats.summary.latency_blocks,
+            Some(Distribution {
+                min: 4,
+                max: 10,
+                mean: 35.0 / 6.0,
+            })
+        );
+        assert_eq!(
+            stats.summary.latency_seconds,
+            Some(Distribution {
+                min: 48,
+                max: 120,
+                mean: 70.0,
+            })
+        );
+        assert_eq!(stats.summary.l2_blocks, 6);
+        assert_eq!(stats.summary.window_threshold, 5);
+    }
+
+    #[test]
+    fn test_window_gaps() {
+        // Blocks included exactly at the threshold count towards a gap, and runs break
+        // at blocks below it and at missing blocks.
+        assert_eq!(window_gaps(50), [(2, 3, 7), (5, 5, 5), (7, 7, 10)]);
+        assert_eq!(window_gaps(60), [(3, 3, 7), (7, 7, 10)]);
+    }
+
+    #[test]
+    fn test_window_gaps_threshold_bounds() {
+        // Every block counts towards a gap with a threshold of 0.
+        assert_eq!(window_gaps(0), [(1, 5, 7), (7, 7, 10)]);
+        // Only blocks included at the end of their sequencing window count with a
+        // threshold of 100.
+        assert_eq!(window_gaps(100), [(7, 7, 10)]);
+    }
+
+    fn channel(compressed_size: usize, decompressed_size: Option<usize>) -> Channel {
+        let block = |timestamp| L2BlockRef {
+            number: None,
+            timestamp,
+            epoch_num: 0,
+            tx_count: 0,
+        };
+        Channel {
+            id: FixedBytes::ZERO,
+            opened_in: 1,
+            closed_in: decompressed_size.map(|_| 2),
+            frames: vec![0, 1],
+            txs: vec![],
+            l1_blocks: vec![1, 2],
+            compressed_size,
+            decompressed_size,
+            batches: decompressed_size
+                .map(|_| {
+                    vec![
+                        BatchInfo::Single {
+                            parent_hash: Default::default(),
+                            block: block(2),
+                        },
+                        BatchInfo::Span {
+                            parent_check: FixedBytes::ZERO,
+                            l1_origin_check: FixedBytes::ZERO,
+                            blocks: vec![block(4), block(6)],
+                        },
+                    ]
+                })
+                .unwrap_or_default(),
+            error: None,
+        }
+    }
+
+    #[test]
+    fn test_compression_ratio() {
+        let channels = [
+            channel(100, Some(400)),
+            channel(50, None),
+            channel(100, Some(200)),
+        ];
+        let stats = Stats::new(&rollup_config(), &[], &channels, DataUsage::default(), 50);
+        let s = &stats.summary;
+        assert_eq!((s.channels, s.decoded_channels), (3, 2));
+        assert_eq!((s.compressed_bytes, s.decompressed_bytes), (200, 600));
+        assert_eq!(s.compression_ratio, Some(3.0));
+        assert_eq!((s.single_batches, s.span_batches), (2, 2));
+        assert_eq!(stats.channels[0].compression_ratio, Some(4.0));
+        assert_eq!(stats.channels[0].l2_blocks, 3);
+        assert_eq!(stats.channels[1].compression_ratio, None);
+        assert_eq!(s.latency_blocks, None);
+    }
+
+    #[test]
+    fn test_compression_ratio_nothing_decoded() {
+        let channels = [channel(50, None), channel(30, None)];
+        let stats = Stats::new(&rollup_config(), &[], &channels, DataUsage::default(), 50);
+        let s = &stats.summary;
+        assert_eq!((s.channels, s.decoded_channels), (2, 0));
+        assert_eq!((s.compressed_bytes, s.decompressed_bytes), (0, 0));
+        assert_eq!(s.compression_ratio, None);
+        assert_eq!((s.single_batches, s.span_batches), (0, 0));
+        let row = format!("{:<29}  -\n", "Compression ratio");
+        assert!(stats.table().contains(&row), "{}", stats.table());
+
+        // Empty channels have no ratio either.
+        let stats = Stats::new(
+            &rollup_config(),
+            &[],
+            &[channel(0, Some(0))],
+            DataUsage::default(),
+            50,
+        );
+        assert_eq!(stats.summary.compression_ratio, None);
+        assert_eq!(stats.channels[0].compression_ratio, None);
+    }
+
+    fn signer(n: u8) -> PrivateKeySigner {
+        PrivateKeySigner::from_bytes(&B256::with_last_byte(n)).unwrap()
+    }
+
+    fn calldata_tx(signer: &PrivateKeySigner, to: Address, input: &[u8]) -> TxEnvelope {
+        let tx = TxEip1559 {
+            chain_id: 900,
+            to: TxKind::Call(to),
+            input: Bytes::copy_from_slice(input),
+            ..Default::default()
+        };
+        let signature = signer.sign_hash_sync(&tx.signature_hash()).unwrap();
+        tx.into_signed(signature).into()
+    }
+
+    fn blob_tx(signer: &PrivateKeySigner, blobs: u8) -> TxEnvelope {
+        let tx = TxEip4844 {
+            chain_id: 900,
+            to: INBOX,
+            blob_versioned_hashes: (0..blobs).map(|i| keccak256([i])).collect(),
+            ..Default::default()
+        };
+        let signature = signer.sign_hash_sync(&tx.signature_hash()).unwrap();
+        tx.into_signed(signature).into()
+    }
+
+    #[test]
+    fn test_data_usage() {
+        let cfg = rollup_config();
+        let (batcher, other) = (signer(1), signer(2));
+        let txs = [
+            calldata_tx(&batcher, INBOX, &[0; 10]),
+            // Transactions from other senders or to other addresses are not batcher data.
+            calldata_tx(&other, INBOX, &[0; 20]),
+            calldata_tx(
+                &batcher,
+                address!("0000000000000000000000000000000000000001"),
+                &[0; 30],
+            ),
+            blob_tx(&batcher, 2),
+            calldata_tx(&batcher, INBOX, &[0; 5]),
+        ];
+        let items = [
+            Bytes::from(vec![0; 10]),
+            Bytes::from(vec![0; 100]),
+            Bytes::from(vec![0; 50]),
+            Bytes::from(vec![0; 5]),
+        ];
+
+        let mut usage = DataUsage::default();
+        usage.add(&cfg, batcher.address(), &txs, &items);
+        assert_eq!(
+            usage,
+            DataUsage {
+                calldata_txs: 2,
+                calldata_bytes: 15,
+                blob_txs: 1,
+                blobs: 2,
+                blob_bytes: 150,
+            }
+        );
+
+        // Usage accumulates over L1 blocks.
+        usage.add(&cfg, batcher.address(), &txs[3..4], &items[1..3]);
+        assert_eq!((usage.blob_txs, usage.blobs, usage.blob_bytes), (2, 4, 300));
+        assert_eq!((usage.calldata_txs, usage.calldata_bytes), (2, 15));
+    }
+}
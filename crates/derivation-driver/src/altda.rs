@@ -0,0 +1,334 @@
+//! Alternative data availability (alt-DA), where the batcher posts commitments to L1
+//! and the batch data is served by a DA server.
+
+use alloy_primitives::{hex, keccak256, Address, Bytes, B256};
+use anyhow::{anyhow, bail, ensure, Result};
+use async_trait::async_trait;
+use kona_derive::{
+    errors::{StageError, StageResult},
+    traits::{AsyncIterator, DataAvailabilityProvider},
+};
+use kona_primitives::BlockInfo;
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::sync::{Arc, Mutex};
+use tracing::{debug, warn};
+
+/// The logging target to use for [tracing].
+const TARGET: &str = "altda";
+
+/// The version byte of L1 transaction data that carries an alt-DA commitment rather
+/// than frames.
+pub const TX_DATA_VERSION_1: u8 = 0x01;
+
+/// The type byte of a [AltDaCommitment::Keccak256] commitment.
+const KECCAK256_COMMITMENT_TYPE: u8 = 0x00;
+
+/// The type byte of a [AltDaCommitment::Generic] commitment.
+const GENERIC_COMMITMENT_TYPE: u8 = 0x01;
+
+/// A commitment to an input served by a DA server.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum AltDaCommitment {
+    /// The keccak256 hash of the input, which the input is checked against.
+    Keccak256(B256),
+    /// A commitment specific to the DA layer, which is taken on trust.
+    Generic(Bytes),
+}
+
+impl AltDaCommitment {
+    /// Decodes a commitment from its type byte followed by its payload.
+    pub fn decode(data: &[u8]) -> Result<Self> {
+        match data.split_first() {
+            Some((&KECCAK256_COMMITMENT_TYPE, hash)) => {
+                ensure!(
+                    hash.len() == 32,
+                    "Invalid keccak256 commitment length {}",
+                    hash.len()
+                );
+                Ok(Self::Keccak256(B256::from_slice(hash)))
+            }
+            Some((&GENERIC_COMMITMENT_TYPE, payload)) => {
+                ensure!(!payload.is_empty(), "Empty generic commitment");
+                Ok(Self::Generic(Bytes::copy_from_slice(payload)))
+            }
+            Some((ty, _)) => bail!("Unknown commitment type {ty}"),
+            None => bail!("Empty commitment"),
+        }
+    }
+
+    /// Encodes the commitment as its type byte followed by its payload. This is the
+    /// form the DA server looks inputs up by.
+    pub fn encode(&self) -> Bytes {
+        let (ty, payload) = match self {
+            Self::Keccak256(hash) => (KECCAK256_COMMITMENT_TYPE, hash.as_slice()),
+            Self::Generic(payload) => (GENERIC_COMMITMENT_TYPE, payload.as_ref()),
+        };
+        [&[ty][..], payload].concat().into()
+    }
+
+    /// Checks that the input matches the commitment. Generic commitments cannot be
+    /// checked, and accept any input.
+    pub fn verify(&self, input: &[u8]) -> Result<()> {
+        match self {
+            Self::Keccak256(hash) => ensure!(
+                keccak256(input) == *hash,
+                "Input does not match keccak256 commitment {hash}"
+            ),
+            Self::Generic(_) => {}
+        }
+        Ok(())
+    }
+}
+
+/// Fetches the inputs that alt-DA commitments refer to.
+#[async_trait]
+pub trait InputFetcher {
+    /// Returns the input for the given commitment.
+    async fn get_input(&mut self, commitment: &AltDaCommitment) -> Result<Bytes>;
+}
+
+/// An [InputFetcher] backed by the HTTP API of a DA server.
+#[derive(Debug, Clone)]
+pub struct DaServerClient {
+    /// The base URL of the DA server.
+    url: String,
+    /// The HTTP client.
+    client: reqwest::Client,
+}
+
+impl DaServerClient {
+    /// Creates a new [DaServerClient] for the DA server at the given URL.
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into().trim_end_matches('/').to_string(),
+            client: reqwest::Client::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl InputFetcher for DaServerClient {
+    async fn get_input(&mut self, commitment: &AltDaCommitment) -> Result<Bytes> {
+        let encoded = commitment.encode();
+        let url = format!("{}/get/{}", self.url, hex::encode_prefixed(&encoded));
+        let response = self.client.get(&url).send().await?;
+        let status = response.status();
+        if status == reqwest::StatusCode::NOT_FOUND {
+            bail!("Input {encoded} not found on the DA server");
+        }
+        ensure!(
+            status.is_success(),
+            "DA server returned {status} for input {encoded}"
+        );
+        let input = response.bytes().await?;
+        commitment.verify(&input)?;
+        Ok(input.into())
+    }
+}
+
+/// Wraps an [InputFetcher], recording every input it fetches.
+///
+/// Clones share the same recording, so the inputs fetched through every data iterator
+/// end up in one place.
+#[derive(Debug, Clone)]
+pub struct RecordingInputFetcher<F> {
+    /// The wrapped fetcher.
+    inner: F,
+    /// The inputs recorded so far, keyed by their encoded commitment.
+    inputs: Arc<Mutex<HashMap<Bytes, Bytes>>>,
+}
+
+impl<F> RecordingInputFetcher<F> {
+    /// Creates a new [RecordingInputFetcher] around the given fetcher.
+    pub fn new(inner: F) -> Self {
+        Self {
+            inner,
+            inputs: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Returns the inputs recorded since the last call, keyed by their encoded
+    /// commitment, and clears them.
+    pub fn take(&self) -> HashMap<Bytes, Bytes> {
+        std::mem::take(&mut *self.inputs.lock().expect("recording lock poisoned"))
+    }
+}
+
+#[async_trait]
+impl<F: InputFetcher + Send + Sync> InputFetcher for RecordingInputFetcher<F> {
+    async fn get_input(&mut self, commitment: &AltDaCommitment) -> Result<Bytes> {
+        let input = self.inner.get_input(commitment).await?;
+        self.inputs
+            .lock()
+            .expect("recording lock poisoned")
+            .insert(commitment.encode(), input.clone());
+        Ok(input)
+    }
+}
+
+/// A data source for alt-DA chains, which resolves the commitments posted to L1
+/// through an [InputFetcher].
+///
+/// Data read by the wrapped source that starts with [TX_DATA_VERSION_1] is replaced by
+/// the input its commitment refers to. Other data is passed through unchanged, and
+/// commitments that cannot be decoded are skipped, as in the op-node.
+#[derive(Debug, Clone)]
+pub struct AltDaSource<D, F> {
+    /// The data source reading the commitments from L1.
+    inner: D,
+    /// The fetcher resolving the commitments.
+    fetcher: F,
+}
+
+impl<D, F> AltDaSource<D, F> {
+    /// Creates a new [AltDaSource] over the given data source.
+    pub fn new(inner: D, fetcher: F) -> Self {
+        Self { inner, fetcher }
+    }
+}
+
+#[async_trait]
+impl<D, F> DataAvailabilityProvider for AltDaSource<D, F>
+where
+    D: DataAvailabilityProvider + Send + Sync,
+    F: InputFetcher + Clone + Debug + Send + Sync,
+{
+    type Item = Bytes;
+    type DataIter = AltDaDataIter<D::DataIter, F>;
+
+    async fn open_data(
+        &self,
+        block_ref: &BlockInfo,
+        batcher_address: Address,
+    ) -> Result<Self::DataIter> {
+        Ok(AltDaDataIter {
+            inner: self.inner.open_data(block_ref, batcher_address).await?,
+            fetcher: self.fetcher.clone(),
+            pending: None,
+        })
+    }
+}
+
+/// The data iterator of an [AltDaSource].
+#[derive(Debug)]
+pub struct AltDaDataIter<I, F> {
+    /// The data iterator of the wrapped source.
+    inner: I,
+    /// The fetcher resolving the commitments.
+    fetcher: F,
+    /// The commitment whose input could not be fetched yet. It is retried on the next
+    /// call, so that a failed fetch does not drop it.
+    pending: Option<AltDaCommitment>,
+}
+
+#[async_trait]
+impl<I, F> AsyncIterator for AltDaDataIter<I, F>
+where
+    I: AsyncIterator + Send,
+    F: InputFetcher + Debug + Send + Sync,
+{
+    type Item = Bytes;
+
+    async fn next(&mut self) -> StageResult<Self::Item> {
+        let commitment = match self.pending.take() {
+            Some(commitment) => commitment,
+            None => loop {
+                let data: Bytes = self.inner.next().await?.into();
+                if data.first() != Some(&TX_DATA_VERSION_1) {
+                    return Ok(data);
+                }
+                match AltDaCommitment::decode(&data[1..]) {
+                    Ok(commitment) => break commitment,
+                    Err(e) => warn!(target: TARGET, "Skipping invalid alt-DA commitment: {e}"),
+                }
+            },
+        };
+        match self.fetcher.get_input(&commitment).await {
+            Ok(input) => {
+                debug!(target: TARGET, "Resolved alt-DA commitment {}", commitment.encode());
+                Ok(input)
+            }
+            Err(e) => {
+                self.pending = Some(commitment.clone());
+                Err(StageError::Custom(anyhow!(
+                    "Failed to fetch alt-DA input {}: {e}",
+                    commitment.encode()
+                )))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{extract::Path, http::StatusCode, routing::get, Router};
+    use tokio::net::TcpListener;
+
+    /// Serves the given inputs like a DA server, keyed by hex encoded commitment.
+    async fn mock_da_server(inputs: HashMap<String, Vec<u8>>) -> String {
+        let app = Router::new().route(
+            "/get/:commitment",
+            get(move |Path(commitment): Path<String>| {
+                let input = inputs.get(&commitment).cloned();
+                async move { input.ok_or(StatusCode::NOT_FOUND) }
+            }),
+        );
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        format!("http://{addr}")
+    }
+
+    #[test]
+    fn test_commitment_roundtrip() {
+        let keccak = AltDaCommitment::Keccak256(keccak256(b"input"));
+        let encoded = keccak.encode();
+        assert_eq!(encoded.len(), 33);
+        assert_eq!(encoded[0], KECCAK256_COMMITMENT_TYPE);
+        assert_eq!(AltDaCommitment::decode(&encoded).unwrap(), keccak);
+
+        let generic = AltDaCommitment::Generic(Bytes::from_static(b"da-layer-ref"));
+        assert_eq!(AltDaCommitment::decode(&generic.encode()).unwrap(), generic);
+
+        assert!(AltDaCommitment::decode(&[]).is_err());
+        assert!(AltDaCommitment::decode(&[KECCAK256_COMMITMENT_TYPE, 1, 2]).is_err());
+        assert!(AltDaCommitment::decode(&[0x02, 1, 2]).is_err());
+    }
+
+    #[test]
+    fn test_commitment_verify() {
+        let keccak = AltDaCommitment::Keccak256(keccak256(b"input"));
+        assert!(keccak.verify(b"input").is_ok());
+        assert!(keccak.verify(b"other").is_err());
+
+        let generic = AltDaCommitment::Generic(Bytes::from_static(b"ref"));
+        assert!(generic.verify(b"anything").is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_da_server_client() {
+        let good = AltDaCommitment::Keccak256(keccak256(b"frames"));
+        let bad = AltDaCommitment::Keccak256(keccak256(b"expected"));
+        let missing = AltDaCommitment::Keccak256(keccak256(b"missing"));
+        let inputs = HashMap::from([
+            (hex::encode_prefixed(good.encode()), b"frames".to_vec()),
+            (hex::encode_prefixed(bad.encode()), b"tampered".to_vec()),
+        ]);
+        let url = mock_da_server(inputs).await;
+
+        let mut client = RecordingInputFetcher::new(DaServerClient::new(url));
+        assert_eq!(
+            client.get_input(&good).await.unwrap(),
+            Bytes::from_static(b"frames")
+        );
+        assert!(client.get_input(&bad).await.is_err());
+        assert!(client.get_input(&missing).await.is_err());
+
+        let recorded = client.take();
+        assert_eq!(recorded.len(), 1);
+        assert_eq!(recorded[&good.encode()], Bytes::from_static(b"frames"));
+        assert!(client.take().is_empty());
+    }
+}
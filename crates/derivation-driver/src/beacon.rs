@@ -0,0 +1,190 @@
+//! A beacon client that spreads requests over several beacon endpoints.
+
+use async_trait::async_trait;
+use futures::Future;
+use kona_derive::online::{BeaconClient, OnlineBeaconClient};
+use kona_primitives::{APIBlobSidecar, APIConfigResponse, APIGenesisResponse, IndexedBlobHash};
+use std::sync::{Arc, Mutex};
+use tracing::warn;
+
+/// The logging target to use for [tracing].
+const TARGET: &str = "beacon";
+
+/// A beacon endpoint along with its URL, used in logs.
+#[derive(Debug, Clone)]
+struct Endpoint<B> {
+    /// The URL of the endpoint.
+    url: String,
+    /// The client of the endpoint.
+    client: B,
+}
+
+/// Sends beacon requests to the healthiest of several beacon endpoints.
+///
+/// An endpoint's health is its number of consecutive failed requests. Each request
+/// goes to the endpoints in order of health, starting with the one that failed the
+/// least, and moves on to the next endpoint when one fails. Endpoints that fail the
+/// same number of times are tried in the order they were given in. Clones share the
+/// same health, so every stage of the pipeline avoids an unhealthy endpoint.
+#[derive(Debug, Clone)]
+pub struct BeaconPool<B = OnlineBeaconClient> {
+    /// The beacon endpoints.
+    endpoints: Vec<Endpoint<B>>,
+    /// The number of consecutive failures of each endpoint.
+    failures: Arc<Mutex<Vec<u32>>>,
+}
+
+impl BeaconPool {
+    /// Creates a [BeaconPool] over HTTP beacon endpoints.
+    pub fn new_http(urls: Vec<String>) -> Self {
+        Self::new(
+            urls.into_iter()
+                .map(|url| (url.clone(), OnlineBeaconClient::new_http(url)))
+                .collect(),
+        )
+    }
+}
+
+impl<B> BeaconPool<B> {
+    /// Creates a [BeaconPool] over the given endpoints, identified by their URLs.
+    pub fn new(endpoints: Vec<(String, B)>) -> Self {
+        let failures = Arc::new(Mutex::new(vec![0; endpoints.len()]));
+        let endpoints = endpoints
+            .into_iter()
+            .map(|(url, client)| Endpoint { url, client })
+            .collect();
+        Self {
+            endpoints,
+            failures,
+        }
+    }
+
+    /// Returns the indices of the endpoints, healthiest first.
+    fn order(&self) -> Vec<usize> {
+        let failures = self.failures.lock().expect("health lock poisoned");
+        let mut order = (0..failures.len()).collect::<Vec<_>>();
+        order.sort_by_key(|i| failures[*i]);
+        order
+    }
+
+    /// Records the outcome of a request to the endpoint at the given index.
+    fn record(&self, index: usize, ok: bool) {
+        let mut failures = self.failures.lock().expect("health lock poisoned");
+        failures[index] = if ok {
+            0
+        } else {
+            failures[index].saturating_add(1)
+        };
+    }
+
+    /// Sends the request to the endpoints in order of health until one succeeds.
+    /// Returns the error of the last endpoint if they all fail.
+    async fn request<'a, T, F, Fut>(&'a self, what: &str, request: F) -> anyhow::Result<T>
+    where
+        F: Fn(&'a B) -> Fut,
+        Fut: Future<Output = anyhow::Result<T>>,
+    {
+        let mut last_error = None;
+        for index in self.order() {
+            let endpoint = &self.endpoints[index];
+            match request(&endpoint.client).await {
+                Ok(value) => {
+                    self.record(index, true);
+                    return Ok(value);
+                }
+                Err(e) => {
+                    warn!(target: TARGET, "Failed to fetch {what} from beacon endpoint {}: {e}", endpoint.url);
+                    self.record(index, false);
+                    last_error = Some(e);
+                }
+            }
+        }
+        Err(last_error.unwrap_or_else(|| anyhow::anyhow!("No beacon endpoints configured")))
+    }
+}
+
+#[async_trait]
+impl<B: BeaconClient + Send + Sync> BeaconClient for BeaconPool<B> {
+    async fn config_spec(&self) -> anyhow::Result<APIConfigResponse> {
+        self.request("config spec", |client| client.config_spec())
+            .await
+    }
+
+    async fn beacon_genesis(&self) -> anyhow::Result<APIGenesisResponse> {
+        self.request("beacon genesis", |client| client.beacon_genesis())
+            .await
+    }
+
+    async fn beacon_blob_side_cars(
+        &self,
+        slot: u64,
+        hashes: &[IndexedBlobHash],
+    ) -> anyhow::Result<Vec<APIBlobSidecar>> {
+        self.request("blob sidecars", |client| {
+            client.beacon_blob_side_cars(slot, hashes)
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    /// A client that fails a fixed number of times before succeeding.
+    #[derive(Debug, Default)]
+    struct Flaky {
+        failures_left: AtomicU32,
+        calls: AtomicU32,
+    }
+
+    impl Flaky {
+        fn new(failures: u32) -> Self {
+            Self {
+                failures_left: AtomicU32::new(failures),
+                calls: AtomicU32::new(0),
+            }
+        }
+
+        async fn call(&self) -> anyhow::Result<u32> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            let left = self.failures_left.load(Ordering::SeqCst);
+            if left > 0 {
+                self.failures_left.store(left - 1, Ordering::SeqCst);
+                anyhow::bail!("unavailable");
+            }
+            Ok(self.calls.load(Ordering::SeqCst))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fails_over_to_healthy_endpoint() {
+        let pool = BeaconPool::new(vec![
+            ("a".to_string(), Flaky::new(u32::MAX)),
+            ("b".to_string(), Flaky::new(0)),
+        ]);
+
+        pool.request("value", |c| c.call()).await.unwrap();
+        assert_eq!(pool.order(), vec![1, 0]);
+
+        // The failing endpoint is no longer tried first.
+        pool.request("value", |c| c.call()).await.unwrap();
+        assert_eq!(pool.endpoints[0].client.calls.load(Ordering::SeqCst), 1);
+        assert_eq!(pool.endpoints[1].client.calls.load(Ordering::SeqCst), 2);
+        assert_eq!(pool.order(), vec![1, 0]);
+    }
+
+    #[tokio::test]
+    async fn test_all_endpoints_fail() {
+        let pool = BeaconPool::new(vec![
+            ("a".to_string(), Flaky::new(u32::MAX)),
+            ("b".to_string(), Flaky::new(u32::MAX)),
+        ]);
+        assert!(pool.request("value", |c| c.call()).await.is_err());
+        assert_eq!(pool.order(), vec![0, 1]);
+
+        let empty = BeaconPool::<Flaky>::new(vec![]);
+        assert!(empty.request("value", |c| c.call()).await.is_err());
+    }
+}
@@ -0,0 +1,445 @@
+//! Assembly of the frames posted by the batcher into channels, and decoding of the
+//! batches they carry.
+
+use alloy_consensus::{Transaction, TxEnvelope};
+use alloy_primitives::{Address, Bytes, FixedBytes, TxKind, B256};
+use color_eyre::{
+    eyre::{bail, ensure, eyre},
+    Result,
+};
+use kona_derive::batch::Batch;
+use kona_primitives::{Frame, RollupConfig};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::io::Read;
+
+/// The version byte of brotli compressed channels, introduced by Fjord.
+const CHANNEL_VERSION_BROTLI: u8 = 0x01;
+
+/// The maximum size of a decompressed channel, as of Fjord.
+const MAX_RLP_BYTES_PER_CHANNEL: usize = 100_000_000;
+
+/// A frame, with the L1 transaction that posted it.
+#[derive(Debug, Clone)]
+pub struct PostedFrame {
+    /// The number of the L1 block the frame was posted in.
+    pub l1_block: u64,
+    /// The hash of the transaction that posted the frame.
+    pub tx_hash: B256,
+    /// The frame.
+    pub frame: Frame,
+}
+
+/// A channel, assembled from its frames.
+///
+/// Frames are assembled in the order they appear on L1, without applying the
+/// channel timeout, so that channels the pipeline would drop can still be inspected.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Channel {
+    /// The channel id.
+    pub id: FixedBytes<16>,
+    /// The L1 block carrying the first frame of the channel.
+    pub opened_in: u64,
+    /// The L1 block carrying the last frame of the channel, if it is complete.
+    pub closed_in: Option<u64>,
+    /// The numbers of the frames received, in L1 order.
+    pub frames: Vec<u16>,
+    /// The transactions that posted the frames, in L1 order.
+    pub txs: Vec<B256>,
+    /// The L1 blocks the frames were posted in, in L1 order.
+    pub l1_blocks: Vec<u64>,
+    /// The size of the compressed channel data.
+    pub compressed_size: usize,
+    /// The size of the decompressed channel data, if it could be decompressed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub decompressed_size: Option<usize>,
+    /// The batches in the channel.
+    pub batches: Vec<BatchInfo>,
+    /// Why the channel could not be decoded, or why decoding stopped early.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// A batch decoded from a channel.
+#[derive(Serialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum BatchInfo {
+    /// A singular batch, producing a single L2 block.
+    #[serde(rename_all = "camelCase")]
+    Single {
+        /// The hash of the parent L2 block.
+        parent_hash: B256,
+        /// The L2 block produced by the batch.
+        block: L2BlockRef,
+    },
+    /// A span batch, producing a range of L2 blocks.
+    #[serde(rename_all = "camelCase")]
+    Span {
+        /// The first 20 bytes of the hash of the parent L2 block.
+        parent_check: FixedBytes<20>,
+        /// The first 20 bytes of the hash of the L1 origin of the last L2 block.
+        l1_origin_check: FixedBytes<20>,
+        /// The L2 blocks produced by the batch.
+        blocks: Vec<L2BlockRef>,
+    },
+}
+
+impl BatchInfo {
+    /// Returns the L2 blocks produced by the batch.
+    pub fn blocks(&self) -> &[L2BlockRef] {
+        match self {
+            Self::Single { block, .. } => std::slice::from_ref(block),
+            Self::Span { blocks, .. } => blocks,
+        }
+    }
+
+    /// Returns whether the batch is a span batch.
+    pub fn is_span(&self) -> bool {
+        matches!(self, Self::Span { .. })
+    }
+}
+
+/// An L2 block produced by a batch.
+#[derive(Serialize, Debug, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub struct L2BlockRef {
+    /// The L2 block number, derived from the timestamp. Missing if the timestamp is
+    /// before the L2 genesis or not on a block boundary.
+    pub number: Option<u64>,
+    /// The L2 block timestamp.
+    pub timestamp: u64,
+    /// The number of the L1 origin of the block.
+    pub epoch_num: u64,
+    /// The number of transactions in the block, excluding deposits.
+    pub tx_count: usize,
+}
+
+impl Display for L2BlockRef {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.number {
+            Some(number) => write!(f, "L2 block {number}")?,
+            None => write!(f, "L2 block ?")?,
+        }
+        write!(
+            f,
+            " (timestamp {}, epoch {}, {} txs)",
+            self.timestamp, self.epoch_num, self.tx_count
+        )
+    }
+}
+
+/// Returns the frames in the data items read from an L1 block, attributed to the
+/// batcher transactions of the block.
+///
+/// The data source yields one item per calldata batcher transaction and one item per
+/// blob of a blob batcher transaction, in transaction order, which is how the items
+/// are matched to the transactions.
+pub fn posted_frames(
+    cfg: &RollupConfig,
+    batcher: Address,
+    l1_block: u64,
+    txs: &[TxEnvelope],
+    items: &[Bytes],
+) -> Result<Vec<PostedFrame>> {
+    let mut tx_hashes = Vec::with_capacity(items.len());
+    for tx in batcher_txs(cfg, batcher, txs) {
+        tx_hashes.extend(std::iter::repeat(*tx.tx_hash()).take(data_items(tx)));
+    }
+    ensure!(
+        tx_hashes.len() == items.len(),
+        "L1 block {l1_block} has {} batcher data items, but {} were read",
+        tx_hashes.len(),
+        items.len()
+    );
+
+    let mut frames = Vec::new();
+    for (tx_hash, item) in tx_hashes.into_iter().zip(items) {
+        frames.extend(
+            Frame::parse_frames(item)
+                .map_err(|e| eyre!(e))?
+                .into_iter()
+                .map(|frame| PostedFrame {
+                    l1_block,
+                    tx_hash,
+                    frame,
+                }),
+        );
+    }
+    Ok(frames)
+}
+
+/// Returns the transactions the batcher sent to the batch inbox, in transaction order.
+pub fn batcher_txs<'a>(
+    cfg: &RollupConfig,
+    batcher: Address,
+    txs: &'a [TxEnvelope],
+) -> impl Iterator<Item = &'a TxEnvelope> + 'a {
+    let inbox = TxKind::Call(cfg.batch_inbox_address);
+    txs.iter()
+        .filter(move |tx| tx.to() == inbox && tx.recover_signer().ok() == Some(batcher))
+}
+
+/// Returns the number of data items the data source yields for a batcher transaction,
+/// which is its number of blobs for a blob transaction and one otherwise.
+pub fn data_items(tx: &TxEnvelope) -> usize {
+    match tx {
+        TxEnvelope::Eip4844(signed) => signed.tx().tx().blob_versioned_hashes.len(),
+        _ => 1,
+    }
+}
+
+/// Groups the frames by channel, and decodes the batches of every complete channel.
+pub fn assemble_channels(
+    cfg: &RollupConfig,
+    frames: impl IntoIterator<Item = PostedFrame>,
+) -> Vec<Channel> {
+    // Each channel with its frame data by number, and the number of its last frame.
+    let mut channels: Vec<(Channel, HashMap<u16, Vec<u8>>, Option<u16>)> = Vec::new();
+    for PostedFrame {
+        l1_block,
+        tx_hash,
+        frame,
+    } in frames
+    {
+        let id = FixedBytes::from(frame.id);
+        let index = match channels.iter().position(|(channel, ..)| channel.id == id) {
+            Some(index) => index,
+            None => {
+                channels.push((
+                    Channel {
+                        id,
+                        opened_in: l1_block,
+                        closed_in: None,
+                        frames: vec![],
+                        txs: vec![],
+                        l1_blocks: vec![],
+                        compressed_size: 0,
+                        decompressed_size: None,
+                        batches: vec![],
+                        error: None,
+                    },
+                    HashMap::new(),
+                    None,
+                ));
+                channels.len() - 1
+            }
+        };
+        let (channel, data, last) = &mut channels[index];
+        channel.frames.push(frame.number);
+        if !channel.txs.contains(&tx_hash) {
+            channel.txs.push(tx_hash);
+        }
+        if channel.l1_blocks.last() != Some(&l1_block) {
+            channel.l1_blocks.push(l1_block);
+        }
+        if frame.is_last && last.is_none() {
+            channel.closed_in = Some(l1_block);
+            *last = Some(frame.number);
+        }
+        // As in the channel bank, a repeated frame number is ignored.
+        data.entry(frame.number).or_insert(frame.data);
+    }
+
+    channels
+        .into_iter()
+        .map(|(mut channel, data, last)| {
+            match last {
+                Some(last) if (0..=last).all(|n| data.contains_key(&n)) => {
+                    let compressed = (0..=last)
+                        .flat_map(|n| data[&n].iter().copied())
+                        .collect::<Vec<_>>();
+                    channel.compressed_size = compressed.len();
+                    if let Err(e) = decode_channel(cfg, &compressed, &mut channel) {
+                        channel.error = Some(e.to_string());
+                    }
+                }
+                Some(_) => channel.error = Some("Channel is missing frames".to_string()),
+                None => channel.error = Some("Channel is not closed".to_string()),
+            }
+            channel
+        })
+        .collect()
+}
+
+/// Decompresses the channel data, and decodes its batches into the channel.
+fn decode_channel(cfg: &RollupConfig, compressed: &[u8], channel: &mut Channel) -> Result<()> {
+    let data = match compressed.first() {
+        // The low nibble of a zlib header is the compression method, 8 or 15.
+        Some(byte) if byte & 0x0f == 8 || byte & 0x0f == 15 => {
+            miniz_oxide::inflate::decompress_to_vec_zlib_with_limit(
+                compressed,
+                MAX_RLP_BYTES_PER_CHANNEL,
+            )
+            .map_err(|e| eyre!("Failed to decompress channel: {e:?}"))?
+        }
+        Some(&CHANNEL_VERSION_BROTLI) => {
+            let mut data = Vec::new();
+            brotli::Decompressor::new(&compressed[1..], 4096)
+                .take(MAX_RLP_BYTES_PER_CHANNEL as u64 + 1)
+                .read_to_end(&mut data)
+                .map_err(|e| eyre!("Failed to decompress channel: {e}"))?;
+            ensure!(
+                data.len() <= MAX_RLP_BYTES_PER_CHANNEL,
+                "Decompressed channel exceeds {MAX_RLP_BYTES_PER_CHANNEL} bytes"
+            );
+            data
+        }
+        Some(byte) => bail!("Unknown channel compression {byte:#04x}"),
+        None => bail!("Empty channel"),
+    };
+    channel.decompressed_size = Some(data.len());
+
+    let mut reader = data.as_slice();
+    while !reader.is_empty() {
+        let encoded = alloy_rlp::Header::decode_bytes(&mut reader, false)
+            .map_err(|e| eyre!("Failed to decode batch: {e}"))?;
+        let batch = Batch::decode(&mut &encoded[..], cfg)
+            .map_err(|e| eyre!("Failed to decode batch: {e:?}"))?;
+        channel.batches.push(match batch {
+            Batch::Single(batch) => BatchInfo::Single {
+                parent_hash: batch.parent_hash,
+                block: block_ref(
+                    cfg,
+                    batch.timestamp,
+                    batch.epoch_num,
+                    batch.transactions.len(),
+                ),
+            },
+            Batch::Span(batch) => BatchInfo::Span {
+                parent_check: batch.parent_check,
+                l1_origin_check: batch.l1_origin_check,
+                blocks: batch
+                    .batches
+                    .iter()
+                    .map(|b| block_ref(cfg, b.timestamp, b.epoch_num, b.transactions.len()))
+                    .collect(),
+            },
+        });
+    }
+    Ok(())
+}
+
+/// Returns the [L2BlockRef] of a block in a batch.
+fn block_ref(cfg: &RollupConfig, timestamp: u64, epoch_num: u64, tx_count: usize) -> L2BlockRef {
+    let number = timestamp
+        .checked_sub(cfg.genesis.l2_time)
+        .filter(|elapsed| cfg.block_time > 0 && elapsed % cfg.block_time == 0)
+        .map(|elapsed| cfg.genesis.l2.number + elapsed / cfg.block_time);
+    L2BlockRef {
+        number,
+        timestamp,
+        epoch_num,
+        tx_count,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_rlp::Encodable;
+    use kona_derive::batch::SingleBatch;
+    use std::io::Write;
+
+    /// Compresses channel data with zlib.
+    fn zlib(data: &[u8]) -> Vec<u8> {
+        miniz_oxide::deflate::compress_to_vec_zlib(data, 9)
+    }
+
+    /// Compresses channel data with brotli, prefixed by the brotli channel version.
+    fn brotli(data: &[u8]) -> Vec<u8> {
+        let mut writer = brotli::CompressorWriter::new(vec![CHANNEL_VERSION_BROTLI], 4096, 11, 22);
+        writer.write_all(data).unwrap();
+        writer.into_inner()
+    }
+
+    /// Returns the frames of a channel holding the given batches, compressed with the
+    /// given function and split into frames of at most `chunk` bytes of data.
+    fn channel_frames(
+        id: [u8; 16],
+        batches: &[SingleBatch],
+        compress: fn(&[u8]) -> Vec<u8>,
+        chunk: usize,
+    ) -> Vec<PostedFrame> {
+        let mut data = Vec::new();
+        for batch in batches {
+            let mut encoded = vec![0u8];
+            batch.encode(&mut encoded);
+            encoded.as_slice().encode(&mut data);
+        }
+        let compressed = compress(&data);
+        let chunks = compressed.chunks(chunk).collect::<Vec<_>>();
+        chunks
+            .iter()
+            .enumerate()
+            .map(|(i, chunk)| PostedFrame {
+                l1_block: 100 + i as u64,
+                tx_hash: B256::with_last_byte(i as u8),
+                frame: Frame {
+                    id,
+                    number: i as u16,
+                    data: chunk.to_vec(),
+                    is_last: i + 1 == chunks.len(),
+                },
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_assemble_channels() {
+        let mut cfg = RollupConfig {
+            block_time: 2,
+            ..Default::default()
+        };
+        cfg.genesis.l2.number = 10;
+        cfg.genesis.l2_time = 1000;
+        let batches = [1004, 1006].map(|timestamp| SingleBatch {
+            epoch_num: 7,
+            timestamp,
+            ..Default::default()
+        });
+
+        let frames = channel_frames([1; 16], &batches, zlib, 16);
+        assert!(frames.len() > 1);
+        let channels = assemble_channels(&cfg, frames.clone());
+        assert_eq!(channels.len(), 1);
+        let channel = &channels[0];
+        assert_eq!(channel.error, None);
+        assert_eq!(channel.opened_in, 100);
+        assert_eq!(channel.closed_in, Some(100 + frames.len() as u64 - 1));
+        assert_eq!(channel.txs.len(), frames.len());
+        assert_eq!(channel.l1_blocks.len(), frames.len());
+        let blocks = channel
+            .batches
+            .iter()
+            .flat_map(|b| b.blocks().iter().map(|block| block.number))
+            .collect::<Vec<_>>();
+        assert_eq!(blocks, vec![Some(12), Some(13)]);
+        assert!(!channel.batches[0].is_span());
+
+        // A brotli compressed channel decodes to the same batches.
+        let brotli_frames = channel_frames([2; 16], &batches, brotli, 16);
+        let brotli_channels = assemble_channels(&cfg, brotli_frames);
+        assert_eq!(brotli_channels.len(), 1);
+        let brotli_channel = &brotli_channels[0];
+        assert_eq!(brotli_channel.error, None);
+        assert_eq!(brotli_channel.decompressed_size, channel.decompressed_size);
+        let brotli_blocks = brotli_channel
+            .batches
+            .iter()
+            .flat_map(|b| b.blocks().iter().map(|block| block.number))
+            .collect::<Vec<_>>();
+        assert_eq!(brotli_blocks, blocks);
+
+        // A channel missing a frame is reported, without batches.
+        let mut missing = frames;
+        missing.remove(0);
+        let channels = assemble_channels(&cfg, missing);
+        assert_eq!(
+            channels[0].error.as_deref(),
+            Some("Channel is missing frames")
+        );
+        assert!(channels[0].batches.is_empty());
+    }
+}
@@ -0,0 +1,259 @@
+//! Concurrent, rate-limited fetching with retries.
+
+use clap::Args;
+use color_eyre::Result;
+use futures::{stream, Future, StreamExt, TryStreamExt};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::{sync::Mutex, time::Instant};
+use tracing::warn;
+
+/// The logging target to use for [tracing].
+const TARGET: &str = "fetch";
+
+/// CLI arguments that control how blocks are fetched.
+#[derive(Args, Clone, Debug)]
+pub struct FetchArgs {
+    /// The maximum number of blocks fetched at the same time.
+    #[arg(
+        long,
+        default_value_t = 16,
+        help = "Maximum number of blocks fetched at the same time"
+    )]
+    pub concurrency: usize,
+    /// The maximum number of requests sent per second. Unlimited if unset.
+    #[arg(long, help = "Maximum number of requests sent per second")]
+    pub rate_limit: Option<u32>,
+    /// The maximum number of attempts made for each request.
+    #[arg(
+        long,
+        default_value_t = 5,
+        help = "Maximum number of attempts made for each request"
+    )]
+    pub max_attempts: u32,
+}
+
+impl Default for FetchArgs {
+    fn default() -> Self {
+        Self {
+            concurrency: 16,
+            rate_limit: None,
+            max_attempts: 5,
+        }
+    }
+}
+
+impl FetchArgs {
+    /// Returns the [Fetcher] configured by the arguments.
+    pub fn fetcher(&self) -> Fetcher {
+        Fetcher {
+            concurrency: self.concurrency.max(1),
+            limiter: self.rate_limit.map(RateLimiter::per_second),
+            retry: RetryPolicy {
+                max_attempts: self.max_attempts.max(1),
+                ..Default::default()
+            },
+        }
+    }
+}
+
+/// Retries failed requests with exponential backoff.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// The maximum number of attempts, including the first one.
+    pub max_attempts: u32,
+    /// The delay before the first retry.
+    pub initial_backoff: Duration,
+    /// The maximum delay between two attempts.
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(10),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Returns the delay after the given failed attempt, counting from 1.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 1u32
+            .checked_shl(attempt.saturating_sub(1))
+            .unwrap_or(u32::MAX);
+        self.initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff)
+    }
+}
+
+/// Spaces requests out evenly so that at most a fixed number are sent per second.
+#[derive(Debug, Clone)]
+pub struct RateLimiter {
+    /// The minimum time between two requests.
+    interval: Duration,
+    /// The earliest time the next request may be sent.
+    next: Arc<Mutex<Instant>>,
+}
+
+impl RateLimiter {
+    /// Creates a [RateLimiter] that allows the given number of requests per second.
+    pub fn per_second(requests: u32) -> Self {
+        Self {
+            interval: Duration::from_secs(1) / requests.max(1),
+            next: Arc::new(Mutex::new(Instant::now())),
+        }
+    }
+
+    /// Waits until the next request may be sent.
+    pub async fn acquire(&self) {
+        let slot = {
+            let mut next = self.next.lock().await;
+            let slot = (*next).max(Instant::now());
+            *next = slot + self.interval;
+            slot
+        };
+        tokio::time::sleep_until(slot).await;
+    }
+}
+
+/// Fetches items concurrently, rate limiting and retrying every request.
+#[derive(Debug, Clone)]
+pub struct Fetcher {
+    /// The maximum number of items fetched at the same time.
+    concurrency: usize,
+    /// The rate limiter shared by every request, if any.
+    limiter: Option<RateLimiter>,
+    /// The retry policy applied to every request.
+    retry: RetryPolicy,
+}
+
+impl Default for Fetcher {
+    fn default() -> Self {
+        FetchArgs::default().fetcher()
+    }
+}
+
+impl Fetcher {
+    /// Creates a new [Fetcher].
+    pub fn new(concurrency: usize, limiter: Option<RateLimiter>, retry: RetryPolicy) -> Self {
+        Self {
+            concurrency: concurrency.max(1),
+            limiter,
+            retry,
+        }
+    }
+
+    /// Sends a single request, retrying it with exponential backoff until it succeeds
+    /// or the attempts run out. Every attempt is rate limited.
+    pub async fn call<T, F, Fut>(&self, what: &str, mut request: F) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let mut attempt = 1;
+        loop {
+            if let Some(limiter) = &self.limiter {
+                limiter.acquire().await;
+            }
+            match request().await {
+                Ok(value) => return Ok(value),
+                Err(e) if attempt >= self.retry.max_attempts => {
+                    return Err(
+                        e.wrap_err(format!("Failed to fetch {what} after {attempt} attempts"))
+                    );
+                }
+                Err(e) => {
+                    let backoff = self.retry.backoff(attempt);
+                    warn!(target: TARGET, "Failed to fetch {what} (attempt {attempt}), retrying in {backoff:?}: {e}");
+                    tokio::time::sleep(backoff).await;
+                    attempt += 1;
+                }
+            }
+        }
+    }
+
+    /// Fetches every item concurrently, and returns the results in the order of the
+    /// items. Stops at the first error.
+    pub async fn map_ordered<I, T, F, Fut>(&self, items: I, fetch: F) -> Result<Vec<T>>
+    where
+        I: IntoIterator,
+        F: FnMut(I::Item) -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        stream::iter(items)
+            .map(fetch)
+            .buffered(self.concurrency)
+            .try_collect()
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use color_eyre::eyre::eyre;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    #[test]
+    fn test_backoff() {
+        let policy = RetryPolicy {
+            max_attempts: 10,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(1),
+        };
+        assert_eq!(policy.backoff(1), Duration::from_millis(100));
+        assert_eq!(policy.backoff(2), Duration::from_millis(200));
+        assert_eq!(policy.backoff(4), Duration::from_millis(800));
+        assert_eq!(policy.backoff(5), Duration::from_secs(1));
+        assert_eq!(policy.backoff(64), Duration::from_secs(1));
+    }
+
+    #[tokio::test]
+    async fn test_call_retries() {
+        let retry = RetryPolicy {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(1),
+        };
+        let fetcher = Fetcher::new(1, None, retry);
+
+        let attempts = AtomicU32::new(0);
+        let value = fetcher
+            .call("value", || async {
+                match attempts.fetch_add(1, Ordering::SeqCst) {
+                    0 | 1 => Err(eyre!("transient")),
+                    n => Ok(n),
+                }
+            })
+            .await
+            .unwrap();
+        assert_eq!(value, 2);
+
+        let attempts = AtomicU32::new(0);
+        let result: Result<()> = fetcher
+            .call("value", || async {
+                attempts.fetch_add(1, Ordering::SeqCst);
+                Err(eyre!("permanent"))
+            })
+            .await;
+        assert!(result.is_err());
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_map_ordered() {
+        let fetcher = Fetcher::new(4, None, RetryPolicy::default());
+        let results = fetcher
+            .map_ordered(0..8u64, |i| async move {
+                tokio::time::sleep(Duration::from_millis(8 - i)).await;
+                Ok(i * 2)
+            })
+            .await
+            .unwrap();
+        assert_eq!(results, (0..8).map(|i| i * 2).collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,40 @@
+#![doc = include_str!("../README.md")]
+#![warn(
+    missing_debug_implementations,
+    missing_docs,
+    unreachable_pub,
+    rustdoc::all
+)]
+#![deny(unused_must_use, rust_2018_idioms)]
+#![cfg_attr(docsrs, feature(doc_cfg, doc_auto_cfg))]
+
+pub mod altda;
+pub use altda::{
+    AltDaCommitment, AltDaSource, DaServerClient, InputFetcher, RecordingInputFetcher,
+};
+
+pub mod beacon;
+pub use beacon::BeaconPool;
+
+pub mod channels;
+pub use channels::{
+    assemble_channels, batcher_txs, data_items, posted_frames, BatchInfo, Channel, L2BlockRef,
+    PostedFrame,
+};
+
+pub mod fetch;
+pub use fetch::{FetchArgs, Fetcher, RateLimiter, RetryPolicy};
+
+pub mod online;
+
+pub mod recorder;
+pub use recorder::{BatcherData, L2Reads, RecordingDataSource, RecordingL2Provider};
+
+pub mod rollup;
+pub use rollup::RollupConfigArgs;
+
+pub mod session;
+pub use session::{
+    stalled_exit_code, DerivationHooks, DerivationSession, DerivedBlock, StallArgs, StalledError,
+    STALLED_EXIT_CODE,
+};
@@ -0,0 +1,148 @@
+//! Providers backed by L1, L2 and beacon RPCs.
+
+use crate::beacon::BeaconPool;
+use color_eyre::{eyre::eyre, Result};
+use kona_derive::online::{
+    AlloyChainProvider, AlloyL2ChainProvider, OnlineBeaconClient,
+    OnlineBlobProvider as BeaconBlobProvider, OnlineBlobProviderBuilder,
+    OnlineBlobProviderWithFallback, SimpleSlotDerivation,
+};
+use kona_derive::traits::L2ChainProvider;
+use kona_primitives::{BlobSidecar, BlockInfo, IndexedBlobHash, L2BlockInfo, RollupConfig};
+use reqwest::Url;
+use std::sync::Arc;
+use superchain_registry::ROLLUP_CONFIGS;
+use tracing::warn;
+
+/// The logging target to use for [tracing].
+const TARGET: &str = "online";
+
+/// The blob provider backed by a pool of beacon endpoints, falling back to a blob
+/// archiver for blobs the beacon endpoints no longer serve.
+pub type OnlineBlobProvider =
+    OnlineBlobProviderWithFallback<BeaconPool, OnlineBeaconClient, SimpleSlotDerivation>;
+
+/// The providers used to derive from live L1, L2 and beacon RPCs.
+#[derive(Debug, Clone)]
+pub struct OnlineProviders {
+    /// The L1 chain provider.
+    pub l1: AlloyChainProvider,
+    /// The L2 chain provider.
+    pub l2: AlloyL2ChainProvider,
+    /// The blob provider.
+    pub blobs: OnlineBlobProvider,
+    /// The blob sidecar provider, which shares the beacon endpoints of the blob provider.
+    pub sidecars: OnlineSidecarProvider,
+}
+
+impl OnlineProviders {
+    /// Creates the providers for the given endpoints.
+    ///
+    /// Blobs are fetched from the healthiest of the beacon endpoints, see [BeaconPool].
+    /// Blobs past the retention window of the beacon endpoints are fetched from the
+    /// blob archiver, if one is given. A blob archiver serves the beacon blob sidecars
+    /// API for the whole history of the chain.
+    pub fn new(
+        cfg: Arc<RollupConfig>,
+        l1_rpc_url: Url,
+        l2_rpc_url: Url,
+        beacon_urls: Vec<String>,
+        blob_archiver_url: Option<String>,
+    ) -> Self {
+        let beacon = BeaconPool::new_http(beacon_urls);
+        let archiver = blob_archiver_url.map(OnlineBeaconClient::new_http);
+        Self {
+            l1: AlloyChainProvider::new_http(l1_rpc_url),
+            l2: AlloyL2ChainProvider::new_http(l2_rpc_url, cfg),
+            blobs: OnlineBlobProviderBuilder::new()
+                .with_beacon_client(beacon.clone())
+                .with_fallback(archiver.clone())
+                .build(),
+            sidecars: OnlineSidecarProvider::new(beacon, archiver),
+        }
+    }
+}
+
+/// Fetches blob sidecars, which carry the KZG commitment and proof of each blob, from
+/// the beacon endpoints, falling back to the blob archiver like [OnlineBlobProvider].
+#[derive(Debug, Clone)]
+pub struct OnlineSidecarProvider {
+    /// The provider backed by the beacon endpoints.
+    beacon: BeaconBlobProvider<BeaconPool, SimpleSlotDerivation>,
+    /// The provider backed by the blob archiver, if any.
+    archiver: Option<BeaconBlobProvider<OnlineBeaconClient, SimpleSlotDerivation>>,
+}
+
+impl OnlineSidecarProvider {
+    /// Creates a new [OnlineSidecarProvider].
+    pub fn new(beacon: BeaconPool, archiver: Option<OnlineBeaconClient>) -> Self {
+        Self {
+            beacon: BeaconBlobProvider::new(beacon, None, None),
+            archiver: archiver.map(|client| BeaconBlobProvider::new(client, None, None)),
+        }
+    }
+
+    /// Returns the sidecars of the blobs with the given indexed hashes in the given L1
+    /// block, ordered by blob index.
+    pub async fn sidecars(
+        &mut self,
+        block: &BlockInfo,
+        blob_hashes: &[IndexedBlobHash],
+    ) -> Result<Vec<BlobSidecar>> {
+        if blob_hashes.is_empty() {
+            return Ok(Vec::new());
+        }
+        self.beacon
+            .load_configs()
+            .await
+            .map_err(|e| eyre!("Failed to load the beacon config: {e}"))?;
+        let error = match self
+            .beacon
+            .fetch_filtered_sidecars(block, blob_hashes)
+            .await
+        {
+            Ok(sidecars) => return Ok(sidecars),
+            Err(e) => e,
+        };
+        let Some(archiver) = &mut self.archiver else {
+            return Err(eyre!("Failed to fetch blob sidecars: {error}"));
+        };
+        warn!(target: TARGET, "Failed to fetch blob sidecars of L1 block {} from the beacon endpoints, trying the blob archiver: {error}", block.number);
+        // The slot of the block is derived from the beacon genesis and slot time.
+        archiver.genesis_time = self.beacon.genesis_time;
+        archiver.slot_interval = self.beacon.slot_interval;
+        archiver
+            .fetch_filtered_sidecars(block, blob_hashes)
+            .await
+            .map_err(|e| eyre!("Failed to fetch blob sidecars from the blob archiver: {e}"))
+    }
+}
+
+/// Looks up the rollup config in the superchain registry by the chain id of the L2 RPC.
+pub async fn rollup_config(l2_rpc_url: Url) -> Result<RollupConfig> {
+    let mut l2_provider = AlloyL2ChainProvider::new_http(l2_rpc_url, Arc::new(Default::default()));
+    let l2_chain_id = l2_provider.chain_id().await.map_err(|e| eyre!(e))?;
+    let cfg = ROLLUP_CONFIGS
+        .get(&l2_chain_id)
+        .cloned()
+        .ok_or_else(|| eyre!("No rollup config found for L2 chain ID: {}", l2_chain_id))?;
+    Ok(cfg)
+}
+
+/// Returns the L2 cursor to derive the given starting L2 block from, which is its parent.
+/// Starts at the L2 genesis if the start block is before it.
+pub async fn start_cursor<L2: L2ChainProvider>(
+    l2_provider: &mut L2,
+    cfg: &RollupConfig,
+    start_block: u64,
+) -> Result<L2BlockInfo> {
+    let number = if start_block < cfg.genesis.l2.number {
+        cfg.genesis.l2.number
+    } else {
+        start_block.saturating_sub(1)
+    };
+    l2_provider
+        .l2_block_info_by_number(number)
+        .await
+        .map_err(|_| eyre!("Failed to fetch genesis L2 block info for pipeline cursor"))
+}
@@ -0,0 +1,397 @@
+//! Providers that record the L2 chain data and the batcher data read by the pipeline.
+
+use alloy_primitives::{Address, Bytes, B256};
+use anyhow::Result;
+use async_trait::async_trait;
+use kona_derive::{
+    errors::StageResult,
+    traits::{AsyncIterator, DataAvailabilityProvider, L2ChainProvider},
+};
+use kona_primitives::{
+    BlockInfo, L2BlockInfo, L2ExecutionPayloadEnvelope, RollupConfig, SystemConfig,
+};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// The L2 chain data read through a [RecordingL2Provider], keyed by L2 block number.
+#[derive(Debug, Clone, Default)]
+pub struct L2Reads {
+    /// The L2 block infos that were read.
+    pub block_infos: HashMap<u64, L2BlockInfo>,
+    /// The L2 payloads that were read.
+    pub payloads: HashMap<u64, L2ExecutionPayloadEnvelope>,
+    /// The L2 system configs that were read.
+    pub system_configs: HashMap<u64, SystemConfig>,
+}
+
+/// Wraps an [L2ChainProvider], recording every successful read.
+///
+/// Clones share the same recording, so the reads made by every stage of the pipeline
+/// end up in one place. This gives the exact set of L2 blocks that derivation depends
+/// on, such as the blocks a span batch is checked against.
+#[derive(Debug, Clone)]
+pub struct RecordingL2Provider<L2> {
+    /// The wrapped provider.
+    inner: L2,
+    /// The reads recorded so far.
+    reads: Arc<Mutex<L2Reads>>,
+}
+
+impl<L2> RecordingL2Provider<L2> {
+    /// Creates a new [RecordingL2Provider] around the given provider.
+    pub fn new(inner: L2) -> Self {
+        Self {
+            inner,
+            reads: Arc::new(Mutex::new(L2Reads::default())),
+        }
+    }
+
+    /// Returns the reads recorded since the last call, and clears them.
+    pub fn take(&self) -> L2Reads {
+        std::mem::take(&mut *self.reads.lock().expect("recording lock poisoned"))
+    }
+
+    /// Records a read.
+    fn record(&self, f: impl FnOnce(&mut L2Reads)) {
+        f(&mut self.reads.lock().expect("recording lock poisoned"));
+    }
+}
+
+#[async_trait]
+impl<L2: L2ChainProvider + Send + Sync> L2ChainProvider for RecordingL2Provider<L2> {
+    async fn l2_block_info_by_number(&mut self, number: u64) -> Result<L2BlockInfo> {
+        let info = self.inner.l2_block_info_by_number(number).await?;
+        self.record(|reads| {
+            reads.block_infos.insert(number, info);
+        });
+        Ok(info)
+    }
+
+    async fn payload_by_number(&mut self, number: u64) -> Result<L2ExecutionPayloadEnvelope> {
+        let payload = self.inner.payload_by_number(number).await?;
+        self.record(|reads| {
+            reads.payloads.insert(number, payload.clone());
+        });
+        Ok(payload)
+    }
+
+    async fn system_config_by_number(
+        &mut self,
+        number: u64,
+        rollup_config: Arc<RollupConfig>,
+    ) -> Result<SystemConfig> {
+        let system_config = self
+            .inner
+            .system_config_by_number(number, rollup_config)
+            .await?;
+        self.record(|reads| {
+            reads.system_configs.insert(number, system_config.clone());
+        });
+        Ok(system_config)
+    }
+}
+
+/// The batcher data read from an L1 block through a [RecordingDataSource].
+#[derive(Debug, Clone)]
+pub struct BatcherData {
+    /// The L1 block the data was read from.
+    pub block: BlockInfo,
+    /// The batcher address the data was filtered by.
+    pub batcher: Address,
+    /// The data items read, in order.
+    pub items: Vec<Bytes>,
+}
+
+/// Wraps a [DataAvailabilityProvider], recording the data items read from every L1
+/// block.
+///
+/// Clones share the same recording. A block that is opened again, such as after a
+/// pipeline reset, replaces its earlier recording.
+#[derive(Debug, Clone)]
+pub struct RecordingDataSource<D> {
+    /// The wrapped data source.
+    inner: D,
+    /// The data recorded so far, in the order the blocks were opened.
+    data: Arc<Mutex<Vec<BatcherData>>>,
+}
+
+impl<D> RecordingDataSource<D> {
+    /// Creates a new [RecordingDataSource] around the given data source.
+    pub fn new(inner: D) -> Self {
+        Self {
+            inner,
+            data: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    /// Returns the data recorded since the last call, and clears it.
+    pub fn take(&self) -> Vec<BatcherData> {
+        std::mem::take(&mut *self.data.lock().expect("recording lock poisoned"))
+    }
+}
+
+#[async_trait]
+impl<D> DataAvailabilityProvider for RecordingDataSource<D>
+where
+    D: DataAvailabilityProvider + Send + Sync,
+{
+    type Item = Bytes;
+    type DataIter = RecordingDataIter<D::DataIter>;
+
+    async fn open_data(
+        &self,
+        block_ref: &BlockInfo,
+        batcher_address: Address,
+    ) -> Result<Self::DataIter> {
+        let inner = self.inner.open_data(block_ref, batcher_address).await?;
+        let mut data = self.data.lock().expect("recording lock poisoned");
+        data.retain(|d| d.block.hash != block_ref.hash);
+        data.push(BatcherData {
+            block: *block_ref,
+            batcher: batcher_address,
+            items: vec![],
+        });
+        Ok(RecordingDataIter {
+            inner,
+            block: block_ref.hash,
+            data: self.data.clone(),
+        })
+    }
+}
+
+/// The data iterator of a [RecordingDataSource].
+#[derive(Debug)]
+pub struct RecordingDataIter<I> {
+    /// The data iterator of the wrapped source.
+    inner: I,
+    /// The hash of the L1 block being read.
+    block: B256,
+    /// The recording shared with the data source.
+    data: Arc<Mutex<Vec<BatcherData>>>,
+}
+
+#[async_trait]
+impl<I: AsyncIterator + Send> AsyncIterator for RecordingDataIter<I> {
+    type Item = Bytes;
+
+    async fn next(&mut self) -> StageResult<Self::Item> {
+        let item: Bytes = self.inner.next().await?.into();
+        let mut data = self.data.lock().expect("recording lock poisoned");
+        if let Some(block) = data.iter_mut().rev().find(|d| d.block.hash == self.block) {
+            block.items.push(item.clone());
+        }
+        Ok(item)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::anyhow;
+    use kona_derive::errors::StageError;
+    use kona_primitives::L2ExecutionPayload;
+    use std::collections::VecDeque;
+
+    /// The number of the last L2 block the stub provider knows about.
+    const L2_HEAD: u64 = 5;
+
+    /// An L2 provider that knows the blocks up to [L2_HEAD].
+    #[derive(Debug, Clone)]
+    struct StubL2;
+
+    impl StubL2 {
+        fn check(number: u64) -> Result<()> {
+            if number > L2_HEAD {
+                return Err(anyhow!("L2 block {number} not found"));
+            }
+            Ok(())
+        }
+    }
+
+    #[async_trait]
+    impl L2ChainProvider for StubL2 {
+        async fn l2_block_info_by_number(&mut self, number: u64) -> Result<L2BlockInfo> {
+            Self::check(number)?;
+            Ok(L2BlockInfo {
+                block_info: BlockInfo {
+                    number,
+                    ..Default::default()
+                },
+                ..Default::default()
+            })
+        }
+
+        async fn payload_by_number(&mut self, number: u64) -> Result<L2ExecutionPayloadEnvelope> {
+            Self::check(number)?;
+            Ok(L2ExecutionPayloadEnvelope {
+                parent_beacon_block_root: None,
+                execution_payload: L2ExecutionPayload {
+                    block_number: number,
+                    ..Default::default()
+                },
+            })
+        }
+
+        async fn system_config_by_number(
+            &mut self,
+            number: u64,
+            _: Arc<RollupConfig>,
+        ) -> Result<SystemConfig> {
+            Self::check(number)?;
+            Ok(SystemConfig {
+                gas_limit: number,
+                ..Default::default()
+            })
+        }
+    }
+
+    /// A data source holding the batcher data of some L1 blocks, keyed by block hash.
+    #[derive(Debug, Clone, Default)]
+    struct StubSource(HashMap<B256, Vec<Bytes>>);
+
+    #[derive(Debug)]
+    struct StubIter(VecDeque<Bytes>);
+
+    #[async_trait]
+    impl DataAvailabilityProvider for StubSource {
+        type Item = Bytes;
+        type DataIter = StubIter;
+
+        async fn open_data(&self, block_ref: &BlockInfo, _: Address) -> Result<Self::DataIter> {
+            let items = self
+                .0
+                .get(&block_ref.hash)
+                .ok_or_else(|| anyhow!("L1 block {} not found", block_ref.number))?;
+            Ok(StubIter(items.iter().cloned().collect()))
+        }
+    }
+
+    #[async_trait]
+    impl AsyncIterator for StubIter {
+        type Item = Bytes;
+
+        async fn next(&mut self) -> StageResult<Self::Item> {
+            self.0.pop_front().ok_or(StageError::Eof)
+        }
+    }
+
+    fn sorted<V>(map: &HashMap<u64, V>) -> Vec<u64> {
+        let mut keys = map.keys().copied().collect::<Vec<_>>();
+        keys.sort_unstable();
+        keys
+    }
+
+    fn l1_block(number: u64) -> BlockInfo {
+        BlockInfo {
+            hash: B256::with_last_byte(number as u8),
+            number,
+            ..Default::default()
+        }
+    }
+
+    fn items(data: &[&'static str]) -> Vec<Bytes> {
+        data.iter()
+            .map(|item| Bytes::from_static(item.as_bytes()))
+            .collect()
+    }
+
+    /// Reads every item of the iterator, until it runs out of data.
+    async fn read_all<I: AsyncIterator<Item = Bytes>>(iter: &mut I) -> Vec<Bytes> {
+        let mut items = Vec::new();
+        while let Ok(item) = iter.next().await {
+            items.push(item);
+        }
+        items
+    }
+
+    #[tokio::test]
+    async fn test_recording_l2_provider() {
+        let mut provider = RecordingL2Provider::new(StubL2);
+        let mut clone = provider.clone();
+        let cfg = Arc::new(RollupConfig::default());
+
+        clone.l2_block_info_by_number(1).await.unwrap();
+        provider.l2_block_info_by_number(2).await.unwrap();
+        provider.l2_block_info_by_number(2).await.unwrap();
+        provider.payload_by_number(3).await.unwrap();
+        clone.system_config_by_number(4, cfg.clone()).await.unwrap();
+        // Failed reads are not recorded.
+        assert!(provider.l2_block_info_by_number(9).await.is_err());
+        assert!(provider.payload_by_number(9).await.is_err());
+        assert!(provider
+            .system_config_by_number(9, cfg.clone())
+            .await
+            .is_err());
+
+        // Clones share the recording, and repeated reads are recorded once.
+        let reads = clone.take();
+        assert_eq!(sorted(&reads.block_infos), [1, 2]);
+        assert_eq!(reads.block_infos[&2].block_info.number, 2);
+        assert_eq!(sorted(&reads.payloads), [3]);
+        assert_eq!(reads.payloads[&3].execution_payload.block_number, 3);
+        assert_eq!(sorted(&reads.system_configs), [4]);
+        assert_eq!(reads.system_configs[&4].gas_limit, 4);
+
+        // Taking the reads clears them, and later reads are recorded afresh.
+        let reads = provider.take();
+        assert!(reads.block_infos.is_empty());
+        assert!(reads.payloads.is_empty());
+        assert!(reads.system_configs.is_empty());
+        provider.payload_by_number(1).await.unwrap();
+        let reads = provider.take();
+        assert!(reads.block_infos.is_empty());
+        assert_eq!(sorted(&reads.payloads), [1]);
+    }
+
+    #[tokio::test]
+    async fn test_recording_data_source() {
+        let (a, b, unknown) = (l1_block(1), l1_block(2), l1_block(3));
+        let batcher = Address::repeat_byte(0xba);
+        let stub = StubSource(HashMap::from([
+            (a.hash, items(&["a0", "a1"])),
+            (b.hash, items(&["b0"])),
+        ]));
+        let source = RecordingDataSource::new(stub);
+
+        let mut iter = source.open_data(&a, batcher).await.unwrap();
+        assert_eq!(read_all(&mut iter).await, items(&["a0", "a1"]));
+        // Reads past the end of the data are not recorded.
+        assert!(iter.next().await.is_err());
+        let mut iter = source.clone().open_data(&b, batcher).await.unwrap();
+        assert_eq!(read_all(&mut iter).await, items(&["b0"]));
+        // Blocks that fail to open are not recorded.
+        assert!(source.open_data(&unknown, batcher).await.is_err());
+
+        let data = source.take();
+        let recorded = data
+            .iter()
+            .map(|d| (d.block, d.batcher, d.items.clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            recorded,
+            [
+                (a, batcher, items(&["a0", "a1"])),
+                (b, batcher, items(&["b0"])),
+            ]
+        );
+        assert!(source.take().is_empty());
+
+        // A block opened again replaces its earlier recording, and only the items read
+        // since are recorded.
+        let mut iter = source.open_data(&a, batcher).await.unwrap();
+        assert_eq!(read_all(&mut iter).await.len(), 2);
+        let mut iter = source.open_data(&b, batcher).await.unwrap();
+        assert_eq!(read_all(&mut iter).await.len(), 1);
+        let mut iter = source.open_data(&a, batcher).await.unwrap();
+        iter.next().await.unwrap();
+        let data = source.take();
+        let recorded = data
+            .iter()
+            .map(|d| (d.block.hash, d.items.clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            recorded,
+            [(b.hash, items(&["b0"])), (a.hash, items(&["a0"]))]
+        );
+    }
+}
@@ -0,0 +1,200 @@
+//! Loading of rollup configs for chains that are not in the superchain registry.
+
+use crate::online;
+use clap::Args;
+use color_eyre::{
+    eyre::{bail, eyre},
+    Result,
+};
+use kona_primitives::RollupConfig;
+use reqwest::Url;
+use serde_json::{json, Value};
+use std::path::PathBuf;
+use superchain_registry::ROLLUP_CONFIGS;
+use tracing::{debug, info, warn};
+
+/// The logging target to use for [tracing].
+const TARGET: &str = "rollup-config";
+
+/// CLI arguments that select where the rollup config is loaded from.
+#[derive(Args, Clone, Debug, Default)]
+pub struct RollupConfigArgs {
+    /// An op-node `rollup.json` file to load the rollup config from.
+    #[arg(long, help = "op-node rollup.json file to load the rollup config from")]
+    pub rollup_config: Option<PathBuf>,
+    /// A rollup node RPC URL to fetch the rollup config from with `optimism_rollupConfig`.
+    #[arg(long, help = "Rollup node RPC url to fetch the rollup config from")]
+    pub rollup_rpc_url: Option<String>,
+}
+
+impl RollupConfigArgs {
+    /// Resolves the rollup config.
+    ///
+    /// The rollup config file takes precedence. Otherwise the config is looked up in the
+    /// superchain registry, by the given L2 chain id or the chain id of the L2 RPC. Chains
+    /// missing from the registry fall back to `optimism_rollupConfig`, sent to the rollup
+    /// node RPC if one is configured and to the L2 RPC otherwise.
+    pub async fn resolve(&self, l2_rpc_url: Url, l2_chain_id: Option<u64>) -> Result<RollupConfig> {
+        if let Some(path) = &self.rollup_config {
+            let value = serde_json::from_reader(std::fs::File::open(path)?)?;
+            let cfg = from_op_node_json(value)?;
+            info!(target: TARGET, "Loaded rollup config for chain {} from {:?}", cfg.l2_chain_id, path);
+            return Ok(cfg);
+        }
+
+        let registry = match l2_chain_id {
+            Some(id) => ROLLUP_CONFIGS
+                .get(&id)
+                .cloned()
+                .ok_or_else(|| eyre!("No rollup config found for L2 chain ID: {}", id)),
+            None => online::rollup_config(l2_rpc_url.clone()).await,
+        };
+        let registry_err = match registry {
+            Ok(cfg) => return Ok(cfg),
+            Err(e) => e,
+        };
+        debug!(target: TARGET, "{registry_err}, falling back to optimism_rollupConfig");
+
+        let rollup_rpc_url = match &self.rollup_rpc_url {
+            Some(url) => Url::parse(url).map_err(|e| eyre!(e))?,
+            None => l2_rpc_url,
+        };
+        fetch_rollup_config(rollup_rpc_url).await.map_err(|e| {
+            warn!(target: TARGET, "Failed to fetch rollup config: {e}");
+            eyre!("{registry_err}, and optimism_rollupConfig failed: {e}")
+        })
+    }
+}
+
+/// Fetches the rollup config from a rollup node with `optimism_rollupConfig`.
+pub async fn fetch_rollup_config(rollup_rpc_url: Url) -> Result<RollupConfig> {
+    let request = json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": "optimism_rollupConfig",
+        "params": [],
+    });
+    let mut response: Value = reqwest::Client::new()
+        .post(rollup_rpc_url)
+        .json(&request)
+        .send()
+        .await
+        .map_err(|e| eyre!(e))?
+        .json()
+        .await
+        .map_err(|e| eyre!(e))?;
+    if let Some(error) = response.get("error") {
+        bail!("optimism_rollupConfig returned an error: {error}");
+    }
+    let cfg = from_op_node_json(response["result"].take())?;
+    info!(target: TARGET, "Fetched rollup config for chain {} from the rollup node", cfg.l2_chain_id);
+    Ok(cfg)
+}
+
+/// Converts a rollup config in the op-node `rollup.json` format into a [RollupConfig].
+///
+/// op-node names the genesis batcher `batcherAddr`, and omits fields that are not part of
+/// its config. Omitted fields are defaulted, so that a hardfork missing from the file is
+/// never activated, even if the registry config of the same chain activates it.
+pub fn from_op_node_json(mut value: Value) -> Result<RollupConfig> {
+    if !value.is_object() {
+        bail!("Rollup config must be a JSON object");
+    }
+    if let Some(system_config) = value
+        .pointer_mut("/genesis/system_config")
+        .and_then(Value::as_object_mut)
+    {
+        if let Some(batcher) = system_config.remove("batcherAddr") {
+            system_config.insert("batcherAddress".to_string(), batcher);
+        }
+    }
+
+    let mut merged = serde_json::to_value(RollupConfig::default())?;
+    merge(&mut merged, value);
+    serde_json::from_value(merged).map_err(|e| eyre!("Invalid rollup config: {e}"))
+}
+
+/// Recursively merges the fields of `overlay` into `base`.
+fn merge(base: &mut Value, overlay: Value) {
+    match (base, overlay) {
+        (Value::Object(base), Value::Object(overlay)) => {
+            for (key, value) in overlay {
+                merge(base.entry(key).or_insert(Value::Null), value);
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_op_node_json() {
+        let value = json!({
+            "genesis": {
+                "l1": {
+                    "hash": "0x438335a20d98863a4c0c97999eb2481921ccd28553eac6f913af7c12aec04108",
+                    "number": 17422590
+                },
+                "l2": {
+                    "hash": "0xdbf6a80fef073de06add9b0d14026d6e5a86c85f6d102c36d3d8e9cf89c2afd3",
+                    "number": 105235063
+                },
+                "l2_time": 1686068903,
+                "system_config": {
+                    "batcherAddr": "0x6887246668a3b87f54deb3b94ba47a6f63f32985",
+                    "overhead": "0x00000000000000000000000000000000000000000000000000000000000000bc",
+                    "scalar": "0x00000000000000000000000000000000000000000000000000000000000a6fe0",
+                    "gasLimit": 30000000
+                }
+            },
+            "block_time": 2,
+            "max_sequencer_drift": 600,
+            "seq_window_size": 3600,
+            "channel_timeout": 300,
+            "l1_chain_id": 900,
+            "l2_chain_id": 901,
+            "regolith_time": 0,
+            "batch_inbox_address": "0xff00000000000000000000000000000000000901",
+            "deposit_contract_address": "0xbeb5fc579115071764c7423a4f12edde41f106ed",
+            "l1_system_config_address": "0x229047fed2591dbec1ef1118d64f7af3db9eb290"
+        });
+        let cfg = from_op_node_json(value).unwrap();
+        assert_eq!(cfg.l2_chain_id, 901);
+        assert_eq!(cfg.seq_window_size, 3600);
+        assert_eq!(cfg.genesis.l2.number, 105235063);
+        let system_config = cfg.genesis.system_config.unwrap();
+        assert_eq!(
+            system_config.batcher_address,
+            "0x6887246668a3b87f54deb3b94ba47a6f63f32985"
+                .parse()
+                .unwrap()
+        );
+        assert_eq!(system_config.gas_limit, 30_000_000);
+    }
+
+    #[test]
+    fn test_from_op_node_json_omitted_hardforks() {
+        // OP Mainnet activates fjord in the registry, but not in this file.
+        assert!(ROLLUP_CONFIGS[&10u64].fjord_time.is_some());
+        let value = json!({
+            "block_time": 2,
+            "l1_chain_id": 1,
+            "l2_chain_id": 10,
+            "regolith_time": 0,
+            "ecotone_time": 1710374401
+        });
+        let cfg = from_op_node_json(value).unwrap();
+        assert_eq!(cfg.ecotone_time, Some(1710374401));
+        assert_eq!(cfg.fjord_time, None);
+        assert_eq!(cfg.granite_time, None);
+        assert_eq!(cfg.seq_window_size, 0);
+    }
+
+    #[test]
+    fn test_from_op_node_json_rejects_non_objects() {
+        assert!(from_op_node_json(json!([])).is_err());
+    }
+}
@@ -0,0 +1,520 @@
+//! The derivation session that steps the pipeline and advances the L2 cursor.
+
+use crate::altda::{AltDaSource, InputFetcher};
+use crate::fetch::RetryPolicy;
+use async_trait::async_trait;
+use clap::Args;
+use color_eyre::{eyre::eyre, Report, Result};
+use kona_derive::{
+    errors::StageError,
+    pipeline::PipelineBuilder,
+    sources::EthereumDataSource,
+    stages::StatefulAttributesBuilder,
+    traits::{
+        BlobProvider, ChainProvider, DataAvailabilityProvider, L2ChainProvider, Pipeline,
+        StepResult,
+    },
+};
+use kona_primitives::{BlockInfo, L2AttributesWithParent, L2BlockInfo, RollupConfig};
+use std::fmt::{Debug, Display};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, error, trace, warn};
+
+/// The logging target to use for [tracing].
+const TARGET: &str = "driver";
+
+/// The process exit code used when derivation aborts with a [StalledError].
+pub const STALLED_EXIT_CODE: i32 = 3;
+
+/// The error returned when a [DerivationSession] stops making progress.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StalledError {
+    /// The number of the L2 safe head the session was deriving on top of.
+    pub cursor: u64,
+    /// The number of the L1 origin of the pipeline, if it has one.
+    pub l1_origin: Option<u64>,
+    /// Why the session stalled.
+    pub reason: String,
+}
+
+impl Display for StalledError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Derivation stalled at L2 block {}", self.cursor)?;
+        if let Some(l1_origin) = self.l1_origin {
+            write!(f, " with L1 origin {l1_origin}")?;
+        }
+        write!(f, ": {}", self.reason)
+    }
+}
+
+impl std::error::Error for StalledError {}
+
+/// Returns [STALLED_EXIT_CODE] if the error is a [StalledError], including one wrapped
+/// in further context, and `None` otherwise.
+pub fn stalled_exit_code(error: &Report) -> Option<i32> {
+    error
+        .downcast_ref::<StalledError>()
+        .map(|_| STALLED_EXIT_CODE)
+}
+
+/// Returns how long to wait before stepping the pipeline again after a failed step,
+/// counting the consecutive failures in `failed_steps`.
+///
+/// The pipeline reports [StageError::NotEnoughData] for most steps while it reads frames
+/// and channels, so those are stepped again right away and do not count as failures.
+fn step_backoff(retry: &RetryPolicy, error: &StageError, failed_steps: &mut u32) -> Duration {
+    if matches!(error, StageError::NotEnoughData) {
+        return Duration::ZERO;
+    }
+    *failed_steps += 1;
+    retry.backoff(*failed_steps)
+}
+
+/// CLI arguments that bound how long a [DerivationSession] waits for progress.
+#[derive(Args, Clone, Debug)]
+pub struct StallArgs {
+    /// The number of consecutive pipeline steps without progress before aborting.
+    #[arg(
+        long,
+        default_value_t = 10_000,
+        help = "Pipeline steps without progress before aborting"
+    )]
+    pub max_stalled_steps: usize,
+    /// The number of attempts made to advance the L1 origin or the L2 cursor before aborting.
+    #[arg(
+        long,
+        default_value_t = 10,
+        help = "Attempts to advance the L1 origin or L2 cursor before aborting"
+    )]
+    pub provider_attempts: u32,
+}
+
+impl Default for StallArgs {
+    fn default() -> Self {
+        Self {
+            max_stalled_steps: 10_000,
+            provider_attempts: 10,
+        }
+    }
+}
+
+impl StallArgs {
+    /// Returns the retry policy for advancing the L1 origin and the L2 cursor.
+    pub fn retry_policy(&self) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: self.provider_attempts.max(1),
+            ..Default::default()
+        }
+    }
+}
+
+/// An L2 block derived by a [DerivationSession].
+#[derive(Debug, Clone)]
+pub struct DerivedBlock {
+    /// The derived payload attributes and their parent.
+    pub attributes: L2AttributesWithParent,
+    /// The block info of the derived block, which is the new L2 cursor.
+    pub l2_block_info: L2BlockInfo,
+    /// The L1 origin of the pipeline when the block was derived. This is the last L1
+    /// block needed to derive it.
+    pub l1_origin: BlockInfo,
+}
+
+impl DerivedBlock {
+    /// Returns the number of the derived L2 block.
+    pub fn number(&self) -> u64 {
+        self.l2_block_info.block_info.number
+    }
+}
+
+/// Callbacks invoked by [DerivationSession::run].
+#[async_trait]
+pub trait DerivationHooks: Send {
+    /// Called for every derived L2 block, after the cursor has advanced to it.
+    async fn on_block(&mut self, block: DerivedBlock) -> Result<()>;
+}
+
+/// Steps the derivation pipeline and advances the L2 cursor over a range of L2 blocks.
+pub struct DerivationSession<L2> {
+    /// The derivation pipeline.
+    pipeline: Box<dyn Pipeline + Send>,
+    /// The L2 chain provider used to advance the cursor.
+    l2_provider: L2,
+    /// The L2 safe head the pipeline derives on top of.
+    cursor: L2BlockInfo,
+    /// The L2 block number to stop at, inclusive.
+    end_block: u64,
+    /// Whether to stop, rather than retry, when the L1 origin cannot be advanced or
+    /// the next L2 safe head cannot be fetched.
+    stop_on_provider_error: bool,
+    /// The retry policy for advancing the L1 origin and fetching the next L2 safe head.
+    retry: RetryPolicy,
+    /// The maximum number of consecutive steps without progress before stopping.
+    max_idle_steps: Option<usize>,
+    /// The maximum number of consecutive steps without progress before aborting with
+    /// a [StalledError].
+    max_stalled_steps: Option<usize>,
+}
+
+impl<L2> Debug for DerivationSession<L2> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DerivationSession")
+            .field("cursor", &self.cursor)
+            .field("end_block", &self.end_block)
+            .field("stop_on_provider_error", &self.stop_on_provider_error)
+            .field("retry", &self.retry)
+            .field("max_idle_steps", &self.max_idle_steps)
+            .field("max_stalled_steps", &self.max_stalled_steps)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<L2> DerivationSession<L2>
+where
+    L2: L2ChainProvider + Clone + Send + Sync + Debug + 'static,
+{
+    /// Creates a new [DerivationSession] that derives on top of the given L2 cursor,
+    /// starting the pipeline at the cursor's L1 origin.
+    pub async fn new<C, B>(
+        cfg: Arc<RollupConfig>,
+        chain_provider: C,
+        l2_provider: L2,
+        blob_provider: B,
+        cursor: L2BlockInfo,
+    ) -> Result<Self>
+    where
+        C: ChainProvider + Clone + Send + Sync + Debug + 'static,
+        B: BlobProvider + Clone + Send + Sync + Debug + 'static,
+    {
+        let dap = EthereumDataSource::new(chain_provider.clone(), blob_provider, &cfg);
+        Self::with_data_source(cfg, chain_provider, l2_provider, dap, cursor).await
+    }
+
+    /// Creates a new [DerivationSession] for an alt-DA chain, which resolves the
+    /// commitments posted to L1 through the given [InputFetcher].
+    pub async fn new_alt_da<C, B, F>(
+        cfg: Arc<RollupConfig>,
+        chain_provider: C,
+        l2_provider: L2,
+        blob_provider: B,
+        fetcher: F,
+        cursor: L2BlockInfo,
+    ) -> Result<Self>
+    where
+        C: ChainProvider + Clone + Send + Sync + Debug + 'static,
+        B: BlobProvider + Clone + Send + Sync + Debug + 'static,
+        F: InputFetcher + Clone + Send + Sync + Debug + 'static,
+    {
+        let dap = AltDaSource::new(
+            EthereumDataSource::new(chain_provider.clone(), blob_provider, &cfg),
+            fetcher,
+        );
+        Self::with_data_source(cfg, chain_provider, l2_provider, dap, cursor).await
+    }
+
+    /// Creates a new [DerivationSession] that reads L1 data through the given data
+    /// availability provider.
+    pub async fn with_data_source<C, D>(
+        cfg: Arc<RollupConfig>,
+        mut chain_provider: C,
+        l2_provider: L2,
+        dap: D,
+        cursor: L2BlockInfo,
+    ) -> Result<Self>
+    where
+        C: ChainProvider + Clone + Send + Sync + Debug + 'static,
+        D: DataAvailabilityProvider + Clone + Send + Sync + Debug + 'static,
+    {
+        let l1_tip = chain_provider
+            .block_info_by_number(cursor.l1_origin.number)
+            .await
+            .map_err(|e| eyre!("Failed to fetch L1 origin of the L2 cursor: {e}"))?;
+        let attributes = StatefulAttributesBuilder::new(
+            cfg.clone(),
+            l2_provider.clone(),
+            chain_provider.clone(),
+        );
+        let pipeline = PipelineBuilder::new()
+            .rollup_config(cfg)
+            .dap_source(dap)
+            .l2_chain_provider(l2_provider.clone())
+            .chain_provider(chain_provider)
+            .builder(attributes)
+            .origin(l1_tip)
+            .build();
+
+        Ok(Self {
+            pipeline: Box::new(pipeline),
+            l2_provider,
+            cursor,
+            end_block: u64::MAX,
+            stop_on_provider_error: false,
+            retry: RetryPolicy::default(),
+            max_idle_steps: None,
+            max_stalled_steps: None,
+        })
+    }
+
+    /// Sets the L2 block number to stop deriving at, inclusive.
+    pub fn with_end_block(mut self, end_block: u64) -> Self {
+        self.end_block = end_block;
+        self
+    }
+
+    /// Stops the session, rather than retrying, when the L1 origin cannot be advanced
+    /// or the next L2 safe head cannot be fetched. Useful for providers backed by
+    /// static data, where retrying can never succeed.
+    pub fn with_stop_on_provider_error(mut self, stop: bool) -> Self {
+        self.stop_on_provider_error = stop;
+        self
+    }
+
+    /// Sets the retry policy for advancing the L1 origin and fetching the next L2 safe
+    /// head. Once the attempts run out, the session aborts with a [StalledError]. Its
+    /// backoff is also applied between failed pipeline steps, other than those that
+    /// only lack data.
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Stops the session after the given number of consecutive pipeline steps that
+    /// neither advance the L1 origin nor derive attributes.
+    pub fn with_max_idle_steps(mut self, max_idle_steps: usize) -> Self {
+        self.max_idle_steps = Some(max_idle_steps);
+        self
+    }
+
+    /// Aborts the session with a [StalledError] after the given number of consecutive
+    /// pipeline steps that neither advance the L1 origin nor derive attributes.
+    pub fn with_max_stalled_steps(mut self, max_stalled_steps: usize) -> Self {
+        self.max_stalled_steps = Some(max_stalled_steps);
+        self
+    }
+
+    /// Applies the retry policy and stall limit of the [StallArgs].
+    pub fn with_stall_args(self, args: &StallArgs) -> Self {
+        self.with_retry_policy(args.retry_policy())
+            .with_max_stalled_steps(args.max_stalled_steps)
+    }
+
+    /// Returns the current L2 cursor.
+    pub fn cursor(&self) -> L2BlockInfo {
+        self.cursor
+    }
+
+    /// Returns the L2 chain provider.
+    pub fn l2_provider(&mut self) -> &mut L2 {
+        &mut self.l2_provider
+    }
+
+    /// Steps the pipeline until the next L2 block is derived and the cursor advanced
+    /// to it. Returns `None` once the end block is reached or the session stops.
+    pub async fn next_block(&mut self) -> Result<Option<DerivedBlock>> {
+        let mut idle_steps = 0;
+        let mut origin_errors = 0;
+        let mut failed_steps = 0;
+        loop {
+            if self.cursor.block_info.number >= self.end_block {
+                trace!(target: TARGET, "Cursor is beyond the end block, stopping");
+                return Ok(None);
+            }
+
+            match self.pipeline.step(self.cursor).await {
+                StepResult::PreparedAttributes => {
+                    trace!(target: TARGET, "Prepared attributes");
+                    failed_steps = 0;
+                }
+                StepResult::AdvancedOrigin => {
+                    trace!(target: TARGET, "Advanced origin");
+                    idle_steps = 0;
+                    origin_errors = 0;
+                    failed_steps = 0;
+                }
+                StepResult::OriginAdvanceErr(e) => {
+                    if self.stop_on_provider_error {
+                        debug!(target: TARGET, "Could not advance origin, stopping: {:?}", e);
+                        return Ok(None);
+                    }
+                    origin_errors += 1;
+                    if origin_errors >= self.retry.max_attempts {
+                        return Err(self
+                            .stalled(format!(
+                                "could not advance the L1 origin after {origin_errors} attempts: {e:?}"
+                            ))
+                            .into());
+                    }
+                    let backoff = self.retry.backoff(origin_errors);
+                    warn!(target: TARGET, "Could not advance origin, retrying in {:?}: {:?}", backoff, e);
+                    tokio::time::sleep(backoff).await;
+                }
+                StepResult::StepFailed(e) => {
+                    let backoff = step_backoff(&self.retry, &e, &mut failed_steps);
+                    match e {
+                        StageError::NotEnoughData => {
+                            debug!(target: TARGET, "Not enough data to step derivation pipeline");
+                        }
+                        _ => {
+                            error!(target: TARGET, "Error stepping derivation pipeline, retrying in {:?}: {:?}", backoff, e);
+                            tokio::time::sleep(backoff).await;
+                        }
+                    }
+                }
+            }
+
+            let Some(attributes) = self.pipeline.next() else {
+                idle_steps += 1;
+                if self.max_idle_steps.is_some_and(|max| idle_steps > max) {
+                    warn!(target: TARGET, "Derivation made no progress after {} steps, stopping", idle_steps - 1);
+                    return Ok(None);
+                }
+                if self.max_stalled_steps.is_some_and(|max| idle_steps > max) {
+                    let reason = format!("no progress after {} pipeline steps", idle_steps - 1);
+                    return Err(self.stalled(reason).into());
+                }
+                continue;
+            };
+            let l1_origin = *self
+                .pipeline
+                .origin()
+                .ok_or(eyre!("Failed to get pipeline l1 origin"))?;
+
+            let Some(l2_block_info) = self.advance_cursor().await? else {
+                return Ok(None);
+            };
+            return Ok(Some(DerivedBlock {
+                attributes,
+                l2_block_info,
+                l1_origin,
+            }));
+        }
+    }
+
+    /// Runs the session to completion, calling the hooks for every derived block.
+    pub async fn run<H: DerivationHooks>(&mut self, hooks: &mut H) -> Result<()> {
+        while let Some(block) = self.next_block().await? {
+            hooks.on_block(block).await?;
+        }
+        Ok(())
+    }
+
+    /// Advances the cursor to the next L2 safe head, retrying with backoff in case the
+    /// fetch fails. Returns `None` if the session stops on provider errors, and a
+    /// [StalledError] once the attempts run out.
+    async fn advance_cursor(&mut self) -> Result<Option<L2BlockInfo>> {
+        let next = self.cursor.block_info.number + 1;
+        let mut attempt = 1;
+        loop {
+            match self.l2_provider.l2_block_info_by_number(next).await {
+                Ok(info) => {
+                    self.cursor = info;
+                    return Ok(Some(info));
+                }
+                Err(e) if self.stop_on_provider_error => {
+                    debug!(target: TARGET, "Failed to fetch next pending l2 safe head {}, stopping: {:?}", next, e);
+                    return Ok(None);
+                }
+                Err(e) if attempt >= self.retry.max_attempts => {
+                    let reason = format!(
+                        "failed to fetch the next L2 safe head {next} after {attempt} attempts: {e:?}"
+                    );
+                    return Err(self.stalled(reason).into());
+                }
+                Err(e) => {
+                    let backoff = self.retry.backoff(attempt);
+                    error!(target: TARGET, "Failed to fetch next pending l2 safe head: {}, retrying in {:?}, err: {:?}", next, backoff, e);
+                    tokio::time::sleep(backoff).await;
+                    attempt += 1;
+                }
+            }
+        }
+    }
+
+    /// Returns a [StalledError] describing the current position of the session.
+    fn stalled(&self, reason: String) -> StalledError {
+        let error = StalledError {
+            cursor: self.cursor.block_info.number,
+            l1_origin: self.pipeline.origin().map(|origin| origin.number),
+            reason,
+        };
+        error!(target: TARGET, "{error}");
+        error
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use color_eyre::eyre::WrapErr;
+
+    fn stalled() -> StalledError {
+        StalledError {
+            cursor: 7,
+            l1_origin: Some(12),
+            reason: "no progress after 3 pipeline steps".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_stalled_error_display() {
+        assert_eq!(
+            stalled().to_string(),
+            "Derivation stalled at L2 block 7 with L1 origin 12: no progress after 3 pipeline steps"
+        );
+        let error = StalledError {
+            l1_origin: None,
+            ..stalled()
+        };
+        assert_eq!(
+            error.to_string(),
+            "Derivation stalled at L2 block 7: no progress after 3 pipeline steps"
+        );
+    }
+
+    #[test]
+    fn test_step_backoff() {
+        let retry = RetryPolicy {
+            max_attempts: 10,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(10),
+        };
+        let mut failed_steps = 0;
+        for _ in 0..10_000 {
+            assert_eq!(
+                step_backoff(&retry, &StageError::NotEnoughData, &mut failed_steps),
+                Duration::ZERO
+            );
+        }
+        assert_eq!(failed_steps, 0);
+
+        let error = StageError::Custom(anyhow::anyhow!("provider unavailable"));
+        assert_eq!(
+            step_backoff(&retry, &error, &mut failed_steps),
+            Duration::from_millis(500)
+        );
+        assert_eq!(
+            step_backoff(&retry, &error, &mut failed_steps),
+            Duration::from_secs(1)
+        );
+        assert_eq!(
+            step_backoff(&retry, &StageError::NotEnoughData, &mut failed_steps),
+            Duration::ZERO
+        );
+        assert_eq!(failed_steps, 2);
+    }
+
+    #[test]
+    fn test_stalled_exit_code() {
+        assert_eq!(
+            stalled_exit_code(&Report::new(stalled())),
+            Some(STALLED_EXIT_CODE)
+        );
+        let wrapped: Result<()> = Err(stalled()).wrap_err("Failed to derive range");
+        assert_eq!(
+            stalled_exit_code(&wrapped.unwrap_err()),
+            Some(STALLED_EXIT_CODE)
+        );
+        assert_eq!(stalled_exit_code(&eyre!("Failed to fetch block")), None);
+    }
+}
@@ -0,0 +1,303 @@
+//! Runs the entries of a manifest, skipping the ones that are up to date.
+
+use crate::state::BatchState;
+use alloy_primitives::B256;
+use clap::Args;
+use color_eyre::Result;
+use std::fmt::Display;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tracing::{error, info};
+
+/// The logging target to use for [tracing].
+const TARGET: &str = "batch";
+
+/// CLI arguments for generating the fixtures of a manifest.
+#[derive(Args, Clone, Debug)]
+pub struct BatchArgs {
+    /// The manifest listing the fixtures to generate.
+    #[arg(long, help = "Manifest listing the fixtures to generate")]
+    pub manifest: PathBuf,
+    /// Regenerate every fixture, even the ones that are up to date.
+    #[arg(
+        long,
+        help = "Regenerate every fixture, even the ones that are up to date"
+    )]
+    pub force: bool,
+}
+
+/// The outcome of a manifest entry in a batch run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Status {
+    /// The fixture was generated.
+    Generated,
+    /// The fixture was up to date, and skipped.
+    UpToDate,
+    /// Generating the fixture failed with the given error.
+    Failed(String),
+}
+
+impl Display for Status {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Generated => write!(f, "generated"),
+            Self::UpToDate => write!(f, "up to date"),
+            Self::Failed(e) => write!(f, "failed: {e}"),
+        }
+    }
+}
+
+/// A row of the summary of a batch run.
+#[derive(Debug, Clone)]
+pub struct Row {
+    /// The kind of fixture.
+    pub kind: String,
+    /// The output path, as written in the manifest.
+    pub output: PathBuf,
+    /// The outcome of the entry.
+    pub status: Status,
+    /// How long generating the fixture took.
+    pub elapsed: Duration,
+}
+
+/// The summary of a batch run, displayed as a table.
+#[derive(Debug, Clone, Default)]
+pub struct Report {
+    /// The rows of the summary, in manifest order.
+    pub rows: Vec<Row>,
+}
+
+impl Report {
+    /// Returns the number of entries that failed.
+    pub fn failures(&self) -> usize {
+        self.rows
+            .iter()
+            .filter(|row| matches!(row.status, Status::Failed(_)))
+            .count()
+    }
+}
+
+impl Display for Report {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let header = ["KIND", "OUTPUT", "TIME", "STATUS"];
+        let rows = self
+            .rows
+            .iter()
+            .map(|row| {
+                [
+                    row.kind.clone(),
+                    row.output.display().to_string(),
+                    format!("{:.1}s", row.elapsed.as_secs_f64()),
+                    row.status.to_string(),
+                ]
+            })
+            .collect::<Vec<_>>();
+        let mut widths = header.map(str::len);
+        for row in &rows {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.len());
+            }
+        }
+        let line = |f: &mut std::fmt::Formatter<'_>, cells: [&str; 4]| {
+            let [kind, output, time, status] = cells;
+            writeln!(
+                f,
+                "{kind:<w0$}  {output:<w1$}  {time:>w2$}  {status}",
+                w0 = widths[0],
+                w1 = widths[1],
+                w2 = widths[2],
+            )
+        };
+        line(f, header)?;
+        for row in &rows {
+            line(f, [&row[0], &row[1], &row[2], &row[3]].map(String::as_str))?;
+        }
+        let count = |status: fn(&Status) -> bool| {
+            self.rows.iter().filter(|row| status(&row.status)).count()
+        };
+        write!(
+            f,
+            "{} generated, {} up to date, {} failed",
+            count(|s| *s == Status::Generated),
+            count(|s| *s == Status::UpToDate),
+            self.failures()
+        )
+    }
+}
+
+/// Runs the entries of a manifest one at a time, recording the content hash of every
+/// generated output in the state file of the manifest.
+#[derive(Debug)]
+pub struct Batch {
+    /// The path of the state file.
+    state_path: PathBuf,
+    /// The state of the outputs.
+    state: BatchState,
+    /// Whether up to date entries are regenerated.
+    force: bool,
+    /// The summary of the run so far.
+    report: Report,
+}
+
+impl Batch {
+    /// Starts a batch run over the manifest at the given path.
+    pub fn new(manifest: &Path, force: bool) -> Result<Self> {
+        let state_path = BatchState::path(manifest);
+        Ok(Self {
+            state: BatchState::load(&state_path)?,
+            state_path,
+            force,
+            report: Report::default(),
+        })
+    }
+
+    /// Runs an entry, unless its output is up to date. The output is keyed by its path
+    /// as written in the manifest, and read at its resolved path.
+    ///
+    /// A failing entry is recorded in the report, and does not stop the run. The state
+    /// file is written after every generated entry, so that an interrupted run keeps
+    /// its progress.
+    pub async fn run<F, Fut>(
+        &mut self,
+        kind: &str,
+        key: &Path,
+        output: &Path,
+        input_hash: B256,
+        generate: F,
+    ) -> Result<()>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<()>>,
+    {
+        let start = Instant::now();
+        let status = if !self.force && self.state.is_up_to_date(key, output, input_hash) {
+            info!(target: TARGET, "Skipping {kind} fixture {:?}, which is up to date", key);
+            Status::UpToDate
+        } else {
+            info!(target: TARGET, "Generating {kind} fixture {:?}", key);
+            match generate().await {
+                Ok(()) => {
+                    self.state.record(key, output, input_hash)?;
+                    self.state.save(&self.state_path)?;
+                    Status::Generated
+                }
+                Err(e) => {
+                    error!(target: TARGET, "Failed to generate {kind} fixture {:?}: {e:?}", key);
+                    Status::Failed(e.to_string())
+                }
+            }
+        };
+        self.report.rows.push(Row {
+            kind: kind.to_string(),
+            output: key.to_path_buf(),
+            status,
+            elapsed: start.elapsed(),
+        });
+        Ok(())
+    }
+
+    /// Records an entry that failed before it could be run, such as one whose content
+    /// hash could not be computed.
+    pub fn fail(&mut self, kind: &str, key: &Path, error: impl Display) {
+        error!(target: TARGET, "Failed to generate {kind} fixture {:?}: {error}", key);
+        self.report.rows.push(Row {
+            kind: kind.to_string(),
+            output: key.to_path_buf(),
+            status: Status::Failed(error.to_string()),
+            elapsed: Duration::ZERO,
+        });
+    }
+
+    /// Finishes the run, and returns its summary.
+    pub fn finish(self) -> Report {
+        self.report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::keccak256;
+    use color_eyre::eyre::eyre;
+
+    #[tokio::test]
+    async fn test_batch_skips_up_to_date() {
+        let dir =
+            std::env::temp_dir().join(format!("fixture-manifest-batch-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let manifest = dir.join("fixtures.toml");
+        let output = dir.join("a.json");
+        let key = Path::new("a.json");
+        let hash = keccak256("a");
+        let output_ref = &output;
+        let generate = move || async move {
+            std::fs::write(output_ref, "{}")?;
+            Ok::<_, color_eyre::Report>(())
+        };
+
+        let mut batch = Batch::new(&manifest, false).unwrap();
+        batch
+            .run("derivation", key, &output, hash, generate)
+            .await
+            .unwrap();
+        batch
+            .run(
+                "derivation",
+                Path::new("b.json"),
+                &dir.join("b.json"),
+                hash,
+                || async { Err::<(), _>(eyre!("boom")) },
+            )
+            .await
+            .unwrap();
+        let report = batch.finish();
+        assert_eq!(report.rows[0].status, Status::Generated);
+        assert_eq!(report.rows[1].status, Status::Failed("boom".to_string()));
+        assert_eq!(report.failures(), 1);
+
+        // A new run picks up the state file, and skips the generated entry.
+        let mut batch = Batch::new(&manifest, false).unwrap();
+        batch
+            .run("derivation", key, &output, hash, generate)
+            .await
+            .unwrap();
+        assert_eq!(batch.finish().rows[0].status, Status::UpToDate);
+
+        let mut batch = Batch::new(&manifest, true).unwrap();
+        batch
+            .run("derivation", key, &output, hash, generate)
+            .await
+            .unwrap();
+        assert_eq!(batch.finish().rows[0].status, Status::Generated);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_report_table() {
+        let report = Report {
+            rows: vec![
+                Row {
+                    kind: "execution".to_string(),
+                    output: PathBuf::from("fixtures/execution/Weth9Precompile.json"),
+                    status: Status::Generated,
+                    elapsed: Duration::from_millis(1500),
+                },
+                Row {
+                    kind: "derivation".to_string(),
+                    output: PathBuf::from("a.json"),
+                    status: Status::UpToDate,
+                    elapsed: Duration::ZERO,
+                },
+            ],
+        };
+        let table = report.to_string();
+        let lines = table.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 4);
+        assert!(lines[0].starts_with("KIND        OUTPUT"));
+        assert!(lines[1].starts_with("execution   fixtures/execution/Weth9Precompile.json  1.5s"));
+        assert!(lines[2].ends_with("0.0s  up to date"));
+        assert_eq!(lines[3], "1 generated, 1 up to date, 0 failed");
+    }
+}
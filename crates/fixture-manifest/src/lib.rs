@@ -0,0 +1,18 @@
+#![doc = include_str!("../README.md")]
+#![warn(
+    missing_debug_implementations,
+    missing_docs,
+    unreachable_pub,
+    rustdoc::all
+)]
+#![deny(unused_must_use, rust_2018_idioms)]
+#![cfg_attr(docsrs, feature(doc_cfg, doc_auto_cfg))]
+
+pub mod batch;
+pub use batch::{Batch, BatchArgs, Report, Status};
+
+pub mod manifest;
+pub use manifest::{Chain, DerivationEntry, DerivationMode, ExecutionEntry, Manifest};
+
+pub mod state;
+pub use state::{BatchState, OutputState};
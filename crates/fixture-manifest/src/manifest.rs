@@ -0,0 +1,527 @@
+//! The manifest of the fixtures to generate.
+
+use alloy_primitives::{keccak256, B256};
+use color_eyre::{
+    eyre::{bail, eyre},
+    Result,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// A manifest of execution and derivation fixtures, loaded from TOML.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Manifest {
+    /// The chains derivation fixtures are generated from, by name.
+    #[serde(default)]
+    pub chains: BTreeMap<String, Chain>,
+    /// The execution fixtures generated by `opt8n`.
+    #[serde(default)]
+    pub execution: Vec<ExecutionEntry>,
+    /// The derivation fixtures generated by `opdn`.
+    #[serde(default)]
+    pub derivation: Vec<DerivationEntry>,
+    /// The directory relative paths are resolved against.
+    #[serde(skip)]
+    pub base_dir: PathBuf,
+}
+
+/// The endpoints of a chain. Endpoints may reference environment variables as `${NAME}`.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Chain {
+    /// The L1 RPC URL.
+    pub l1_rpc_url: String,
+    /// The L2 RPC URL.
+    pub l2_rpc_url: String,
+    /// The beacon client URLs.
+    #[serde(default)]
+    pub beacon_url: Vec<String>,
+    /// The blob archiver URL, if any.
+    pub blob_archiver_url: Option<String>,
+    /// The op-node `rollup.json` to load the rollup config from, if any.
+    pub rollup_config: Option<PathBuf>,
+    /// The rollup node RPC URL to fetch the rollup config from, if any.
+    pub rollup_rpc_url: Option<String>,
+    /// The DA server URL, for chains using alternative data availability.
+    pub da_server_url: Option<String>,
+}
+
+/// An execution fixture generated by running a Forge script with `opt8n`.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct ExecutionEntry {
+    /// The Forge script to run, as a contract name or a path within the project.
+    pub script: String,
+    /// The Forge project the script belongs to. Defaults to the manifest directory.
+    pub root: Option<PathBuf>,
+    /// The genesis file with the prestate, if any.
+    pub genesis: Option<PathBuf>,
+    /// The OP hardfork to execute under, if not the default.
+    pub hardfork: Option<String>,
+    /// The Regolith activation timestamp, if not genesis. Requires the hardfork.
+    pub regolith_time: Option<u64>,
+    /// The Canyon activation timestamp, if not genesis. Requires the hardfork.
+    pub canyon_time: Option<u64>,
+    /// The Ecotone activation timestamp, if not genesis. Requires the hardfork.
+    pub ecotone_time: Option<u64>,
+    /// The Fjord activation timestamp, if not genesis. Requires the hardfork.
+    pub fjord_time: Option<u64>,
+    /// The RPC URL of the chain to fork instead of using a genesis file, if any. May
+    /// reference environment variables as `${NAME}`. Requires the fork block number.
+    pub fork_url: Option<String>,
+    /// The block of the forked chain to take the prestate from.
+    pub fork_block_number: Option<u64>,
+    /// The output file.
+    pub output: PathBuf,
+    /// Extra files the fixture depends on, such as the script sources.
+    #[serde(default)]
+    pub inputs: Vec<PathBuf>,
+}
+
+/// How a derivation fixture selects its block range.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum DerivationMode {
+    /// The range is a range of L1 blocks, as in `opdn from-l1`.
+    FromL1,
+    /// The range is a range of L2 blocks, as in `opdn from-l2`.
+    FromL2,
+}
+
+impl DerivationMode {
+    /// Returns the name of the `opdn` subcommand of the mode.
+    pub fn command(&self) -> &'static str {
+        match self {
+            Self::FromL1 => "from-l1",
+            Self::FromL2 => "from-l2",
+        }
+    }
+}
+
+/// A derivation fixture generated from a block range of a chain with `opdn`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct DerivationEntry {
+    /// The name of the chain in the manifest.
+    pub chain: String,
+    /// How the block range is selected.
+    pub mode: DerivationMode,
+    /// The first block of the range.
+    pub start_block: u64,
+    /// The last block of the range.
+    pub end_block: u64,
+    /// The output file.
+    pub output: PathBuf,
+    /// Extra arguments passed to the `opdn` subcommand, such as `--kzg-proofs`.
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Extra files the fixture depends on.
+    #[serde(default)]
+    pub inputs: Vec<PathBuf>,
+}
+
+impl Manifest {
+    /// Loads the manifest at the given path, and checks that every derivation entry
+    /// refers to a declared chain.
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| eyre!("Failed to read manifest {:?}: {e}", path))?;
+        let mut manifest: Self = toml::from_str(&contents)
+            .map_err(|e| eyre!("Failed to parse manifest {:?}: {e}", path))?;
+        manifest.base_dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+        for entry in &manifest.derivation {
+            manifest.chain(&entry.chain)?;
+        }
+        Ok(manifest)
+    }
+
+    /// Resolves a path of the manifest against its directory.
+    pub fn resolve(&self, path: &Path) -> PathBuf {
+        self.base_dir.join(path)
+    }
+
+    /// Returns the chain with the given name.
+    pub fn chain(&self, name: &str) -> Result<&Chain> {
+        self.chains
+            .get(name)
+            .ok_or_else(|| eyre!("Chain {name:?} is not declared in the manifest"))
+    }
+
+    /// Hashes the given value along with the contents of the given files.
+    fn content_hash<T: Serialize>(&self, value: &T, files: &[&Path]) -> Result<B256> {
+        let mut data = serde_json::to_vec(value)?;
+        for file in files {
+            let path = self.resolve(file);
+            let contents =
+                std::fs::read(&path).map_err(|e| eyre!("Failed to read input {:?}: {e}", path))?;
+            data.extend_from_slice(file.to_string_lossy().as_bytes());
+            data.extend_from_slice(keccak256(contents).as_slice());
+        }
+        Ok(keccak256(data))
+    }
+}
+
+impl Chain {
+    /// Returns the chain with the environment variables in its endpoints expanded.
+    pub fn expand(&self) -> Result<Self> {
+        let expand_opt = |s: &Option<String>| s.as_deref().map(expand_env).transpose();
+        Ok(Self {
+            l1_rpc_url: expand_env(&self.l1_rpc_url)?,
+            l2_rpc_url: expand_env(&self.l2_rpc_url)?,
+            beacon_url: self
+                .beacon_url
+                .iter()
+                .map(|url| expand_env(url))
+                .collect::<Result<_>>()?,
+            blob_archiver_url: expand_opt(&self.blob_archiver_url)?,
+            rollup_config: self.rollup_config.clone(),
+            rollup_rpc_url: expand_opt(&self.rollup_rpc_url)?,
+            da_server_url: expand_opt(&self.da_server_url)?,
+        })
+    }
+}
+
+impl ExecutionEntry {
+    /// Returns the content hash of the entry, over its fields, the extra arguments every
+    /// `opt8n script` run gets, and the contents of the genesis file, the script if it
+    /// is a file, and the extra inputs. The fork URL is hashed before environment
+    /// variables are expanded.
+    pub fn content_hash(&self, manifest: &Manifest, extra_args: &[String]) -> Result<B256> {
+        let root = self.root.clone().unwrap_or_default();
+        let script = root.join(&self.script);
+        let mut files = self.inputs.iter().map(PathBuf::as_path).collect::<Vec<_>>();
+        files.extend(self.genesis.as_deref());
+        if manifest.resolve(&script).is_file() {
+            files.push(&script);
+        }
+        manifest.content_hash(&(self, extra_args), &files)
+    }
+
+    /// Returns the directory to run `opt8n` in, which is the Forge project.
+    pub fn dir(&self, manifest: &Manifest) -> PathBuf {
+        let dir = manifest.resolve(self.root.as_deref().unwrap_or(Path::new("")));
+        if dir.as_os_str().is_empty() {
+            PathBuf::from(".")
+        } else {
+            dir
+        }
+    }
+
+    /// Returns the arguments of the `opt8n script` subcommand that generates the
+    /// fixture, with environment variables expanded and paths resolved against the
+    /// manifest directory.
+    pub fn args(&self, manifest: &Manifest) -> Result<Vec<String>> {
+        let mut args = vec![
+            "script".to_string(),
+            "--output".to_string(),
+            absolute(&manifest.resolve(&self.output))?,
+        ];
+        if let Some(genesis) = &self.genesis {
+            args.push("--genesis".to_string());
+            args.push(absolute(&manifest.resolve(genesis))?);
+        }
+        let activation_times = [
+            ("--regolith-time", self.regolith_time),
+            ("--canyon-time", self.canyon_time),
+            ("--ecotone-time", self.ecotone_time),
+            ("--fjord-time", self.fjord_time),
+        ];
+        if let Some(hardfork) = &self.hardfork {
+            args.push("--op-hardfork".to_string());
+            args.push(hardfork.clone());
+            for (flag, time) in activation_times {
+                if let Some(time) = time {
+                    args.push(flag.to_string());
+                    args.push(time.to_string());
+                }
+            }
+        } else if activation_times.iter().any(|(_, time)| time.is_some()) {
+            bail!(
+                "Execution entry {} sets hardfork activation times without a hardfork",
+                self.script
+            );
+        }
+        match (&self.fork_url, self.fork_block_number) {
+            (Some(url), Some(block)) => {
+                args.push("--fork-url".to_string());
+                args.push(expand_env(url)?);
+                args.push("--fork-block-number".to_string());
+                args.push(block.to_string());
+            }
+            (None, None) => {}
+            _ => bail!(
+                "Execution entry {} must set both fork-url and fork-block-number, or neither",
+                self.script
+            ),
+        }
+        args.push(self.script.clone());
+        Ok(args)
+    }
+}
+
+impl DerivationEntry {
+    /// Returns the content hash of the entry, over its fields, its chain, and the
+    /// contents of the rollup config and the extra inputs. Endpoints are hashed before
+    /// environment variables are expanded.
+    pub fn content_hash(&self, manifest: &Manifest) -> Result<B256> {
+        let chain = manifest.chain(&self.chain)?;
+        let mut files = self.inputs.iter().map(PathBuf::as_path).collect::<Vec<_>>();
+        files.extend(chain.rollup_config.as_deref());
+        manifest.content_hash(&(self, chain), &files)
+    }
+
+    /// Returns the arguments of the `opdn` subcommand that generates the fixture, with
+    /// environment variables expanded and paths resolved against the manifest directory.
+    pub fn args(&self, manifest: &Manifest) -> Result<Vec<String>> {
+        let chain = manifest.chain(&self.chain)?.expand()?;
+        let mut args = vec![
+            self.mode.command().to_string(),
+            "--start-block".to_string(),
+            self.start_block.to_string(),
+            "--end-block".to_string(),
+            self.end_block.to_string(),
+            "--l1-rpc-url".to_string(),
+            chain.l1_rpc_url,
+            "--l2-rpc-url".to_string(),
+            chain.l2_rpc_url,
+            "--output".to_string(),
+            manifest
+                .resolve(&self.output)
+                .to_string_lossy()
+                .into_owned(),
+        ];
+        for url in chain.beacon_url {
+            args.push("--beacon-url".to_string());
+            args.push(url);
+        }
+        let optional = [
+            ("--blob-archiver-url", chain.blob_archiver_url),
+            (
+                "--rollup-config",
+                chain
+                    .rollup_config
+                    .map(|path| manifest.resolve(&path).to_string_lossy().into_owned()),
+            ),
+            ("--rollup-rpc-url", chain.rollup_rpc_url),
+            ("--da-server-url", chain.da_server_url),
+        ];
+        for (flag, value) in optional {
+            if let Some(value) = value {
+                args.push(flag.to_string());
+                args.push(value);
+            }
+        }
+        args.extend(self.args.iter().cloned());
+        Ok(args)
+    }
+}
+
+/// Returns the absolute form of the path, for commands run in another directory.
+fn absolute(path: &Path) -> Result<String> {
+    Ok(std::env::current_dir()?
+        .join(path)
+        .to_string_lossy()
+        .into_owned())
+}
+
+/// Expands the `${NAME}` references to environment variables in the string.
+fn expand_env(s: &str) -> Result<String> {
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(start) = rest.find("${") {
+        out.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find('}') else {
+            bail!("Unterminated environment variable reference in {s:?}");
+        };
+        let name = &rest[start + 2..start + end];
+        let value = std::env::var(name).map_err(|_| {
+            eyre!("Environment variable {name} referenced in the manifest is not set")
+        })?;
+        out.push_str(&value);
+        rest = &rest[start + end + 1..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MANIFEST: &str = r#"
+[chains.devnet]
+l1-rpc-url = "http://localhost:8545"
+l2-rpc-url = "http://localhost:9545"
+beacon-url = ["http://localhost:5052", "http://localhost:5053"]
+da-server-url = "http://localhost:3100"
+
+[[execution]]
+script = "Weth9Precompile"
+root = "examples/exec-scripts"
+hardfork = "ecotone"
+ecotone-time = 1700000000
+output = "fixtures/execution/Weth9Precompile.json"
+
+[[derivation]]
+chain = "devnet"
+mode = "from-l2"
+start-block = 10
+end-block = 20
+output = "fixtures/derivation/devnet.json"
+args = ["--kzg-proofs"]
+"#;
+
+    fn manifest() -> Manifest {
+        let mut manifest: Manifest = toml::from_str(MANIFEST).unwrap();
+        manifest.base_dir = PathBuf::from("base");
+        manifest
+    }
+
+    #[test]
+    fn test_parse_manifest() {
+        let manifest = manifest();
+        assert_eq!(manifest.execution.len(), 1);
+        assert_eq!(manifest.execution[0].hardfork.as_deref(), Some("ecotone"));
+        assert_eq!(manifest.execution[0].ecotone_time, Some(1_700_000_000));
+        assert_eq!(manifest.derivation[0].mode, DerivationMode::FromL2);
+        assert_eq!(manifest.chain("devnet").unwrap().beacon_url.len(), 2);
+        assert!(manifest.chain("mainnet").is_err());
+
+        let unknown = "[[derivation]]\nchain = \"a\"\nmode = \"from-l3\"\nstart-block = 0\nend-block = 1\noutput = \"a.json\"";
+        assert!(toml::from_str::<Manifest>(unknown).is_err());
+    }
+
+    #[test]
+    fn test_derivation_args() {
+        let manifest = manifest();
+        let args = manifest.derivation[0].args(&manifest).unwrap();
+        assert_eq!(
+            args,
+            [
+                "from-l2",
+                "--start-block",
+                "10",
+                "--end-block",
+                "20",
+                "--l1-rpc-url",
+                "http://localhost:8545",
+                "--l2-rpc-url",
+                "http://localhost:9545",
+                "--output",
+                "base/fixtures/derivation/devnet.json",
+                "--beacon-url",
+                "http://localhost:5052",
+                "--beacon-url",
+                "http://localhost:5053",
+                "--da-server-url",
+                "http://localhost:3100",
+                "--kzg-proofs",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_execution_args() {
+        let manifest = manifest();
+        let entry = &manifest.execution[0];
+        let output = absolute(Path::new("base/fixtures/execution/Weth9Precompile.json")).unwrap();
+        assert_eq!(
+            entry.args(&manifest).unwrap(),
+            [
+                "script",
+                "--output",
+                &output,
+                "--op-hardfork",
+                "ecotone",
+                "--ecotone-time",
+                "1700000000",
+                "Weth9Precompile",
+            ]
+        );
+
+        let mut entry = entry.clone();
+        entry.hardfork = None;
+        assert!(entry.args(&manifest).is_err());
+        entry.ecotone_time = None;
+        assert_eq!(
+            entry.args(&manifest).unwrap(),
+            ["script", "--output", &output, "Weth9Precompile"]
+        );
+
+        std::env::set_var("FIXTURE_MANIFEST_TEST_FORK_KEY", "secret");
+        entry.fork_url = Some("https://rpc/${FIXTURE_MANIFEST_TEST_FORK_KEY}".to_string());
+        assert!(entry.args(&manifest).is_err());
+        entry.fork_block_number = Some(120_000_000);
+        assert_eq!(
+            entry.args(&manifest).unwrap(),
+            [
+                "script",
+                "--output",
+                &output,
+                "--fork-url",
+                "https://rpc/secret",
+                "--fork-block-number",
+                "120000000",
+                "Weth9Precompile",
+            ]
+        );
+        entry.fork_url = None;
+        assert!(entry.args(&manifest).is_err());
+    }
+
+    #[test]
+    fn test_content_hash() {
+        let manifest = manifest();
+        let entry = &manifest.derivation[0];
+        let hash = entry.content_hash(&manifest).unwrap();
+        assert_eq!(hash, entry.content_hash(&manifest).unwrap());
+
+        let mut changed = manifest.clone();
+        changed.derivation[0].end_block = 21;
+        assert_ne!(hash, changed.derivation[0].content_hash(&changed).unwrap());
+
+        let mut changed = manifest.clone();
+        changed.chains.get_mut("devnet").unwrap().l2_rpc_url = "http://other".to_string();
+        assert_ne!(hash, changed.derivation[0].content_hash(&changed).unwrap());
+
+        // Missing inputs are an error rather than silently ignored.
+        let mut changed = manifest.clone();
+        changed.derivation[0]
+            .inputs
+            .push(PathBuf::from("missing.json"));
+        assert!(changed.derivation[0].content_hash(&changed).is_err());
+    }
+
+    #[test]
+    fn test_execution_content_hash() {
+        let manifest = manifest();
+        let entry = &manifest.execution[0];
+        let hash = entry.content_hash(&manifest, &[]).unwrap();
+        assert_eq!(hash, entry.content_hash(&manifest, &[]).unwrap());
+
+        let mut changed = entry.clone();
+        changed.fork_url = Some("${FIXTURE_MANIFEST_TEST_UNSET}".to_string());
+        changed.fork_block_number = Some(1);
+        let fork_hash = changed.content_hash(&manifest, &[]).unwrap();
+        assert_ne!(hash, fork_hash);
+        changed.fork_block_number = Some(2);
+        assert_ne!(fork_hash, changed.content_hash(&manifest, &[]).unwrap());
+
+        let extra_args = ["--fork-block-number".to_string(), "1".to_string()];
+        assert_ne!(hash, entry.content_hash(&manifest, &extra_args).unwrap());
+    }
+
+    #[test]
+    fn test_expand_env() {
+        std::env::set_var("FIXTURE_MANIFEST_TEST_KEY", "secret");
+        assert_eq!(
+            expand_env("https://rpc/${FIXTURE_MANIFEST_TEST_KEY}/v1").unwrap(),
+            "https://rpc/secret/v1"
+        );
+        assert_eq!(expand_env("no variables").unwrap(), "no variables");
+        assert!(expand_env("${FIXTURE_MANIFEST_TEST_UNSET}").is_err());
+        assert!(expand_env("${UNTERMINATED").is_err());
+    }
+}
@@ -0,0 +1,115 @@
+//! The state of the batch runs over a manifest.
+
+use alloy_primitives::{keccak256, B256};
+use color_eyre::{eyre::eyre, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// The content hashes each output of a manifest was last generated from, keyed by the
+/// output path as written in the manifest.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct BatchState {
+    /// The state of each generated output.
+    pub outputs: BTreeMap<PathBuf, OutputState>,
+}
+
+/// The content hashes of a generated output.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct OutputState {
+    /// The content hash of the entry the output was generated from.
+    pub input_hash: B256,
+    /// The hash of the output file, to detect outputs that were changed or removed.
+    pub output_hash: B256,
+}
+
+impl BatchState {
+    /// Returns the path of the state file of the manifest at the given path.
+    pub fn path(manifest: &Path) -> PathBuf {
+        manifest.with_extension("state.json")
+    }
+
+    /// Loads the state file at the given path. Returns an empty state if there is none.
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let file = std::fs::File::open(path)?;
+        serde_json::from_reader(std::io::BufReader::new(file))
+            .map_err(|e| eyre!("Failed to parse batch state {:?}: {e}", path))
+    }
+
+    /// Writes the state file to the given path.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let mut file = std::fs::File::create(path)?;
+        serde_json::to_writer_pretty(&mut file, self)?;
+        writeln!(file)?;
+        Ok(())
+    }
+
+    /// Returns whether the output was generated from the given content hash and has
+    /// not changed since.
+    pub fn is_up_to_date(&self, key: &Path, output: &Path, input_hash: B256) -> bool {
+        let Some(state) = self.outputs.get(key) else {
+            return false;
+        };
+        state.input_hash == input_hash
+            && std::fs::read(output).is_ok_and(|contents| keccak256(contents) == state.output_hash)
+    }
+
+    /// Records that the output was generated from the given content hash.
+    pub fn record(&mut self, key: &Path, output: &Path, input_hash: B256) -> Result<()> {
+        let contents = std::fs::read(output)
+            .map_err(|e| eyre!("Failed to read generated output {:?}: {e}", output))?;
+        self.outputs.insert(
+            key.to_path_buf(),
+            OutputState {
+                input_hash,
+                output_hash: keccak256(contents),
+            },
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_up_to_date() {
+        let dir =
+            std::env::temp_dir().join(format!("fixture-manifest-state-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let output = dir.join("fixture.json");
+        let key = Path::new("fixture.json");
+        let hash = keccak256("entry");
+
+        let mut state = BatchState::default();
+        assert!(!state.is_up_to_date(key, &output, hash));
+        assert!(state.record(key, &output, hash).is_err());
+
+        std::fs::write(&output, "{}").unwrap();
+        state.record(key, &output, hash).unwrap();
+        assert!(state.is_up_to_date(key, &output, hash));
+        assert!(!state.is_up_to_date(key, &output, keccak256("changed entry")));
+
+        // Changing or removing the output invalidates it.
+        std::fs::write(&output, "{\"edited\":true}").unwrap();
+        assert!(!state.is_up_to_date(key, &output, hash));
+        std::fs::remove_file(&output).unwrap();
+        assert!(!state.is_up_to_date(key, &output, hash));
+
+        let path = BatchState::path(&dir.join("fixtures.toml"));
+        assert_eq!(path, dir.join("fixtures.state.json"));
+        state.save(&path).unwrap();
+        assert_eq!(BatchState::load(&path).unwrap(), state);
+        assert_eq!(
+            BatchState::load(&dir.join("missing.json")).unwrap(),
+            BatchState::default()
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
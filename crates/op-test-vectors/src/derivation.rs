@@ -1,7 +1,7 @@
 //! Module containing the derivation test fixture.
 
 use alloy_consensus::{Header, Receipt};
-use alloy_primitives::Bytes;
+use alloy_primitives::{Bytes, FixedBytes};
 use hashbrown::HashMap;
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
@@ -39,6 +39,20 @@ pub struct DerivationFixture<
     /// For example, if the starting L2 cursor is 1 and the ending L2 cursor is 3,
     /// the range of L2 blocks to derive is [1, 3).
     pub l2_cursor_end: u64,
+    /// The inputs of the alt-DA commitments posted to L1, for chains using alternative
+    /// data availability. Fixtures with inputs are derived with an alt-DA data source.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub alt_da_inputs: Vec<AltDaInput>,
+}
+
+/// The input an alt-DA commitment refers to, as served by the DA server.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AltDaInput {
+    /// The encoded commitment, which is the commitment type byte followed by its payload.
+    pub commitment: Bytes,
+    /// The input data, which holds frames like the data of a calldata batcher transaction.
+    pub data: Bytes,
 }
 
 /// A fixture block is a minimal block with associated data including blobs
@@ -56,8 +70,37 @@ pub struct FixtureBlock<Blob: DeserializeOwned + Serialize> {
     pub transactions: Vec<Bytes>,
     /// Blobs for this block.
     pub blobs: Vec<Box<Blob>>,
+    /// The KZG commitments of the blobs, in the same order, if stored.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub kzg_commitments: Vec<FixedBytes<48>>,
+    /// The KZG proofs of the blobs against their commitments, in the same order, if stored.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub kzg_proofs: Vec<FixedBytes<48>>,
     /// Receipts for this block.
     pub receipts: Vec<Receipt>,
+    /// Proofs of the kept transactions and receipts, if the block is pruned.
+    /// A pruned block only contains the transactions and receipts read by derivation.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pruned: Option<PrunedBlock>,
+}
+
+/// The proofs of the transactions and receipts kept in a pruned [FixtureBlock].
+///
+/// The kept transactions and receipts are proven against the `transactionsRoot` and
+/// `receiptsRoot` of the block header by the nodes of the respective tries.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PrunedBlock {
+    /// The indices of the kept transactions in the full block.
+    pub transaction_indices: Vec<u64>,
+    /// The transactions trie nodes proving the kept transactions.
+    pub transaction_proof: Vec<Bytes>,
+    /// The indices of the kept receipts in the full block.
+    pub receipt_indices: Vec<u64>,
+    /// The EIP-2718 types of the kept receipts, needed to encode them as trie leaves.
+    pub receipt_types: Vec<u8>,
+    /// The receipts trie nodes proving the kept receipts.
+    pub receipt_proof: Vec<Bytes>,
 }
 
 #[cfg(test)]
@@ -95,6 +138,8 @@ mod tests {
                     bytes!("02f870018307c100808476d0a39c82565f94388c818ca8b9251b393131c08a736a67ccb1929787b60572b2eb6c9080c001a033bee682348fa78ffc1027bc9981e7dc60eca03af909c4eb05720e781fdae179a01ccf85367c246082fa09ef748d3b07c90752c2b59034a6b881cf99aca586eaf5"),
                 ],
                 blobs: vec![],
+                kzg_commitments: vec![],
+                kzg_proofs: vec![],
                 receipts: vec![
                     Receipt {
                         status: alloy_consensus::Eip658Value::Eip658(true),
@@ -107,6 +152,7 @@ mod tests {
                         ],
                     },
                 ],
+                pruned: None,
             },
             FixtureBlock {
                 header: Header {
@@ -132,6 +178,8 @@ mod tests {
                     bytes!("02f870018307c100808476d0a39c82565f94388c818ca8b9251b393131c08a736a67ccb1929787b60572b2eb6c9080c001a033bee682348fa78ffc1027bc9981e7dc60eca03af909c4eb05720e781fdae179a01ccf85367c246082fa09ef748d3b07c90752c2b59034a6b881cf99aca586eaf5"),
                 ],
                 blobs: vec![],
+                kzg_commitments: vec![],
+                kzg_proofs: vec![],
                 receipts: vec![
                     Receipt {
                         status: alloy_consensus::Eip658Value::Eip658(true),
@@ -144,6 +192,7 @@ mod tests {
                         ],
                     },
                 ],
+                pruned: None,
             },
             FixtureBlock {
                 header: Header {
@@ -168,6 +217,8 @@ mod tests {
                     bytes!("02f870018307c100808476d0a39c82565f94388c818ca8b9251b393131c08a736a67ccb1929787b60572b2eb6c9080c001a033bee682348fa78ffc1027bc9981e7dc60eca03af909c4eb05720e781fdae179a01ccf85367c246082fa09ef748d3b07c90752c2b59034a6b881cf99aca586eaf5"),
                 ],
                 blobs: vec![],
+                kzg_commitments: vec![],
+                kzg_proofs: vec![],
                 receipts: vec![
                     Receipt {
                         status: alloy_consensus::Eip658Value::Eip658(true),
@@ -180,6 +231,7 @@ mod tests {
                         ],
                     },
                 ],
+                pruned: None,
             },
         ]
     }
@@ -363,10 +415,40 @@ mod tests {
             ref_payloads: HashMap::new(),
             l2_cursor_start: 1,
             l2_cursor_end: 3,
+            alt_da_inputs: vec![],
         };
         assert_eq!(fixture, expected);
     }
 
+    #[test]
+    fn test_alt_da_inputs() {
+        let mut fixture = DerivationFixture::<
+            RollupConfig,
+            L2PayloadAttributes,
+            SystemConfig,
+            L2BlockInfo,
+            Blob,
+        >::default();
+        let json = serde_json::to_value(&fixture).unwrap();
+        assert!(json.get("altDaInputs").is_none());
+
+        fixture.alt_da_inputs.push(AltDaInput {
+            commitment: bytes!(
+                "00aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa"
+            ),
+            data: bytes!("00deadbeef"),
+        });
+        let json = serde_json::to_string(&fixture).unwrap();
+        let roundtrip: DerivationFixture<
+            RollupConfig,
+            L2PayloadAttributes,
+            SystemConfig,
+            L2BlockInfo,
+            Blob,
+        > = serde_json::from_str(&json).unwrap();
+        assert_eq!(roundtrip, fixture);
+    }
+
     #[test]
     fn test_fixture_block() {
         let fixture_str = include_str!("./testdata/fixture_block.json");
@@ -379,5 +461,22 @@ mod tests {
         assert_eq!(fixture.header.timestamp, 102);
         assert_eq!(fixture.transactions.len(), 1);
         assert_eq!(fixture.blobs.len(), 0);
+        assert_eq!(fixture.pruned, None);
+    }
+
+    #[test]
+    fn test_pruned_fixture_block() {
+        let mut block = ref_blocks().remove(0);
+        block.pruned = Some(PrunedBlock {
+            transaction_indices: vec![3],
+            transaction_proof: vec![bytes!("c0")],
+            receipt_indices: vec![0, 3],
+            receipt_types: vec![2, 2],
+            receipt_proof: vec![bytes!("c0")],
+        });
+        let json = serde_json::to_value(&block).unwrap();
+        assert_eq!(json["pruned"]["receiptIndices"], serde_json::json!([0, 3]));
+        let decoded: FixtureBlock<Blob> = serde_json::from_value(json).unwrap();
+        assert_eq!(decoded, block);
     }
 }
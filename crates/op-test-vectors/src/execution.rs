@@ -7,6 +7,8 @@ use op_alloy_consensus::OpTypedTransaction;
 use op_alloy_rpc_types::OpTransactionReceipt;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::fmt::Display;
+use std::str::FromStr;
 
 /// The execution fixture is the top-level object that contains
 /// everything needed to run an execution test.
@@ -48,6 +50,64 @@ pub struct ExecutionEnvironment {
     /// The block hashes of the previous blocks.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub block_hashes: Option<HashMap<U256, B256>>,
+    /// The OP hardfork active for the current block, if one was selected.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hardfork: Option<OpHardfork>,
+}
+
+/// An OP Stack hardfork that changes execution, ordered by activation.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum OpHardfork {
+    /// The initial OP Stack execution rules.
+    Bedrock,
+    /// Regolith, which changed deposit transaction processing.
+    Regolith,
+    /// Canyon, which activated Shanghai.
+    Canyon,
+    /// Ecotone, which activated Cancun and blob based L1 fees.
+    Ecotone,
+    /// Fjord, which activated RIP-7212 and FastLZ based L1 fees.
+    Fjord,
+}
+
+impl OpHardfork {
+    /// Every hardfork, in activation order.
+    pub const ALL: [Self; 5] = [
+        Self::Bedrock,
+        Self::Regolith,
+        Self::Canyon,
+        Self::Ecotone,
+        Self::Fjord,
+    ];
+
+    /// Returns the lowercase name of the hardfork.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Bedrock => "bedrock",
+            Self::Regolith => "regolith",
+            Self::Canyon => "canyon",
+            Self::Ecotone => "ecotone",
+            Self::Fjord => "fjord",
+        }
+    }
+}
+
+impl Display for OpHardfork {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for OpHardfork {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|fork| fork.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| format!("Unknown OP hardfork: {s}"))
+    }
 }
 
 /// The execution result is the expected result after running the transactions
@@ -85,6 +145,20 @@ mod tests {
         assert_eq!(serialized_value, expected_value);
     }
 
+    #[test]
+    fn test_op_hardfork_names() {
+        for fork in OpHardfork::ALL {
+            assert_eq!(fork.name().parse::<OpHardfork>(), Ok(fork));
+            assert_eq!(
+                serde_json::to_string(&fork).unwrap(),
+                format!("\"{}\"", fork.name())
+            );
+        }
+        assert_eq!("Ecotone".parse::<OpHardfork>(), Ok(OpHardfork::Ecotone));
+        assert!("cancun".parse::<OpHardfork>().is_err());
+        assert!(OpHardfork::Regolith < OpHardfork::Fjord);
+    }
+
     #[test]
     fn test_serialize_execution_result() {
         let expected_result = include_str!("./testdata/result.json");
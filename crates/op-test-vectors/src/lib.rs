@@ -11,3 +11,5 @@
 pub mod derivation;
 
 pub mod execution;
+
+pub mod system_config;
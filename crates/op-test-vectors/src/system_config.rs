@@ -0,0 +1,99 @@
+//! Module containing the system config update test fixture.
+
+use alloy_primitives::Log;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+/// The system config update fixture is the top-level object that contains
+/// everything needed to test how `ConfigUpdate` logs emitted by the L1
+/// `SystemConfig` contract are applied to a system config.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, Eq, PartialEq)]
+#[serde(
+    bound = "RollupConfig: Serialize + DeserializeOwned, SystemConfig: Serialize + DeserializeOwned"
+)]
+#[serde(rename_all = "camelCase")]
+pub struct SystemConfigUpdateFixture<
+    RollupConfig: DeserializeOwned + Serialize,
+    SystemConfig: DeserializeOwned + Serialize,
+> {
+    /// The rollup config.
+    /// Provides the L1 system config address and the hardfork activation times
+    /// that determine how each update is interpreted.
+    pub rollup_config: RollupConfig,
+    /// The ordered list of system config updates.
+    pub updates: Vec<SystemConfigUpdate<SystemConfig>>,
+}
+
+/// A single system config transition caused by one `ConfigUpdate` log.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, Eq, PartialEq)]
+#[serde(bound = "SystemConfig: Serialize + DeserializeOwned")]
+#[serde(rename_all = "camelCase")]
+pub struct SystemConfigUpdate<SystemConfig: DeserializeOwned + Serialize> {
+    /// A short, human readable description of the update.
+    pub name: String,
+    /// The L1 block number the log was emitted in.
+    pub l1_block_number: u64,
+    /// The L1 block timestamp, used to select hardfork-specific decoding.
+    pub l1_timestamp: u64,
+    /// The raw log, including the emitting address, topics and data.
+    pub log: Log,
+    /// The system config before the log is applied.
+    pub pre: SystemConfig,
+    /// The expected system config after the log is applied.
+    /// `None` if the log is malformed and must be rejected.
+    pub post: Option<SystemConfig>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::{address, b256, uint};
+    use kona_primitives::{RollupConfig, SystemConfig};
+
+    fn ref_system_config() -> SystemConfig {
+        SystemConfig {
+            batcher_address: address!("3333333333333333333333333333333333333333"),
+            overhead: uint!(8_U256),
+            scalar: uint!(7_U256),
+            gas_limit: 30000000,
+            base_fee_scalar: None,
+            blob_base_fee_scalar: None,
+        }
+    }
+
+    #[test]
+    fn test_system_config_update_fixture() {
+        let fixture_str = include_str!("./testdata/system_config_update.json");
+        let fixture: SystemConfigUpdateFixture<RollupConfig, SystemConfig> =
+            serde_json::from_str(fixture_str).unwrap();
+        assert_eq!(fixture.rollup_config, RollupConfig::default());
+        assert_eq!(fixture.updates.len(), 3);
+
+        let batcher = &fixture.updates[0];
+        assert_eq!(batcher.name, "batcher");
+        assert_eq!(batcher.l1_block_number, 1);
+        assert_eq!(batcher.l1_timestamp, 102);
+        assert_eq!(batcher.log.topics().len(), 3);
+        assert_eq!(
+            batcher.log.topics()[0],
+            b256!("1d2b0bda21d56b8bd12d4f94ebacffdfb35f5e226f84b461103bb8beab6353be")
+        );
+        assert_eq!(batcher.pre, ref_system_config());
+        let expected = SystemConfig {
+            batcher_address: address!("4444444444444444444444444444444444444444"),
+            ..ref_system_config()
+        };
+        assert_eq!(batcher.post, Some(expected));
+
+        let gas_limit = &fixture.updates[1];
+        let expected = SystemConfig {
+            batcher_address: address!("4444444444444444444444444444444444444444"),
+            gas_limit: 60000000,
+            ..ref_system_config()
+        };
+        assert_eq!(gas_limit.post, Some(expected));
+
+        let malformed = &fixture.updates[2];
+        assert_eq!(malformed.name, "batcher: unsupported version");
+        assert_eq!(malformed.post, None);
+    }
+}
@@ -0,0 +1,116 @@
+#![doc = include_str!("../README.md")]
+#![warn(
+    missing_debug_implementations,
+    missing_docs,
+    unreachable_pub,
+    rustdoc::all
+)]
+#![deny(unused_must_use, rust_2018_idioms)]
+#![cfg_attr(docsrs, feature(doc_cfg, doc_auto_cfg))]
+
+use clap::Args;
+use std::path::PathBuf;
+
+mod proxy;
+
+pub mod recording;
+
+/// Whether the cache forwards requests to the network or serves recorded responses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheMode {
+    /// Forwards every request upstream and records the response.
+    Record,
+    /// Serves every request from the recorded responses, without network access.
+    Replay,
+}
+
+/// A directory of recorded responses, served through local HTTP proxies.
+#[derive(Debug, Clone)]
+pub struct RpcCache {
+    /// The directory holding the recorded responses.
+    dir: PathBuf,
+    /// The cache mode.
+    mode: CacheMode,
+}
+
+impl RpcCache {
+    /// Creates a new [RpcCache] over the given directory.
+    pub fn new(dir: impl Into<PathBuf>, mode: CacheMode) -> Self {
+        Self {
+            dir: dir.into(),
+            mode,
+        }
+    }
+
+    /// Returns the cache mode.
+    pub fn mode(&self) -> CacheMode {
+        self.mode
+    }
+
+    /// Starts a local proxy in front of the given upstream endpoint and returns its
+    /// base URL, without a trailing slash.
+    ///
+    /// Recordings are stored under a sub-directory with the given name, so that
+    /// identical requests to different endpoints do not collide. In replay mode the
+    /// upstream endpoint is never contacted.
+    pub async fn proxy(&self, name: &str, upstream: &str) -> color_eyre::Result<String> {
+        proxy::spawn(self.dir.join(name), upstream.to_string(), self.mode).await
+    }
+}
+
+/// CLI arguments to route the RPC endpoints of a command through an [RpcCache].
+#[derive(Args, Clone, Debug, Default)]
+pub struct RpcCacheArgs {
+    /// Record every RPC and beacon response to the given directory.
+    #[arg(
+        long,
+        conflicts_with = "replay",
+        help = "Record RPC and beacon responses to a directory"
+    )]
+    pub record: Option<PathBuf>,
+    /// Replay RPC and beacon responses from the given directory, without network access.
+    #[arg(
+        long,
+        help = "Replay recorded RPC and beacon responses from a directory"
+    )]
+    pub replay: Option<PathBuf>,
+}
+
+impl RpcCacheArgs {
+    /// Returns the configured [RpcCache], if any.
+    pub fn cache(&self) -> Option<RpcCache> {
+        match (&self.record, &self.replay) {
+            (Some(dir), _) => Some(RpcCache::new(dir, CacheMode::Record)),
+            (_, Some(dir)) => Some(RpcCache::new(dir, CacheMode::Replay)),
+            _ => None,
+        }
+    }
+
+    /// Routes the given endpoint through the configured cache. Returns the endpoint
+    /// unchanged if no cache is configured.
+    pub async fn route(&self, name: &str, upstream: &str) -> color_eyre::Result<String> {
+        match self.cache() {
+            Some(cache) => cache.proxy(name, upstream).await,
+            None => Ok(upstream.to_string()),
+        }
+    }
+
+    /// Routes several endpoints of the same kind through the configured cache. The
+    /// first endpoint is recorded under the given name, and the others under the name
+    /// suffixed with their index.
+    pub async fn route_all(
+        &self,
+        name: &str,
+        upstreams: &[String],
+    ) -> color_eyre::Result<Vec<String>> {
+        let mut routed = Vec::with_capacity(upstreams.len());
+        for (i, upstream) in upstreams.iter().enumerate() {
+            let name = match i {
+                0 => name.to_string(),
+                i => format!("{name}-{i}"),
+            };
+            routed.push(self.route(&name, upstream).await?);
+        }
+        Ok(routed)
+    }
+}
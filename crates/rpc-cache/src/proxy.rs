@@ -0,0 +1,152 @@
+//! The local HTTP proxy that records and replays responses.
+
+use crate::recording::{
+    has_rpc_error, normalize_request, request_ids, request_key, rewrite_ids, Recording,
+};
+use crate::CacheMode;
+use axum::body::Bytes;
+use axum::extract::State;
+use axum::http::{header::CONTENT_TYPE, Method, StatusCode, Uri};
+use axum::response::{IntoResponse, Response};
+use color_eyre::{
+    eyre::{ensure, eyre},
+    Result,
+};
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::net::TcpListener;
+use tracing::{debug, error, trace, warn};
+
+/// The logging target to use for [tracing].
+const TARGET: &str = "rpc-cache";
+
+/// The state shared by the requests to a single proxy.
+#[derive(Debug)]
+struct ProxyState {
+    /// The HTTP client used to reach the upstream endpoint.
+    client: reqwest::Client,
+    /// The upstream endpoint.
+    upstream: String,
+    /// The directory holding the recordings for this endpoint.
+    dir: PathBuf,
+    /// The cache mode.
+    mode: CacheMode,
+}
+
+/// Spawns a proxy for the upstream endpoint on a local port and returns its base URL.
+pub(crate) async fn spawn(dir: PathBuf, upstream: String, mode: CacheMode) -> Result<String> {
+    match mode {
+        CacheMode::Record => tokio::fs::create_dir_all(&dir).await?,
+        CacheMode::Replay => ensure!(dir.is_dir(), "No recordings found in {:?}", dir),
+    }
+    let state = Arc::new(ProxyState {
+        client: reqwest::Client::new(),
+        upstream,
+        dir,
+        mode,
+    });
+    let router = axum::Router::new()
+        .fallback(handle)
+        .with_state(state.clone());
+
+    let addr: SocketAddr = ([127, 0, 0, 1], 0).into();
+    let listener = TcpListener::bind(addr).await?;
+    let local_addr = listener.local_addr()?;
+    tokio::spawn(async move {
+        if let Err(e) = axum::serve(listener, router.into_make_service()).await {
+            error!(target: TARGET, "RPC cache proxy stopped: {e}");
+        }
+    });
+    debug!(target: TARGET, "Proxying {} ({:?}) on {}", state.upstream, state.mode, local_addr);
+
+    Ok(format!("http://{local_addr}"))
+}
+
+/// Handles a single request, either forwarding and recording it or replaying it.
+async fn handle(
+    State(state): State<Arc<ProxyState>>,
+    method: Method,
+    uri: Uri,
+    body: Bytes,
+) -> Response {
+    let path = uri
+        .path_and_query()
+        .map(|p| p.as_str().to_string())
+        .unwrap_or_else(|| "/".to_string());
+    let request = normalize_request(&body);
+    let key = request_key(method.as_str(), &path, request.as_ref());
+    let file = state.dir.join(format!("{key}.json"));
+    trace!(target: TARGET, "{} {} -> {:?}", method, path, file);
+
+    let result = match state.mode {
+        CacheMode::Record => record(&state, method, path, request, body, file).await,
+        CacheMode::Replay => replay(&body, file).await,
+    };
+    match result {
+        Ok((status, response)) => {
+            let status = StatusCode::from_u16(status).unwrap_or(StatusCode::OK);
+            (status, [(CONTENT_TYPE, "application/json")], response).into_response()
+        }
+        Err(e) => {
+            warn!(target: TARGET, "RPC cache request failed: {e}");
+            (StatusCode::BAD_GATEWAY, e.to_string()).into_response()
+        }
+    }
+}
+
+/// Forwards the request upstream, and records the response if it succeeded without a
+/// JSON-RPC error.
+async fn record(
+    state: &ProxyState,
+    method: Method,
+    path: String,
+    request: Option<serde_json::Value>,
+    body: Bytes,
+    file: PathBuf,
+) -> Result<(u16, String)> {
+    let url = if path == "/" {
+        state.upstream.clone()
+    } else {
+        format!("{}{}", state.upstream.trim_end_matches('/'), path)
+    };
+    let ids = request_ids(&body);
+    let mut upstream = state.client.request(method.clone(), url);
+    if !body.is_empty() {
+        upstream = upstream.header(CONTENT_TYPE, "application/json").body(body);
+    }
+    let res = upstream.send().await.map_err(|e| eyre!(e))?;
+    let status = res.status().as_u16();
+    let response = res.text().await.map_err(|e| eyre!(e))?;
+
+    // Failed requests are not recorded so that transient errors are not replayed. Nodes
+    // answer failed JSON-RPC calls, such as rate limited ones, with a 200 status.
+    if !(200..300).contains(&status) {
+        debug!(target: TARGET, "Not recording response with status {}", status);
+    } else if has_rpc_error(&response) {
+        debug!(target: TARGET, "Not recording JSON-RPC error response");
+    } else {
+        let recording = Recording {
+            method: method.to_string(),
+            path,
+            request,
+            ids,
+            status,
+            response: response.clone(),
+        };
+        tokio::fs::write(&file, serde_json::to_vec(&recording)?).await?;
+    }
+    Ok((status, response))
+}
+
+/// Serves the recorded response to the request.
+async fn replay(body: &[u8], file: PathBuf) -> Result<(u16, String)> {
+    let recording = tokio::fs::read(&file)
+        .await
+        .map_err(|_| eyre!("No recording found for request at {:?}", file))?;
+    let recording: Recording = serde_json::from_slice(&recording)?;
+    Ok((
+        recording.status,
+        rewrite_ids(body, &recording.ids, &recording.response),
+    ))
+}
@@ -0,0 +1,205 @@
+//! Recorded responses and the keys they are stored under.
+
+use alloy_primitives::keccak256;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// A recorded response to a single HTTP request.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct Recording {
+    /// The HTTP method of the request.
+    pub method: String,
+    /// The path and query of the request.
+    pub path: String,
+    /// The request body, with JSON-RPC ids removed.
+    pub request: Option<Value>,
+    /// The JSON-RPC ids of the recorded request, in call order.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ids: Vec<Value>,
+    /// The HTTP status of the response.
+    pub status: u16,
+    /// The response body.
+    pub response: String,
+}
+
+/// Parses a request body as JSON, removing the JSON-RPC ids so that identical calls
+/// made with different ids share a recording.
+pub fn normalize_request(body: &[u8]) -> Option<Value> {
+    let mut value = serde_json::from_slice::<Value>(body).ok()?;
+    match &mut value {
+        Value::Object(call) => {
+            call.remove("id");
+        }
+        Value::Array(calls) => {
+            for call in calls.iter_mut().filter_map(Value::as_object_mut) {
+                call.remove("id");
+            }
+        }
+        _ => {}
+    }
+    Some(value)
+}
+
+/// Returns the JSON-RPC ids of the calls in a request body, in call order.
+pub fn request_ids(body: &[u8]) -> Vec<Value> {
+    match serde_json::from_slice::<Value>(body) {
+        Ok(Value::Object(call)) => call.get("id").cloned().into_iter().collect(),
+        Ok(Value::Array(calls)) => calls
+            .iter()
+            .map(|call| call.get("id").cloned().unwrap_or_default())
+            .collect(),
+        _ => vec![],
+    }
+}
+
+/// Returns the key a request is recorded under, as a hex string.
+pub fn request_key(method: &str, path: &str, request: Option<&Value>) -> String {
+    let request = request.map(Value::to_string).unwrap_or_default();
+    let key = keccak256(format!("{method} {path} {request}"));
+    alloy_primitives::hex::encode(key)
+}
+
+/// Returns whether a response body carries a JSON-RPC error, in any reply of a batch.
+/// Responses that are not JSON-RPC carry no error.
+pub fn has_rpc_error(response: &str) -> bool {
+    let is_error = |reply: &Value| reply.get("error").is_some();
+    match serde_json::from_str::<Value>(response) {
+        Ok(reply @ Value::Object(_)) => is_error(&reply),
+        Ok(Value::Array(replies)) => replies.iter().any(is_error),
+        _ => false,
+    }
+}
+
+/// Replaces the JSON-RPC ids in a recorded response with the ids of the request
+/// being replayed. Responses that are not JSON-RPC are returned unchanged.
+///
+/// Servers may answer a batch in any order, so every reply of a batch is matched to
+/// its call by looking up the reply's id in the `recorded_ids` of the recorded
+/// request. Replies with an unknown id are left unchanged.
+pub fn rewrite_ids(request: &[u8], recorded_ids: &[Value], response: &str) -> String {
+    let Ok(request) = serde_json::from_slice::<Value>(request) else {
+        return response.to_string();
+    };
+    let Ok(mut response_value) = serde_json::from_str::<Value>(response) else {
+        return response.to_string();
+    };
+    match (&request, &mut response_value) {
+        (Value::Object(call), Value::Object(reply)) => {
+            if let Some(id) = call.get("id") {
+                reply.insert("id".to_string(), id.clone());
+            }
+        }
+        (Value::Array(calls), Value::Array(replies)) => {
+            for reply in replies.iter_mut().filter_map(Value::as_object_mut) {
+                let id = reply
+                    .get("id")
+                    .and_then(|id| recorded_ids.iter().position(|recorded| recorded == id))
+                    .and_then(|index| calls.get(index))
+                    .and_then(|call| call.get("id"));
+                if let Some(id) = id {
+                    reply.insert("id".to_string(), id.clone());
+                }
+            }
+        }
+        _ => return response.to_string(),
+    }
+    response_value.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_key_ignores_ids() {
+        let a =
+            normalize_request(br#"{"jsonrpc":"2.0","id":1,"method":"eth_chainId","params":[]}"#);
+        let b =
+            normalize_request(br#"{"jsonrpc":"2.0","id":7,"method":"eth_chainId","params":[]}"#);
+        let c = normalize_request(
+            br#"{"jsonrpc":"2.0","id":7,"method":"eth_blockNumber","params":[]}"#,
+        );
+        assert_eq!(
+            request_key("POST", "/", a.as_ref()),
+            request_key("POST", "/", b.as_ref())
+        );
+        assert_ne!(
+            request_key("POST", "/", a.as_ref()),
+            request_key("POST", "/", c.as_ref())
+        );
+        assert_ne!(
+            request_key("POST", "/", a.as_ref()),
+            request_key("GET", "/", a.as_ref())
+        );
+    }
+
+    #[test]
+    fn test_has_rpc_error() {
+        assert!(!has_rpc_error(r#"{"jsonrpc":"2.0","id":1,"result":"0xa"}"#));
+        assert!(has_rpc_error(
+            r#"{"jsonrpc":"2.0","id":1,"error":{"code":-32005,"message":"rate limited"}}"#
+        ));
+        assert!(!has_rpc_error(
+            r#"[{"id":0,"result":"0x1"},{"id":1,"result":"0x2"}]"#
+        ));
+        assert!(has_rpc_error(
+            r#"[{"id":0,"result":"0x1"},{"id":1,"error":{"code":-32000,"message":"header not found"}}]"#
+        ));
+        assert!(!has_rpc_error(r#"{"data":{"root":"0x00"}}"#));
+        assert!(!has_rpc_error("not json"));
+    }
+
+    #[test]
+    fn test_rewrite_ids() {
+        let response = rewrite_ids(
+            br#"{"jsonrpc":"2.0","id":42,"method":"eth_chainId","params":[]}"#,
+            &[1.into()],
+            r#"{"jsonrpc":"2.0","id":1,"result":"0xa"}"#,
+        );
+        let response: Value = serde_json::from_str(&response).unwrap();
+        assert_eq!(response["id"], 42);
+        assert_eq!(response["result"], "0xa");
+
+        let batch = rewrite_ids(
+            br#"[{"id":3,"method":"a"},{"id":4,"method":"b"}]"#,
+            &[0.into(), 1.into()],
+            r#"[{"id":0,"result":"0x1"},{"id":1,"result":"0x2"}]"#,
+        );
+        let batch: Value = serde_json::from_str(&batch).unwrap();
+        assert_eq!(batch[0]["id"], 3);
+        assert_eq!(batch[1]["id"], 4);
+
+        assert_eq!(rewrite_ids(b"", &[], "not json"), "not json");
+    }
+
+    #[test]
+    fn test_rewrite_ids_reordered_batch() {
+        let recorded =
+            br#"[{"id":10,"method":"a"},{"id":"b","method":"b"},{"id":12,"method":"c"}]"#;
+        let recorded_ids = request_ids(recorded);
+        assert_eq!(recorded_ids, [Value::from(10), "b".into(), 12.into()]);
+
+        let batch = rewrite_ids(
+            br#"[{"id":3,"method":"a"},{"id":4,"method":"b"},{"id":5,"method":"c"}]"#,
+            &recorded_ids,
+            r#"[{"id":12,"result":"0xc"},{"id":10,"result":"0xa"},{"id":99,"result":"0x0"},{"id":"b","result":"0xb"}]"#,
+        );
+        let batch: Value = serde_json::from_str(&batch).unwrap();
+        let replies = batch
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|reply| (reply["id"].clone(), reply["result"].clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            replies,
+            [
+                (5.into(), "0xc".into()),
+                (3.into(), "0xa".into()),
+                (99.into(), "0x0".into()),
+                (4.into(), "0xb".into()),
+            ]
+        );
+    }
+}